@@ -0,0 +1,143 @@
+// -*- coding: utf-8 -*-
+//! Tree-of-Thought Beam Search
+//!
+//! Reasoning that expands several candidate inference steps from each
+//! branch, scores the branches and keeps the best `beam_width` at every
+//! depth. The pruned branches are kept for inspection.
+
+use crate::error::Result;
+use crate::level4::agents::classification::QueryType;
+use crate::level4::agents::reasoning::{ReasoningChain, ReasoningStep};
+use crate::level4::agents::reasoning_pipeline::StepContext;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Source of alternative next thoughts
+#[async_trait]
+pub trait ThoughtExpander: Send + Sync {
+    /// Up to `n` candidate steps continuing the branch in `context`; none
+    /// when the branch is complete
+    ///
+    /// Step ids are assigned by the search.
+    async fn expand(&self, context: &StepContext<'_>, n: usize) -> Result<Vec<ReasoningStep>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BeamSearchConfig {
+    /// Branches kept at each depth; at least one is
+    pub beam_width: usize,
+    /// Candidates asked for from each branch; at least one is
+    pub branching_factor: usize,
+    /// Rounds of expansion
+    pub max_depth: usize,
+}
+
+impl Default for BeamSearchConfig {
+    fn default() -> Self {
+        Self {
+            beam_width: 3,
+            branching_factor: 3,
+            max_depth: 3,
+        }
+    }
+}
+
+/// Partial chain explored by the search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThoughtBranch {
+    pub steps: Vec<ReasoningStep>,
+    /// Mean confidence of the steps
+    pub score: f64,
+    /// Expansion round in which the branch fell out of the beam; `0` for
+    /// branches still in the final beam that lost to the winner
+    pub pruned_at_depth: usize,
+}
+
+impl ThoughtBranch {
+    fn new(steps: Vec<ReasoningStep>) -> Self {
+        let score = steps.iter().map(|s| s.confidence).sum::<f64>() / steps.len().max(1) as f64;
+        Self {
+            steps,
+            score,
+            pruned_at_depth: 0,
+        }
+    }
+
+    fn output(&self) -> &str {
+        self.steps.last().map_or("", |s| s.output.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeamSearchResult {
+    /// The winning branch, finished like a regular chain
+    pub chain: ReasoningChain,
+    /// Score of the winning branch before it was finished
+    pub score: f64,
+    /// Every other branch explored, best first within each depth
+    pub pruned: Vec<ThoughtBranch>,
+}
+
+/// What the search is extending
+pub(crate) struct SearchInput<'a> {
+    pub(crate) query: &'a str,
+    pub(crate) query_type: &'a QueryType,
+    pub(crate) response_language: &'a str,
+    /// Steps every branch starts from, e.g. retrieval
+    pub(crate) root: Vec<ReasoningStep>,
+}
+
+/// Best branch grown from `input.root`, and the branches pruned on the way
+pub(crate) async fn search(
+    expander: &dyn ThoughtExpander,
+    config: &BeamSearchConfig,
+    input: SearchInput<'_>,
+) -> Result<(ThoughtBranch, Vec<ThoughtBranch>)> {
+    let beam_width = config.beam_width.max(1);
+    let branching_factor = config.branching_factor.max(1);
+    let mut beam = vec![ThoughtBranch::new(input.root)];
+    let mut pruned = Vec::new();
+
+    for depth in 1..=config.max_depth {
+        let mut candidates = Vec::new();
+        let mut expanded = false;
+        for branch in beam {
+            let context = StepContext {
+                query: input.query,
+                query_type: input.query_type,
+                input: branch.output(),
+                steps: &branch.steps,
+                response_language: input.response_language,
+            };
+            let thoughts = expander.expand(&context, branching_factor).await?;
+            if thoughts.is_empty() {
+                // Complete branches compete as they are
+                candidates.push(branch);
+                continue;
+            }
+            expanded = true;
+            for thought in thoughts.into_iter().take(branching_factor) {
+                let mut steps = branch.steps.clone();
+                steps.push(ReasoningStep { step_id: steps.len(), ..thought });
+                candidates.push(ThoughtBranch::new(steps));
+            }
+        }
+
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        if candidates.len() > beam_width {
+            pruned.extend(candidates.split_off(beam_width).into_iter().map(|branch| ThoughtBranch {
+                pruned_at_depth: depth,
+                ..branch
+            }));
+        }
+        beam = candidates;
+        if !expanded {
+            break;
+        }
+    }
+
+    let mut finalists = beam.into_iter();
+    let winner = finalists.next().expect("the beam always holds a branch");
+    pruned.extend(finalists);
+    Ok((winner, pruned))
+}
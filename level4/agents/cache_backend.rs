@@ -0,0 +1,930 @@
+// -*- coding: utf-8 -*-
+//! Cache Storage Backends
+//! 
+//! Storage abstraction behind `VertexCentricCache`, with an in-memory map
+//! (default) and a Redis backend for sharing vertex computations across workers.
+
+use crate::error::{Error, Result};
+use crate::level4::agents::cache_manager::CacheEntry;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::RwLock;
+
+/// Storage for cache entries and the vertex -> cache key index
+///
+/// Backends only store data; admission, eviction, TTL, and statistics are
+/// handled by `VertexCentricCache` so they behave the same on every backend.
+#[async_trait]
+pub trait CacheBackend: Send + Sync + std::fmt::Debug {
+    /// Backend name for logging and diagnostics
+    fn name(&self) -> &str;
+
+    /// Fetch an entry without recording an access
+    async fn get(&self, cache_key: &str) -> Result<Option<CacheEntry>>;
+
+    /// Fetch several entries, in the order of `cache_keys`
+    ///
+    /// The default issues one `get` per key; backends override it to batch
+    /// lock acquisitions or round trips.
+    async fn get_many(&self, cache_keys: &[String]) -> Result<Vec<Option<CacheEntry>>> {
+        let mut entries = Vec::with_capacity(cache_keys.len());
+        for cache_key in cache_keys {
+            entries.push(self.get(cache_key).await?);
+        }
+        Ok(entries)
+    }
+
+    /// Bump access count and last-access timestamp of an entry
+    async fn record_access(&self, cache_key: &str, timestamp: u64) -> Result<()>;
+
+    async fn record_access_many(&self, cache_keys: &[String], timestamp: u64) -> Result<()> {
+        for cache_key in cache_keys {
+            self.record_access(cache_key, timestamp).await?;
+        }
+        Ok(())
+    }
+
+    /// Insert or replace an entry and index it under its vertex
+    async fn insert(&self, cache_key: &str, entry: CacheEntry) -> Result<()>;
+
+    async fn insert_many(&self, entries: Vec<(String, CacheEntry)>) -> Result<()> {
+        for (cache_key, entry) in entries {
+            self.insert(&cache_key, entry).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove an entry and its index reference
+    async fn remove(&self, cache_key: &str) -> Result<Option<CacheEntry>>;
+
+    async fn contains(&self, cache_key: &str) -> Result<bool>;
+
+    async fn len(&self) -> Result<usize>;
+
+    /// Sum of `size_bytes` over all stored entries
+    async fn memory_bytes(&self) -> Result<usize>;
+
+    /// All entries keyed by cache key (used for eviction scans, stats, snapshots)
+    async fn entries(&self) -> Result<Vec<(String, CacheEntry)>>;
+
+    async fn vertex_entries(&self, vertex_id: &str) -> Result<Vec<CacheEntry>>;
+
+    /// Remove every entry of a vertex, returning the removed entries
+    async fn remove_vertex(&self, vertex_id: &str) -> Result<Vec<CacheEntry>>;
+
+    async fn vertex_index(&self) -> Result<HashMap<String, Vec<String>>>;
+
+    async fn clear(&self) -> Result<()>;
+}
+
+/// Process-local backend over lock-striped `HashMap` segments
+///
+/// Entries are sharded by a hash of the cache key and the vertex index by a
+/// hash of the vertex id, so concurrent operations on different keys rarely
+/// contend. No operation holds more than one shard lock at a time.
+#[derive(Debug)]
+pub struct InMemoryBackend {
+    entry_shards: Vec<RwLock<HashMap<String, CacheEntry>>>,
+    index_shards: Vec<RwLock<HashMap<String, Vec<String>>>>,
+    len: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+impl Default for InMemoryBackend {
+    fn default() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+}
+
+const DEFAULT_SHARDS: usize = 16;
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a backend with `shards` lock-striped segments (at least one)
+    pub fn with_shards(shards: usize) -> Self {
+        let shards = shards.max(1);
+        Self {
+            entry_shards: (0..shards).map(|_| RwLock::new(HashMap::new())).collect(),
+            index_shards: (0..shards).map(|_| RwLock::new(HashMap::new())).collect(),
+            len: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    fn shard_for(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() as usize) % self.entry_shards.len()
+    }
+
+    fn entry_shard(&self, cache_key: &str) -> &RwLock<HashMap<String, CacheEntry>> {
+        &self.entry_shards[self.shard_for(cache_key)]
+    }
+
+    fn index_shard(&self, vertex_id: &str) -> &RwLock<HashMap<String, Vec<String>>> {
+        &self.index_shards[self.shard_for(vertex_id)]
+    }
+
+    /// Positions of `keys` grouped by shard, so each shard is locked once per batch
+    fn group_by_shard<'a>(&self, keys: impl Iterator<Item = &'a str>) -> HashMap<usize, Vec<usize>> {
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (position, key) in keys.enumerate() {
+            groups.entry(self.shard_for(key)).or_default().push(position);
+        }
+        groups
+    }
+}
+
+#[async_trait]
+impl CacheBackend for InMemoryBackend {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn get(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        Ok(self.entry_shard(cache_key).read().await.get(cache_key).cloned())
+    }
+
+    async fn get_many(&self, cache_keys: &[String]) -> Result<Vec<Option<CacheEntry>>> {
+        let mut results = vec![None; cache_keys.len()];
+        for (shard, positions) in self.group_by_shard(cache_keys.iter().map(String::as_str)) {
+            let entries = self.entry_shards[shard].read().await;
+            for position in positions {
+                results[position] = entries.get(&cache_keys[position]).cloned();
+            }
+        }
+        Ok(results)
+    }
+
+    async fn record_access(&self, cache_key: &str, timestamp: u64) -> Result<()> {
+        if let Some(entry) = self.entry_shard(cache_key).write().await.get_mut(cache_key) {
+            entry.access_count += 1;
+            entry.timestamp = timestamp;
+        }
+        Ok(())
+    }
+
+    async fn record_access_many(&self, cache_keys: &[String], timestamp: u64) -> Result<()> {
+        for (shard, positions) in self.group_by_shard(cache_keys.iter().map(String::as_str)) {
+            let mut entries = self.entry_shards[shard].write().await;
+            for position in positions {
+                if let Some(entry) = entries.get_mut(&cache_keys[position]) {
+                    entry.access_count += 1;
+                    entry.timestamp = timestamp;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, cache_key: &str, entry: CacheEntry) -> Result<()> {
+        let vertex_id = entry.vertex_id.clone();
+        let size_bytes = entry.size_bytes;
+        
+        let previous = self.entry_shard(cache_key).write().await
+            .insert(cache_key.to_string(), entry);
+        self.bytes.fetch_add(size_bytes, Ordering::Relaxed);
+        match previous {
+            Some(previous) => {
+                self.bytes.fetch_sub(previous.size_bytes, Ordering::Relaxed);
+            }
+            None => {
+                self.len.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        
+        let mut index = self.index_shard(&vertex_id).write().await;
+        let keys = index.entry(vertex_id).or_insert_with(Vec::new);
+        if !keys.iter().any(|k| k == cache_key) {
+            keys.push(cache_key.to_string());
+        }
+        
+        Ok(())
+    }
+
+    async fn insert_many(&self, entries: Vec<(String, CacheEntry)>) -> Result<()> {
+        let index_updates: Vec<(String, String)> = entries.iter()
+            .map(|(cache_key, entry)| (entry.vertex_id.clone(), cache_key.clone()))
+            .collect();
+        
+        let mut slots: Vec<Option<(String, CacheEntry)>> = entries.into_iter().map(Some).collect();
+        let groups = self.group_by_shard(index_updates.iter().map(|(_, k)| k.as_str()));
+        for (shard, positions) in groups {
+            let mut shard_entries = self.entry_shards[shard].write().await;
+            for position in positions {
+                let Some((cache_key, entry)) = slots[position].take() else { continue };
+                self.bytes.fetch_add(entry.size_bytes, Ordering::Relaxed);
+                match shard_entries.insert(cache_key, entry) {
+                    Some(previous) => {
+                        self.bytes.fetch_sub(previous.size_bytes, Ordering::Relaxed);
+                    }
+                    None => {
+                        self.len.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        
+        // Index shards are taken only after every entry shard lock is released
+        for (shard, positions) in self.group_by_shard(index_updates.iter().map(|(v, _)| v.as_str())) {
+            let mut index = self.index_shards[shard].write().await;
+            for position in positions {
+                let (vertex_id, cache_key) = &index_updates[position];
+                let keys = index.entry(vertex_id.clone()).or_insert_with(Vec::new);
+                if !keys.iter().any(|k| k == cache_key) {
+                    keys.push(cache_key.clone());
+                }
+            }
+        }
+        
+        Ok(())
+    }
+
+    async fn remove(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let removed = self.entry_shard(cache_key).write().await.remove(cache_key);
+        
+        if let Some(entry) = &removed {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            self.bytes.fetch_sub(entry.size_bytes, Ordering::Relaxed);
+            
+            let mut index = self.index_shard(&entry.vertex_id).write().await;
+            if let Some(keys) = index.get_mut(&entry.vertex_id) {
+                keys.retain(|k| k != cache_key);
+                if keys.is_empty() {
+                    index.remove(&entry.vertex_id);
+                }
+            }
+        }
+        
+        Ok(removed)
+    }
+
+    async fn contains(&self, cache_key: &str) -> Result<bool> {
+        Ok(self.entry_shard(cache_key).read().await.contains_key(cache_key))
+    }
+
+    async fn len(&self) -> Result<usize> {
+        Ok(self.len.load(Ordering::Relaxed))
+    }
+
+    async fn memory_bytes(&self) -> Result<usize> {
+        Ok(self.bytes.load(Ordering::Relaxed))
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, CacheEntry)>> {
+        let mut all = Vec::with_capacity(self.len.load(Ordering::Relaxed));
+        for shard in &self.entry_shards {
+            all.extend(shard.read().await.iter().map(|(k, e)| (k.clone(), e.clone())));
+        }
+        Ok(all)
+    }
+
+    async fn vertex_entries(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let keys = self.index_shard(vertex_id).read().await
+            .get(vertex_id)
+            .cloned()
+            .unwrap_or_default();
+        
+        let mut entries = Vec::with_capacity(keys.len());
+        for cache_key in &keys {
+            if let Some(entry) = self.get(cache_key).await? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn remove_vertex(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let keys = self.index_shard(vertex_id).write().await
+            .remove(vertex_id)
+            .unwrap_or_default();
+        
+        let mut removed = Vec::with_capacity(keys.len());
+        for cache_key in &keys {
+            if let Some(entry) = self.entry_shard(cache_key).write().await.remove(cache_key) {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                self.bytes.fetch_sub(entry.size_bytes, Ordering::Relaxed);
+                removed.push(entry);
+            }
+        }
+        Ok(removed)
+    }
+
+    async fn vertex_index(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut index = HashMap::new();
+        for shard in &self.index_shards {
+            index.extend(shard.read().await.iter().map(|(v, keys)| (v.clone(), keys.clone())));
+        }
+        Ok(index)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        for shard in &self.entry_shards {
+            let mut entries = shard.write().await;
+            self.len.fetch_sub(entries.len(), Ordering::Relaxed);
+            self.bytes.fetch_sub(entries.values().map(|e| e.size_bytes).sum(), Ordering::Relaxed);
+            entries.clear();
+        }
+        for shard in &self.index_shards {
+            shard.write().await.clear();
+        }
+        
+        Ok(())
+    }
+}
+
+/// Redis backend shared by multiple GLM workers
+///
+/// Layout under `prefix`: `entry:<cache_key>` holds the JSON entry,
+/// `vertex:<vertex_id>` is a set of cache keys, `keys` is the set of all
+/// cache keys, and `bytes` is the running sum of entry sizes. Access-count
+/// updates are read-modify-write and best-effort.
+pub struct RedisBackend {
+    conn: redis::aio::ConnectionManager,
+    prefix: String,
+    insert_script: redis::Script,
+}
+
+/// Stores entries and moves the byte counter by the size difference in one
+/// atomic step, so concurrent writers of a key cannot make the counter drift
+///
+/// KEYS: the byte counter, the key set, then an entry key and vertex key per
+/// entry. ARGV: a cache key, JSON entry and size per entry. Entries are
+/// stored in order, so a key repeated in one batch is counted once.
+const INSERT_SCRIPT: &str = r#"
+local delta = 0
+for i = 1, #ARGV / 3 do
+    local entry_key = KEYS[2 * i + 1]
+    local previous = redis.call('GET', entry_key)
+    if previous then
+        delta = delta - (cjson.decode(previous).size_bytes or 0)
+    end
+    delta = delta + tonumber(ARGV[3 * i])
+    redis.call('SET', entry_key, ARGV[3 * i - 1])
+    redis.call('SADD', KEYS[2 * i + 2], ARGV[3 * i - 2])
+    redis.call('SADD', KEYS[2], ARGV[3 * i - 2])
+end
+redis.call('INCRBY', KEYS[1], delta)
+return delta
+"#;
+
+impl std::fmt::Debug for RedisBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisBackend")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+impl RedisBackend {
+    /// Connect to Redis at `url`, namespacing all keys under `prefix`
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(redis_error)?;
+        let conn = client.get_connection_manager().await.map_err(redis_error)?;
+        
+        Ok(Self {
+            conn,
+            prefix: prefix.to_string(),
+            insert_script: redis::Script::new(INSERT_SCRIPT),
+        })
+    }
+
+    fn entry_key(&self, cache_key: &str) -> String {
+        format!("{}:entry:{}", self.prefix, cache_key)
+    }
+
+    fn vertex_key(&self, vertex_id: &str) -> String {
+        format!("{}:vertex:{}", self.prefix, vertex_id)
+    }
+
+    fn keys_key(&self) -> String {
+        format!("{}:keys", self.prefix)
+    }
+
+    fn bytes_key(&self) -> String {
+        format!("{}:bytes", self.prefix)
+    }
+
+    /// Fetch entries with a single MGET, keeping positions of missing keys
+    async fn fetch_many(&self, cache_keys: &[String]) -> Result<Vec<Option<CacheEntry>>> {
+        if cache_keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let mut conn = self.conn.clone();
+        let redis_keys: Vec<String> = cache_keys.iter().map(|k| self.entry_key(k)).collect();
+        let raw: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(&redis_keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+        
+        raw.into_iter()
+            .map(|json| match json {
+                Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Store `entries` in order through `INSERT_SCRIPT`
+    async fn store(&self, entries: &[(String, CacheEntry)]) -> Result<()> {
+        let mut invocation = self.insert_script.prepare_invoke();
+        invocation.key(self.bytes_key()).key(self.keys_key());
+        for (cache_key, entry) in entries {
+            invocation.key(self.entry_key(cache_key))
+                .key(self.vertex_key(&entry.vertex_id))
+                .arg(cache_key)
+                .arg(serde_json::to_string(entry)?)
+                .arg(entry.size_bytes);
+        }
+        
+        let mut conn = self.conn.clone();
+        invocation.invoke_async::<_, i64>(&mut conn).await.map_err(redis_error)?;
+        Ok(())
+    }
+
+    async fn load_many(&self, cache_keys: &[String]) -> Result<Vec<(String, CacheEntry)>> {
+        let fetched = self.fetch_many(cache_keys).await?;
+        
+        Ok(cache_keys.iter()
+            .cloned()
+            .zip(fetched)
+            .filter_map(|(cache_key, entry)| entry.map(|entry| (cache_key, entry)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisBackend {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    async fn get(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let mut conn = self.conn.clone();
+        let json: Option<String> = conn.get(self.entry_key(cache_key)).await.map_err(redis_error)?;
+        
+        match json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_many(&self, cache_keys: &[String]) -> Result<Vec<Option<CacheEntry>>> {
+        self.fetch_many(cache_keys).await
+    }
+
+    async fn record_access(&self, cache_key: &str, timestamp: u64) -> Result<()> {
+        if let Some(mut entry) = self.get(cache_key).await? {
+            entry.access_count += 1;
+            entry.timestamp = timestamp;
+            
+            let mut conn = self.conn.clone();
+            let json = serde_json::to_string(&entry)?;
+            conn.set::<_, _, ()>(self.entry_key(cache_key), json).await.map_err(redis_error)?;
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, cache_key: &str, entry: CacheEntry) -> Result<()> {
+        self.store(&[(cache_key.to_string(), entry)]).await
+    }
+
+    async fn insert_many(&self, entries: Vec<(String, CacheEntry)>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.store(&entries).await
+    }
+
+    async fn remove(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let removed = self.get(cache_key).await?;
+        
+        if let Some(entry) = &removed {
+            let mut conn = self.conn.clone();
+            redis::pipe()
+                .atomic()
+                .del(self.entry_key(cache_key)).ignore()
+                .srem(self.vertex_key(&entry.vertex_id), cache_key).ignore()
+                .srem(self.keys_key(), cache_key).ignore()
+                .incr(self.bytes_key(), -(entry.size_bytes as i64)).ignore()
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(redis_error)?;
+        }
+        
+        Ok(removed)
+    }
+
+    async fn contains(&self, cache_key: &str) -> Result<bool> {
+        let mut conn = self.conn.clone();
+        conn.exists(self.entry_key(cache_key)).await.map_err(redis_error)
+    }
+
+    async fn len(&self) -> Result<usize> {
+        let mut conn = self.conn.clone();
+        conn.scard(self.keys_key()).await.map_err(redis_error)
+    }
+
+    async fn memory_bytes(&self) -> Result<usize> {
+        let mut conn = self.conn.clone();
+        let bytes: Option<i64> = conn.get(self.bytes_key()).await.map_err(redis_error)?;
+        Ok(bytes.unwrap_or(0).max(0) as usize)
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, CacheEntry)>> {
+        let mut conn = self.conn.clone();
+        let cache_keys: Vec<String> = conn.smembers(self.keys_key()).await.map_err(redis_error)?;
+        self.load_many(&cache_keys).await
+    }
+
+    async fn vertex_entries(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let mut conn = self.conn.clone();
+        let cache_keys: Vec<String> = conn.smembers(self.vertex_key(vertex_id)).await.map_err(redis_error)?;
+        
+        Ok(self.load_many(&cache_keys).await?
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect())
+    }
+
+    async fn remove_vertex(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let mut conn = self.conn.clone();
+        let cache_keys: Vec<String> = conn.smembers(self.vertex_key(vertex_id)).await.map_err(redis_error)?;
+        let removed = self.load_many(&cache_keys).await?;
+        let removed_bytes: usize = removed.iter().map(|(_, e)| e.size_bytes).sum();
+        
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(self.vertex_key(vertex_id)).ignore()
+            .incr(self.bytes_key(), -(removed_bytes as i64)).ignore();
+        for cache_key in &cache_keys {
+            pipe.del(self.entry_key(cache_key)).ignore()
+                .srem(self.keys_key(), cache_key).ignore();
+        }
+        pipe.query_async::<_, ()>(&mut conn).await.map_err(redis_error)?;
+        
+        Ok(removed.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    async fn vertex_index(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut index: HashMap<String, Vec<String>> = HashMap::new();
+        for (cache_key, entry) in self.entries().await? {
+            index.entry(entry.vertex_id).or_insert_with(Vec::new).push(cache_key);
+        }
+        Ok(index)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let entries = self.entries().await?;
+        let mut conn = self.conn.clone();
+        
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(self.keys_key()).ignore()
+            .del(self.bytes_key()).ignore();
+        for (cache_key, entry) in &entries {
+            pipe.del(self.entry_key(cache_key)).ignore()
+                .del(self.vertex_key(&entry.vertex_id)).ignore();
+        }
+        pipe.query_async::<_, ()>(&mut conn).await.map_err(redis_error)
+    }
+}
+
+/// Directory-backed store with one JSON file per entry
+///
+/// Meant as a large, slow second tier behind an in-memory cache. Entry
+/// metadata is indexed in memory and rebuilt by scanning the directory on
+/// `open`, so entries survive restarts.
+#[derive(Debug)]
+pub struct FileBackend {
+    dir: PathBuf,
+    index: RwLock<HashMap<String, FileEntryMeta>>,
+}
+
+#[derive(Debug, Clone)]
+struct FileEntryMeta {
+    vertex_id: String,
+    size_bytes: usize,
+    path: PathBuf,
+}
+
+/// On-disk record; keeps the cache key since file names are digests of it
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    cache_key: String,
+    entry: CacheEntry,
+}
+
+impl FileBackend {
+    /// Open (creating if needed) a store in `dir`, indexing existing entries
+    pub async fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&dir).await?;
+        
+        let mut index = HashMap::new();
+        let mut files = tokio::fs::read_dir(&dir).await?;
+        while let Some(file) = files.next_entry().await? {
+            let path = file.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match Self::read_path(&path).await {
+                Ok(stored) => {
+                    index.insert(stored.cache_key, FileEntryMeta {
+                        vertex_id: stored.entry.vertex_id,
+                        size_bytes: stored.entry.size_bytes,
+                        path,
+                    });
+                }
+                Err(e) => tracing::warn!("Skipping unreadable cache file {}: {:?}", path.display(), e),
+            }
+        }
+        
+        Ok(Self {
+            dir,
+            index: RwLock::new(index),
+        })
+    }
+
+    /// File for `cache_key`, named by its SHA-256 so names stay stable
+    /// across toolchains
+    fn path_for(&self, cache_key: &str) -> PathBuf {
+        let digest = Sha256::digest(cache_key.as_bytes());
+        let name: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    async fn read_path(path: &Path) -> Result<StoredEntry> {
+        let bytes = tokio::fs::read(path).await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// The entry stored at `path`, if it was stored for `cache_key`
+    async fn read_entry(path: &Path, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let stored = Self::read_path(path).await?;
+        if stored.cache_key != cache_key {
+            tracing::warn!("Cache file {} holds {:?}, not {:?}", path.display(), stored.cache_key, cache_key);
+            return Ok(None);
+        }
+        Ok(Some(stored.entry))
+    }
+
+    async fn read(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let path = match self.index.read().await.get(cache_key) {
+            Some(meta) => meta.path.clone(),
+            None => return Ok(None),
+        };
+        Self::read_entry(&path, cache_key).await
+    }
+
+    /// Write via a temporary file so readers never see a partial entry
+    async fn write(path: &Path, cache_key: &str, entry: &CacheEntry) -> Result<()> {
+        let bytes = serde_json::to_vec(&StoredEntry {
+            cache_key: cache_key.to_string(),
+            entry: entry.clone(),
+        })?;
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, bytes).await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CacheBackend for FileBackend {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn get(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        self.read(cache_key).await
+    }
+
+    async fn record_access(&self, cache_key: &str, timestamp: u64) -> Result<()> {
+        let mut index = self.index.write().await;
+        if let Some(meta) = index.get_mut(cache_key) {
+            if let Some(mut entry) = Self::read_entry(&meta.path, cache_key).await? {
+                entry.access_count += 1;
+                entry.timestamp = timestamp;
+                Self::write(&meta.path, cache_key, &entry).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, cache_key: &str, entry: CacheEntry) -> Result<()> {
+        let path = self.path_for(cache_key);
+        
+        // The index lock serializes writers of the same file
+        let mut index = self.index.write().await;
+        Self::write(&path, cache_key, &entry).await?;
+        let previous = index.insert(cache_key.to_string(), FileEntryMeta {
+            vertex_id: entry.vertex_id,
+            size_bytes: entry.size_bytes,
+            path: path.clone(),
+        });
+        // Files written under an older naming scheme are replaced, not kept
+        if let Some(previous) = previous.filter(|m| m.path != path) {
+            tokio::fs::remove_file(&previous.path).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, cache_key: &str) -> Result<Option<CacheEntry>> {
+        let mut index = self.index.write().await;
+        let path = match index.get(cache_key) {
+            Some(meta) => meta.path.clone(),
+            None => return Ok(None),
+        };
+        // Only forget the file once it is gone, so a failure leaves it indexed
+        let entry = Self::read_entry(&path, cache_key).await?;
+        tokio::fs::remove_file(&path).await?;
+        index.remove(cache_key);
+        Ok(entry)
+    }
+
+    async fn contains(&self, cache_key: &str) -> Result<bool> {
+        Ok(self.index.read().await.contains_key(cache_key))
+    }
+
+    async fn len(&self) -> Result<usize> {
+        Ok(self.index.read().await.len())
+    }
+
+    async fn memory_bytes(&self) -> Result<usize> {
+        Ok(self.index.read().await.values().map(|m| m.size_bytes).sum())
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, CacheEntry)>> {
+        let paths: Vec<(String, PathBuf)> = self.index.read().await.iter()
+            .map(|(k, m)| (k.clone(), m.path.clone()))
+            .collect();
+        
+        let mut entries = Vec::with_capacity(paths.len());
+        for (cache_key, path) in paths {
+            if let Some(entry) = Self::read_entry(&path, &cache_key).await? {
+                entries.push((cache_key, entry));
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn vertex_entries(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let paths: Vec<(String, PathBuf)> = self.index.read().await.iter()
+            .filter(|(_, m)| m.vertex_id == vertex_id)
+            .map(|(k, m)| (k.clone(), m.path.clone()))
+            .collect();
+        
+        let mut entries = Vec::with_capacity(paths.len());
+        for (cache_key, path) in paths {
+            if let Some(entry) = Self::read_entry(&path, &cache_key).await? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn remove_vertex(&self, vertex_id: &str) -> Result<Vec<CacheEntry>> {
+        let mut index = self.index.write().await;
+        let doomed: Vec<(String, PathBuf)> = index.iter()
+            .filter(|(_, m)| m.vertex_id == vertex_id)
+            .map(|(k, m)| (k.clone(), m.path.clone()))
+            .collect();
+        
+        let mut removed = Vec::with_capacity(doomed.len());
+        for (cache_key, path) in doomed {
+            let entry = Self::read_entry(&path, &cache_key).await?;
+            tokio::fs::remove_file(&path).await?;
+            index.remove(&cache_key);
+            removed.extend(entry);
+        }
+        Ok(removed)
+    }
+
+    async fn vertex_index(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut vertex_index: HashMap<String, Vec<String>> = HashMap::new();
+        for (cache_key, meta) in self.index.read().await.iter() {
+            vertex_index.entry(meta.vertex_id.clone()).or_default().push(cache_key.clone());
+        }
+        Ok(vertex_index)
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let mut index = self.index.write().await;
+        for (_, meta) in index.drain() {
+            tokio::fs::remove_file(&meta.path).await?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn redis_error(e: redis::RedisError) -> Error {
+    Error::Cache(format!("redis: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::cache_manager::CacheValue;
+
+    fn entry(vertex_id: &str, key: &str) -> CacheEntry {
+        CacheEntry {
+            vertex_id: vertex_id.to_string(),
+            key: key.to_string(),
+            value: CacheValue::Embedding(vec![1.0]),
+            timestamp: 0,
+            access_count: 1,
+            computation_cost: 0.5,
+            inserted_at: 0,
+            tags: Vec::new(),
+            size_bytes: 64,
+            namespace: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_remove_cleans_index() {
+        let backend = InMemoryBackend::new();
+        backend.insert("v1:a", entry("v1", "a")).await.unwrap();
+        backend.insert("v1:b", entry("v1", "b")).await.unwrap();
+        backend.insert("v1:a", entry("v1", "a")).await.unwrap();
+        
+        assert_eq!(backend.vertex_index().await.unwrap()["v1"].len(), 2);
+        
+        backend.remove("v1:a").await.unwrap();
+        backend.remove("v1:b").await.unwrap();
+        assert!(backend.vertex_index().await.unwrap().is_empty());
+        assert_eq!(backend.len().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sharded_vertex_entries_span_shards() {
+        let backend = InMemoryBackend::with_shards(4);
+        for i in 0..32 {
+            let key = format!("k{}", i);
+            backend.insert(&format!("v1:{}", key), entry("v1", &key)).await.unwrap();
+        }
+        
+        assert_eq!(backend.len().await.unwrap(), 32);
+        assert_eq!(backend.vertex_entries("v1").await.unwrap().len(), 32);
+        assert_eq!(backend.memory_bytes().await.unwrap(), 32 * 64);
+        assert_eq!(backend.remove_vertex("v1").await.unwrap().len(), 32);
+        assert_eq!(backend.len().await.unwrap(), 0);
+        assert_eq!(backend.memory_bytes().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_reopens() {
+        let dir = std::env::temp_dir().join(format!("file_backend_{}", uuid::Uuid::new_v4()));
+        
+        let backend = FileBackend::open(&dir).await.unwrap();
+        backend.insert("v1:a", entry("v1", "a")).await.unwrap();
+        backend.insert("v1:b", entry("v1", "b")).await.unwrap();
+        backend.remove("v1:b").await.unwrap();
+        drop(backend);
+        
+        let reopened = FileBackend::open(&dir).await.unwrap();
+        assert_eq!(reopened.len().await.unwrap(), 1);
+        assert_eq!(reopened.get("v1:a").await.unwrap().unwrap().key, "a");
+        assert_eq!(reopened.vertex_entries("v1").await.unwrap().len(), 1);
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_names_and_removal() {
+        let dir = std::env::temp_dir().join(format!("file_backend_{}", uuid::Uuid::new_v4()));
+        let backend = FileBackend::open(&dir).await.unwrap();
+        
+        // SHA-256 of "v1:a", independent of the toolchain's hasher
+        let path = backend.path_for("v1:a");
+        assert_eq!(
+            path.file_name().unwrap().to_str().unwrap(),
+            "c9eac6694eb5c5074c7ded2bd29424e7d2d4e4f1a45b15ba44bdeeeb272fdf5d.json",
+        );
+        
+        // A file holding another key is not returned for this one
+        backend.insert("v1:a", entry("v1", "a")).await.unwrap();
+        FileBackend::write(&path, "v1:b", &entry("v1", "b")).await.unwrap();
+        assert!(backend.get("v1:a").await.unwrap().is_none());
+        
+        // A failed removal keeps the entry indexed
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert!(backend.remove("v1:a").await.is_err());
+        assert!(backend.contains("v1:a").await.unwrap());
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
@@ -0,0 +1,209 @@
+// -*- coding: utf-8 -*-
+//! Negative-Lookup Filter
+//! 
+//! Bloom filter over cached keys so definite misses skip the backend entirely.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+/// Sizing and maintenance settings for the filter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BloomConfig {
+    pub expected_items: usize,
+    /// Target false-positive rate at `expected_items`
+    pub false_positive_rate: f64,
+    /// Rebuild once removals exceed this share of the keys inserted since the last rebuild
+    pub rebuild_after_removals: f64,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        Self {
+            expected_items: 10_000,
+            false_positive_rate: 0.01,
+            rebuild_after_removals: 0.25,
+        }
+    }
+}
+
+/// Filter effectiveness counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BloomStats {
+    pub bits: usize,
+    pub hash_functions: u32,
+    /// Lookups answered "definitely absent" without touching the backend
+    pub negatives: usize,
+    /// Lookups the filter let through
+    pub passes: usize,
+    /// Passed lookups that found nothing in any tier
+    pub false_positives: usize,
+    /// `false_positives / (false_positives + negatives)`: the observed rate among absent keys
+    pub false_positive_rate: f64,
+    pub rebuilds: usize,
+}
+
+/// Bloom filter over the keys held in every cache tier
+///
+/// Bits are never cleared on removal, since other keys may share them;
+/// removed keys only cause false positives until the next rebuild. Rebuilds
+/// must not race with inserts, so callers hold the cache's admission lock.
+///
+/// The filter knows nothing of keys stored before the first rebuild, so it
+/// answers nothing until one has run.
+pub(crate) struct NegativeLookupFilter {
+    config: BloomConfig,
+    hash_functions: u32,
+    words: RwLock<Vec<AtomicU64>>,
+    inserted: AtomicUsize,
+    removed: AtomicUsize,
+    negatives: AtomicUsize,
+    passes: AtomicUsize,
+    false_positives: AtomicUsize,
+    rebuilds: AtomicUsize,
+    seeded: AtomicBool,
+}
+
+impl NegativeLookupFilter {
+    pub fn new(config: BloomConfig) -> Self {
+        let items = config.expected_items.max(1) as f64;
+        let rate = config.false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(items * rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hash_functions = ((bits as f64 / items) * ln2).round().clamp(1.0, 16.0) as u32;
+        
+        Self {
+            config,
+            hash_functions,
+            words: RwLock::new(Self::empty_words(bits)),
+            inserted: AtomicUsize::new(0),
+            removed: AtomicUsize::new(0),
+            negatives: AtomicUsize::new(0),
+            passes: AtomicUsize::new(0),
+            false_positives: AtomicUsize::new(0),
+            rebuilds: AtomicUsize::new(0),
+            seeded: AtomicBool::new(false),
+        }
+    }
+
+    fn empty_words(bits: usize) -> Vec<AtomicU64> {
+        (0..bits.div_ceil(64)).map(|_| AtomicU64::new(0)).collect()
+    }
+
+    /// Bit positions for `key` by double hashing
+    fn positions(&self, key: &str, bits: usize) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash(0), hash(1) | 1);
+        (0..self.hash_functions as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits as u64) as usize)
+    }
+
+    fn set(&self, words: &[AtomicU64], key: &str) {
+        for bit in self.positions(key, words.len() * 64) {
+            words[bit / 64].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    /// Whether a rebuild has filled the filter with the keys already stored
+    pub fn is_seeded(&self) -> bool {
+        self.seeded.load(Ordering::Acquire)
+    }
+
+    /// `false` means `key` is definitely absent; `true` means it may be cached
+    pub fn may_contain(&self, key: &str) -> bool {
+        if !self.is_seeded() {
+            return true;
+        }
+        let words = self.words.read().unwrap_or_else(|e| e.into_inner());
+        let present = self.positions(key, words.len() * 64)
+            .all(|bit| words[bit / 64].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0);
+        let counter = if present { &self.passes } else { &self.negatives };
+        counter.fetch_add(1, Ordering::Relaxed);
+        present
+    }
+
+    pub fn insert(&self, key: &str) {
+        let words = self.words.read().unwrap_or_else(|e| e.into_inner());
+        self.set(&words, key);
+        self.inserted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_removals(&self, count: usize) {
+        self.removed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_false_positive(&self) {
+        self.false_positives.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn needs_rebuild(&self) -> bool {
+        let inserted = self.inserted.load(Ordering::Relaxed).max(1) as f64;
+        self.removed.load(Ordering::Relaxed) as f64 > inserted * self.config.rebuild_after_removals
+    }
+
+    /// Replace the filter contents with exactly `keys`
+    pub fn rebuild<'a>(&self, keys: impl IntoIterator<Item = &'a str>) {
+        let bits = self.words.read().unwrap_or_else(|e| e.into_inner()).len() * 64;
+        let fresh = Self::empty_words(bits);
+        let mut count = 0;
+        for key in keys {
+            self.set(&fresh, key);
+            count += 1;
+        }
+        
+        *self.words.write().unwrap_or_else(|e| e.into_inner()) = fresh;
+        self.inserted.store(count, Ordering::Relaxed);
+        self.removed.store(0, Ordering::Relaxed);
+        self.rebuilds.fetch_add(1, Ordering::Relaxed);
+        self.seeded.store(true, Ordering::Release);
+    }
+
+    pub fn stats(&self) -> BloomStats {
+        let negatives = self.negatives.load(Ordering::Relaxed);
+        let false_positives = self.false_positives.load(Ordering::Relaxed);
+        let absent = negatives + false_positives;
+        
+        BloomStats {
+            bits: self.words.read().unwrap_or_else(|e| e.into_inner()).len() * 64,
+            hash_functions: self.hash_functions,
+            negatives,
+            passes: self.passes.load(Ordering::Relaxed),
+            false_positives,
+            false_positive_rate: if absent > 0 { false_positives as f64 / absent as f64 } else { 0.0 },
+            rebuilds: self.rebuilds.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives_and_rebuild_clears_removed() {
+        let filter = NegativeLookupFilter::new(BloomConfig {
+            expected_items: 100,
+            ..BloomConfig::default()
+        });
+        let keys: Vec<String> = (0..100).map(|i| format!("v{}:key", i)).collect();
+        assert!(filter.may_contain("absent"));
+        filter.rebuild(std::iter::empty());
+        keys.iter().for_each(|key| filter.insert(key));
+        assert!(keys.iter().all(|key| filter.may_contain(key)));
+        
+        let absent = (0..1000).filter(|i| !filter.may_contain(&format!("absent{}", i))).count();
+        assert!(absent > 950, "too many false positives: {}", 1000 - absent);
+        
+        filter.record_removals(50);
+        assert!(filter.needs_rebuild());
+        filter.rebuild(keys[50..].iter().map(String::as_str));
+        assert!(!filter.needs_rebuild());
+        assert!(keys[50..].iter().all(|key| filter.may_contain(key)));
+    }
+}
@@ -0,0 +1,88 @@
+// -*- coding: utf-8 -*-
+//! Cache Decision Log
+//!
+//! Bounded record of why entries were admitted to or evicted from the cache.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Low-scoring candidates kept alongside each eviction
+pub(crate) const RUNNERS_UP: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecisionKind {
+    Admitted,
+    Evicted,
+}
+
+/// What caused a decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecisionReason {
+    /// Written by a put
+    Put,
+    /// Copied back into memory on an L2 hit
+    Promotion,
+    /// Loaded by `warm_up`
+    WarmUp,
+    /// Evicted to stay under `max_entries`
+    EntryLimit,
+    /// Evicted to stay under `max_memory_bytes`
+    MemoryLimit,
+    /// Evicted to keep a namespace within its quota
+    NamespaceQuota(String),
+}
+
+/// One admission or eviction and the inputs it was made from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheDecision {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub kind: DecisionKind,
+    pub cache_key: String,
+    pub reason: DecisionReason,
+    /// Eviction policy in force
+    pub policy: String,
+    /// Retention score of the entry under `policy`
+    pub score: f64,
+    /// Entry whose admission forced an eviction
+    pub trigger: Option<String>,
+    /// Entries the policy chose between; zero for admissions
+    pub candidates_considered: usize,
+    /// Lowest-scoring candidates kept instead, best victim first
+    pub runners_up: Vec<(String, f64)>,
+    /// Every candidate was pinned, so pins were ignored
+    pub pinned_fallback: bool,
+}
+
+/// Ring buffer of the most recent decisions
+pub(crate) struct DecisionLog {
+    capacity: usize,
+    decisions: Mutex<VecDeque<CacheDecision>>,
+}
+
+impl DecisionLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            decisions: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(&self, decision: CacheDecision) {
+        let mut decisions = self.decisions.lock().unwrap_or_else(|e| e.into_inner());
+        if decisions.len() == self.capacity {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+
+    /// Decisions with `from_ms <= timestamp_ms < to_ms`, oldest first
+    pub fn between(&self, from_ms: u64, to_ms: u64) -> Vec<CacheDecision> {
+        self.decisions.lock().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|d| d.timestamp_ms >= from_ms && d.timestamp_ms < to_ms)
+            .cloned()
+            .collect()
+    }
+}
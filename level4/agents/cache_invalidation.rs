@@ -0,0 +1,186 @@
+// -*- coding: utf-8 -*-
+//! Cross-Process Cache Invalidation
+//!
+//! Broadcasts invalidate-vertex/tag messages between service replicas that
+//! each hold their own in-memory `VertexCentricCache`.
+
+use crate::error::Result;
+use crate::level4::agents::cache_backend::redis_error;
+use crate::level4::agents::clock::{Clock, IdGenerator};
+use async_trait::async_trait;
+use redis::streams::{StreamReadOptions, StreamReadReply};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+/// What a replica should drop from its local cache
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum InvalidationTarget {
+    Vertex(String),
+    /// Resolved neighborhood of a mutated vertex, sent as one message
+    Vertices(Vec<String>),
+    Tag(String),
+}
+
+/// Invalidation broadcast between replicas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidationMessage {
+    pub message_id: String,
+    pub origin: String,
+    pub target: InvalidationTarget,
+    pub timestamp: u64,
+}
+
+impl InvalidationMessage {
+    /// Message stamped by `clock` with an id from `ids`
+    pub fn new(origin: &str, target: InvalidationTarget, clock: &dyn Clock, ids: &dyn IdGenerator) -> Self {
+        Self {
+            message_id: ids.next_id(),
+            origin: origin.to_string(),
+            target,
+            timestamp: clock.now_secs(),
+        }
+    }
+}
+
+/// Message returned by `InvalidationBus::poll`, acknowledged once applied
+#[derive(Debug, Clone)]
+pub struct InvalidationDelivery {
+    pub delivery_id: String,
+    pub message: InvalidationMessage,
+}
+
+/// Transport for invalidation messages with at-least-once delivery
+///
+/// Deliveries stay pending until `ack` is called, and unacknowledged ones are
+/// handed out again by later polls. Applying an invalidation twice is harmless.
+#[async_trait]
+pub trait InvalidationBus: Send + Sync + std::fmt::Debug {
+    /// Identity of this replica; messages it published are skipped on receipt
+    fn replica_id(&self) -> &str;
+
+    async fn publish(&self, message: &InvalidationMessage) -> Result<()>;
+
+    async fn poll(&self, max_messages: usize) -> Result<Vec<InvalidationDelivery>>;
+
+    async fn ack(&self, delivery_id: &str) -> Result<()>;
+}
+
+/// Redis Streams bus
+///
+/// Every replica reads the shared stream through its own consumer group, so
+/// each replica sees every message, and pending entries survive a crash
+/// between receipt and `ack`. Plain pub/sub would drop messages sent while a
+/// replica is disconnected.
+pub struct RedisStreamBus {
+    conn: redis::aio::ConnectionManager,
+    stream: String,
+    replica_id: String,
+    block_ms: usize,
+    max_stream_len: usize,
+}
+
+impl std::fmt::Debug for RedisStreamBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisStreamBus")
+            .field("stream", &self.stream)
+            .field("replica_id", &self.replica_id)
+            .finish()
+    }
+}
+
+impl RedisStreamBus {
+    pub async fn connect(url: &str, stream: &str, replica_id: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(redis_error)?;
+        let mut conn = client.get_connection_manager().await.map_err(redis_error)?;
+        
+        // Start the group at the stream tail; an existing group keeps its offset
+        let created: redis::RedisResult<()> = conn
+            .xgroup_create_mkstream(stream, replica_id, "$")
+            .await;
+        if let Err(e) = created {
+            if e.code() != Some("BUSYGROUP") {
+                return Err(redis_error(e));
+            }
+        }
+        
+        Ok(Self {
+            conn,
+            stream: stream.to_string(),
+            replica_id: replica_id.to_string(),
+            block_ms: 1000,
+            max_stream_len: 10_000,
+        })
+    }
+
+    async fn read(&self, start_id: &str, max_messages: usize) -> Result<Vec<InvalidationDelivery>> {
+        let mut conn = self.conn.clone();
+        let mut options = StreamReadOptions::default()
+            .group(&self.replica_id, &self.replica_id)
+            .count(max_messages);
+        if start_id == ">" {
+            options = options.block(self.block_ms);
+        }
+        
+        let reply: Option<StreamReadReply> = conn
+            .xread_options(&[&self.stream], &[start_id], &options)
+            .await
+            .map_err(redis_error)?;
+        
+        let mut deliveries = Vec::new();
+        for stream_key in reply.map(|r| r.keys).unwrap_or_default() {
+            for stream_id in stream_key.ids {
+                let payload: Option<String> = stream_id.get("payload");
+                match payload.map(|p| serde_json::from_str::<InvalidationMessage>(&p)) {
+                    Some(Ok(message)) => deliveries.push(InvalidationDelivery {
+                        delivery_id: stream_id.id,
+                        message,
+                    }),
+                    _ => {
+                        // Malformed entries would be redelivered forever; drop them
+                        tracing::warn!("Dropping malformed invalidation entry {}", stream_id.id);
+                        self.ack(&stream_id.id).await?;
+                    }
+                }
+            }
+        }
+        
+        Ok(deliveries)
+    }
+}
+
+#[async_trait]
+impl InvalidationBus for RedisStreamBus {
+    fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    async fn publish(&self, message: &InvalidationMessage) -> Result<()> {
+        let mut conn = self.conn.clone();
+        let payload = serde_json::to_string(message)?;
+        
+        conn.xadd_maxlen::<_, _, _, _, ()>(
+            &self.stream,
+            redis::streams::StreamMaxlen::Approx(self.max_stream_len),
+            "*",
+            &[("payload", payload)],
+        )
+        .await
+        .map_err(redis_error)
+    }
+
+    async fn poll(&self, max_messages: usize) -> Result<Vec<InvalidationDelivery>> {
+        // Redeliver our own unacknowledged entries before reading new ones
+        let pending = self.read("0", max_messages).await?;
+        if !pending.is_empty() {
+            return Ok(pending);
+        }
+        self.read(">", max_messages).await
+    }
+
+    async fn ack(&self, delivery_id: &str) -> Result<()> {
+        let mut conn = self.conn.clone();
+        conn.xack::<_, _, _, ()>(&self.stream, &self.replica_id, &[delivery_id])
+            .await
+            .map_err(redis_error)
+    }
+}
@@ -3,21 +3,211 @@
 //! 
 //! Efficient caching of graph vertex computations with reuse optimization.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::level4::agents::cache_backend::{CacheBackend, InMemoryBackend};
+use crate::level4::agents::cache_bloom::{BloomConfig, BloomStats, NegativeLookupFilter};
+use crate::level4::agents::cache_decisions::{CacheDecision, DecisionKind, DecisionLog, DecisionReason, RUNNERS_UP};
+use crate::level4::agents::cache_invalidation::{InvalidationBus, InvalidationMessage, InvalidationTarget};
+use crate::level4::agents::cache_metrics::{CacheMetrics, LatencyHistogram, PrefetchOutcome, PrefetchStats, PrefixStats};
+use crate::level4::agents::cache_wal::{WalOp, WriteAheadLog};
+use crate::level4::agents::clock::{self, Clock, IdGenerator};
+use crate::level4::agents::contention;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, Mutex, OnceCell, RwLock, Semaphore};
+use tokio::task::JoinHandle;
 
 /// Cache entry for vertex computation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
     pub vertex_id: String,
     pub key: String,
-    pub value: Vec<f64>,
+    pub value: CacheValue,
     pub timestamp: u64,
     pub access_count: usize,
     pub computation_cost: f64,
+    #[serde(default)]
+    pub inserted_at: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Estimated heap + inline footprint, computed on insert
+    #[serde(default)]
+    pub size_bytes: usize,
+    /// Tenant the entry belongs to; empty for the default namespace
+    #[serde(default)]
+    pub namespace: String,
+}
+
+impl CacheEntry {
+    /// Estimate the in-memory footprint of this entry in bytes
+    pub fn estimated_size(&self) -> usize {
+        let value_bytes = match &self.value {
+            CacheValue::Embedding(values) => values.len() * std::mem::size_of::<f64>(),
+            CacheValue::Text(text) => text.len(),
+            CacheValue::Bytes(bytes) => bytes.len(),
+            CacheValue::Quantized(quantized) => quantized.stored_bytes(),
+        };
+        let tag_bytes: usize = self.tags.iter()
+            .map(|t| t.len() + std::mem::size_of::<String>())
+            .sum();
+        
+        std::mem::size_of::<CacheEntry>()
+            + self.vertex_id.len()
+            + self.key.len()
+            + value_bytes
+            + tag_bytes
+    }
+}
+
+/// Value stored in a cache entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CacheValue {
+    /// Vertex embedding or other dense vector
+    Embedding(Vec<f64>),
+    /// Reasoning-step output or other text
+    Text(String),
+    /// Serialized subgraphs, token lists, or any opaque payload
+    Bytes(Vec<u8>),
+    /// Embedding stored at reduced precision; reads dequantize it
+    Quantized(QuantizedEmbedding),
+}
+
+impl CacheValue {
+    /// Borrow a full-precision embedding; quantized values need `into_embedding`
+    pub fn as_embedding(&self) -> Option<&[f64]> {
+        match self {
+            CacheValue::Embedding(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn into_embedding(self) -> Option<Vec<f64>> {
+        match self {
+            CacheValue::Embedding(values) => Some(values),
+            CacheValue::Quantized(quantized) => Some(quantized.dequantize()),
+            _ => None,
+        }
+    }
+
+    /// Expand quantized embeddings back to `Embedding`; other values are unchanged
+    pub fn dequantized(self) -> CacheValue {
+        match self {
+            CacheValue::Quantized(quantized) => CacheValue::Embedding(quantized.dequantize()),
+            value => value,
+        }
+    }
+
+    pub fn into_text(self) -> Option<String> {
+        match self {
+            CacheValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            CacheValue::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+/// Storage precision for cached embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EmbeddingQuantization {
+    /// Keep full `f64` precision
+    #[default]
+    None,
+    /// 1 byte per dimension with a per-vector scale (8x smaller)
+    Int8,
+    /// IEEE half precision (4x smaller)
+    F16,
+}
+
+/// Embedding stored at reduced precision
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum QuantizedEmbedding {
+    /// Symmetric int8: each dimension is `value * scale`
+    Int8 { scale: f64, values: Vec<i8> },
+    /// Half-precision bit patterns
+    F16(Vec<u16>),
+}
+
+impl QuantizedEmbedding {
+    /// Quantize `values`, or `None` when `quantization` keeps full precision
+    pub fn quantize(values: &[f64], quantization: EmbeddingQuantization) -> Option<Self> {
+        match quantization {
+            EmbeddingQuantization::None => None,
+            EmbeddingQuantization::Int8 => {
+                let max_abs = values.iter().fold(0.0f64, |max, v| max.max(v.abs()));
+                let scale = if max_abs > 0.0 { max_abs / i8::MAX as f64 } else { 1.0 };
+                Some(QuantizedEmbedding::Int8 {
+                    scale,
+                    values: values.iter().map(|v| (v / scale).round() as i8).collect(),
+                })
+            }
+            EmbeddingQuantization::F16 => Some(QuantizedEmbedding::F16(
+                values.iter().map(|v| half::f16::from_f64(*v).to_bits()).collect(),
+            )),
+        }
+    }
+
+    pub fn dequantize(&self) -> Vec<f64> {
+        match self {
+            QuantizedEmbedding::Int8 { scale, values } => {
+                values.iter().map(|v| *v as f64 * scale).collect()
+            }
+            QuantizedEmbedding::F16(bits) => {
+                bits.iter().map(|b| half::f16::from_bits(*b).to_f64()).collect()
+            }
+        }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        match self {
+            QuantizedEmbedding::Int8 { values, .. } => values.len(),
+            QuantizedEmbedding::F16(bits) => bits.len(),
+        }
+    }
+
+    /// Bytes taken by the quantized payload
+    pub fn stored_bytes(&self) -> usize {
+        match self {
+            QuantizedEmbedding::Int8 { values, .. } => values.len() + std::mem::size_of::<f64>(),
+            QuantizedEmbedding::F16(bits) => bits.len() * std::mem::size_of::<u16>(),
+        }
+    }
+}
+
+impl From<Vec<f64>> for CacheValue {
+    fn from(values: Vec<f64>) -> Self {
+        CacheValue::Embedding(values)
+    }
+}
+
+impl From<String> for CacheValue {
+    fn from(text: String) -> Self {
+        CacheValue::Text(text)
+    }
+}
+
+impl From<&str> for CacheValue {
+    fn from(text: &str) -> Self {
+        CacheValue::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for CacheValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        CacheValue::Bytes(bytes)
+    }
 }
 
 /// Cache statistics
@@ -29,52 +219,509 @@ pub struct CacheStats {
     pub hit_rate: f64,
     pub avg_access_count: f64,
     pub memory_usage_mb: f64,
+    pub memory_usage_bytes: usize,
+    /// Share of lookups served from memory
+    #[serde(default)]
+    pub l1_hit_rate: f64,
+    /// Share of memory misses served from the L2 tier
+    #[serde(default)]
+    pub l2_hit_rate: f64,
+    #[serde(default)]
+    pub l2_entries: usize,
+    /// Full-precision size of cached embeddings divided by their stored size
+    #[serde(default)]
+    pub embedding_compression_ratio: f64,
+    /// Lookups keyed by vertex id prefix
+    #[serde(default)]
+    pub prefixes: HashMap<String, PrefixStats>,
+    /// Entries dropped from the cache entirely
+    #[serde(default)]
+    pub evictions: usize,
+    /// Entries moved from memory to the L2 tier
+    #[serde(default)]
+    pub demotions: usize,
+    #[serde(default)]
+    pub put_latency: LatencyHistogram,
+    /// Time spent waiting on the admission, pin and in-flight locks
+    #[serde(default)]
+    pub lock_waits: HashMap<String, LatencyHistogram>,
+    #[serde(default)]
+    pub prefetch: PrefetchStats,
+    #[serde(default)]
+    pub negative_lookup_filter: Option<BloomStats>,
+}
+
+/// Point-in-time dump of cache entries and the vertex index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheSnapshot {
+    pub created_at: u64,
+    pub entries: Vec<CacheEntry>,
+    pub vertex_index: HashMap<String, Vec<String>>,
+}
+
+/// Upcoming traversal frontier passed in by the retrieval planner
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalityHint {
+    /// Vertices the chain is about to visit; pinned against eviction
+    pub frontier: Vec<String>,
+    /// Likely next hops; prefetched but not pinned
+    pub neighbors: Vec<String>,
+}
+
+/// One line of a precomputed embeddings file (JSON Lines)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRecord {
+    pub vertex_id: String,
+    #[serde(default = "EmbeddingRecord::default_key")]
+    pub key: String,
+    pub embedding: Vec<f64>,
+    #[serde(default)]
+    pub computation_cost: f64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub namespace: String,
+}
+
+impl EmbeddingRecord {
+    fn default_key() -> String {
+        "embedding".to_string()
+    }
+}
+
+impl From<EmbeddingRecord> for CacheEntry {
+    fn from(record: EmbeddingRecord) -> Self {
+        Self {
+            vertex_id: record.vertex_id,
+            key: record.key,
+            value: CacheValue::Embedding(record.embedding),
+            timestamp: 0,
+            access_count: 1,
+            computation_cost: record.computation_cost,
+            inserted_at: 0,
+            tags: record.tags,
+            size_bytes: 0,
+            namespace: record.namespace,
+        }
+    }
+}
+
+/// Observer notified when entries leave the cache or are refreshed in place
+///
+/// Callbacks run inline on the cache's write path and must stay cheap.
+pub trait CacheListener: Send + Sync {
+    fn on_evict(&self, _entry: &CacheEntry) {}
+    fn on_refresh(&self, _entry: &CacheEntry) {}
+}
+
+/// Cache activity published to `VertexCentricCache::subscribe` receivers
+#[derive(Debug, Clone)]
+pub enum CacheEvent {
+    Inserted {
+        vertex_id: String,
+        key: String,
+        size_bytes: usize,
+    },
+    /// Entry removed to make room; carries the full entry so it can be persisted
+    Evicted(CacheEntry),
+    /// Entry moved from memory to the L2 tier to make room
+    Demoted {
+        vertex_id: String,
+        key: String,
+    },
+    /// L2 hit copied back into memory
+    Promoted {
+        vertex_id: String,
+        key: String,
+    },
+    Invalidated {
+        target: InvalidationTarget,
+        removed: usize,
+    },
+    /// Hit rate over the last window fell below the configured alert threshold
+    HitRateDrop {
+        hit_rate: f64,
+        threshold: f64,
+    },
+}
+
+#[derive(Debug, Default)]
+struct HitRateWindow {
+    hits: usize,
+    lookups: usize,
+    below_threshold: bool,
+}
+
+/// Graph adjacency consulted by `invalidate_neighborhood`
+#[async_trait]
+pub trait NeighborProvider: Send + Sync + std::fmt::Debug {
+    /// Vertices whose cached computations depend on `vertex_id`
+    async fn neighbors(&self, vertex_id: &str) -> Result<Vec<String>>;
+}
+
+/// Fixed adjacency map, e.g. for tests or graphs small enough to keep in memory
+#[async_trait]
+impl NeighborProvider for HashMap<String, Vec<String>> {
+    async fn neighbors(&self, vertex_id: &str) -> Result<Vec<String>> {
+        Ok(self.get(vertex_id).cloned().unwrap_or_default())
+    }
+}
+
+/// Source for entries missing from the cache, consulted by `prefetch`
+#[async_trait]
+pub trait CacheLoader: Send + Sync + std::fmt::Debug {
+    /// Fetch or compute the `(key, value)` entries to cache for `vertex_id`
+    async fn load(&self, vertex_id: &str) -> Result<Vec<(String, CacheValue)>>;
+}
+
+/// How busy the system around the cache is, consulted before prefetching
+pub trait LoadSignal: Send + Sync + std::fmt::Debug {
+    /// Current load in `0.0..=1.0`, where `1.0` is saturated
+    fn load(&self) -> f64;
+}
+
+/// Pressure levels at which `prefetch` backs off
+///
+/// Pressure is the larger of the configured `LoadSignal` and memory use as a
+/// fraction of `max_memory_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchThrottle {
+    /// Above this, loads run one at a time instead of `prefetch_concurrency` at once
+    pub throttle_above: f64,
+    /// Above this, loads are skipped until pressure drops
+    pub pause_above: f64,
+}
+
+impl Default for PrefetchThrottle {
+    fn default() -> Self {
+        Self {
+            throttle_above: 0.75,
+            pause_above: 0.95,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PrefetchMode {
+    Normal,
+    Throttled,
+    Paused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+/// Strategy deciding which entry is evicted when the cache is full
+pub trait EvictionPolicy: Send + Sync + std::fmt::Debug {
+    /// Policy name for logging and diagnostics
+    fn name(&self) -> &str;
+
+    /// Retention score of an entry; the lowest-scoring entry is evicted first
+    fn score(&self, entry: &CacheEntry) -> f64;
+}
+
+/// Evicts the least recently accessed entry
+#[derive(Debug, Clone, Default)]
+pub struct LruPolicy;
+
+impl EvictionPolicy for LruPolicy {
+    fn name(&self) -> &str {
+        "lru"
+    }
+
+    fn score(&self, entry: &CacheEntry) -> f64 {
+        entry.timestamp as f64
+    }
+}
+
+/// Evicts the least frequently accessed entry
+#[derive(Debug, Clone, Default)]
+pub struct LfuPolicy;
+
+impl EvictionPolicy for LfuPolicy {
+    fn name(&self) -> &str {
+        "lfu"
+    }
+
+    fn score(&self, entry: &CacheEntry) -> f64 {
+        entry.access_count as f64
+    }
+}
+
+/// Evicts the entry that is cheapest to recompute, weighted by access frequency
+#[derive(Debug, Clone, Default)]
+pub struct CostWeightedPolicy;
+
+impl EvictionPolicy for CostWeightedPolicy {
+    fn name(&self) -> &str {
+        "cost_weighted"
+    }
+
+    fn score(&self, entry: &CacheEntry) -> f64 {
+        entry.computation_cost * entry.access_count as f64
+    }
+}
+
+/// Namespace used by the plain `get`/`put` methods
+pub const DEFAULT_NAMESPACE: &str = "";
+
+/// Limits for one namespace, enforced inside the cache-wide budgets
+///
+/// A namespace at its quota evicts its own lowest-scoring entries rather
+/// than another tenant's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceQuota {
+    pub max_entries: Option<usize>,
+    pub max_memory_bytes: Option<usize>,
+}
+
+/// Per-namespace statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceStats {
+    pub namespace: String,
+    pub total_entries: usize,
+    pub memory_usage_bytes: usize,
+    pub total_hits: usize,
+    pub total_misses: usize,
+    pub hit_rate: f64,
+    pub quota: Option<NamespaceQuota>,
+}
+
+/// Cache configuration
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub max_entries: usize,
+    /// Upper bound on the summed `size_bytes` of all entries
+    pub max_memory_bytes: Option<usize>,
+    pub eviction_policy: Arc<dyn EvictionPolicy>,
+    /// Age after which an entry is no longer served by `get`
+    pub ttl: Option<Duration>,
+    /// Extra window past `ttl` during which `get_or_revalidate` serves stale values
+    pub stale_while_revalidate: Option<Duration>,
+    /// Storage for entries and the vertex index
+    pub backend: Arc<dyn CacheBackend>,
+    /// Broadcasts local invalidations to other replicas
+    pub invalidation_bus: Option<Arc<dyn InvalidationBus>>,
+    /// Precision embeddings are stored at; reads always return `f64`
+    pub embedding_quantization: EmbeddingQuantization,
+    /// Graph adjacency used to cascade invalidations
+    pub neighbor_provider: Option<Arc<dyn NeighborProvider>>,
+    /// Populates vertices that `prefetch` finds missing
+    pub loader: Option<Arc<dyn CacheLoader>>,
+    /// Loader calls `prefetch` runs at once
+    pub prefetch_concurrency: usize,
+    /// Load of the streams or scheduler sharing this cache; prefetch yields to it
+    pub load_signal: Option<Arc<dyn LoadSignal>>,
+    pub prefetch_throttle: PrefetchThrottle,
+    /// Second tier receiving entries evicted from `backend`; hits are promoted back
+    pub l2_backend: Option<Arc<dyn CacheBackend>>,
+    pub l2_max_entries: usize,
+    /// Log of mutations replayed by `recover_from_wal` after a crash
+    pub wal: Option<Arc<WriteAheadLog>>,
+    /// Quotas by namespace; namespaces without one share the global budgets freely
+    pub namespace_quotas: HashMap<String, NamespaceQuota>,
+    /// Bloom filter answering definite misses without a backend lookup
+    ///
+    /// Only for backends this cache alone writes to: entries another process
+    /// adds to a shared backend are invisible to the filter and read as misses.
+    pub negative_lookup_filter: Option<BloomConfig>,
+    /// Buffered events per subscriber before slow receivers start lagging
+    pub event_capacity: usize,
+    /// Emit `CacheEvent::HitRateDrop` when a window's hit rate falls below this
+    pub hit_rate_alert: Option<f64>,
+    /// Lookups per hit-rate window
+    pub hit_rate_window: usize,
+    /// Keep this many recent admission and eviction decisions for inspection
+    pub decision_log_capacity: Option<usize>,
+    /// Vertex ids are grouped for per-prefix stats up to this character
+    pub metrics_prefix_delimiter: char,
+    /// Distinct prefixes tracked before the rest are counted together
+    pub max_metric_prefixes: usize,
+    /// Source of entry timestamps, TTL ages and decision times
+    pub clock: Arc<dyn Clock>,
+    /// Source of invalidation message ids
+    pub ids: Arc<dyn IdGenerator>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_memory_bytes: None,
+            eviction_policy: Arc::new(LruPolicy),
+            ttl: None,
+            stale_while_revalidate: None,
+            backend: Arc::new(InMemoryBackend::new()),
+            invalidation_bus: None,
+            embedding_quantization: EmbeddingQuantization::None,
+            neighbor_provider: None,
+            loader: None,
+            prefetch_concurrency: 8,
+            load_signal: None,
+            prefetch_throttle: PrefetchThrottle::default(),
+            l2_backend: None,
+            l2_max_entries: 100_000,
+            wal: None,
+            namespace_quotas: HashMap::new(),
+            negative_lookup_filter: None,
+            event_capacity: 1024,
+            hit_rate_alert: None,
+            hit_rate_window: 100,
+            decision_log_capacity: None,
+            metrics_prefix_delimiter: '_',
+            max_metric_prefixes: 64,
+            clock: clock::system_clock(),
+            ids: clock::uuid_ids(),
+        }
+    }
 }
 
+/// Outcome of one in-flight computation, shared by every caller waiting on it
+type Flight = Arc<OnceCell<std::result::Result<CacheValue, String>>>;
+
 /// Vertex-centric cache with intelligent reuse
+///
+/// Cloning is cheap and yields a handle onto the same shared storage.
+#[derive(Clone)]
 pub struct VertexCentricCache {
-    cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
-    vertex_index: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    backend: Arc<dyn CacheBackend>,
+    admission: Arc<Mutex<()>>,
     max_entries: usize,
-    hits: Arc<RwLock<usize>>,
-    misses: Arc<RwLock<usize>>,
+    max_memory_bytes: Option<usize>,
+    eviction_policy: Arc<dyn EvictionPolicy>,
+    pinned: Arc<RwLock<HashMap<String, usize>>>,
+    ttl: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    in_flight: Arc<Mutex<HashMap<String, Flight>>>,
+    listeners: Arc<RwLock<Vec<Arc<dyn CacheListener>>>>,
+    invalidation_bus: Option<Arc<dyn InvalidationBus>>,
+    embedding_quantization: EmbeddingQuantization,
+    neighbor_provider: Option<Arc<dyn NeighborProvider>>,
+    loader: Option<Arc<dyn CacheLoader>>,
+    prefetch_limit: Arc<Semaphore>,
+    load_signal: Option<Arc<dyn LoadSignal>>,
+    prefetch_throttle: PrefetchThrottle,
+    l2: Option<Arc<dyn CacheBackend>>,
+    l2_max_entries: usize,
+    l2_hits: Arc<AtomicUsize>,
+    wal: Option<Arc<WriteAheadLog>>,
+    namespace_quotas: Arc<RwLock<HashMap<String, NamespaceQuota>>>,
+    /// (hits, misses) by namespace
+    namespace_lookups: Arc<std::sync::Mutex<HashMap<String, (usize, usize)>>>,
+    /// (entries, bytes) by namespace in the primary tier; `None` until the
+    /// first quota check counts the backend
+    namespace_usage: Arc<std::sync::Mutex<Option<HashMap<String, (usize, usize)>>>>,
+    bloom: Option<Arc<NegativeLookupFilter>>,
+    events: broadcast::Sender<CacheEvent>,
+    hit_rate_alert: Option<f64>,
+    hit_rate_window_size: usize,
+    hit_rate_window: Arc<std::sync::Mutex<HitRateWindow>>,
+    decisions: Option<Arc<DecisionLog>>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+    metrics: Arc<CacheMetrics>,
+    hits: Arc<AtomicUsize>,
+    misses: Arc<AtomicUsize>,
 }
 
 impl VertexCentricCache {
     pub fn new(max_entries: usize) -> Self {
-        Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            vertex_index: Arc::new(RwLock::new(HashMap::new())),
+        Self::with_config(CacheConfig {
             max_entries,
-            hits: Arc::new(RwLock::new(0)),
-            misses: Arc::new(RwLock::new(0)),
+            ..CacheConfig::default()
+        })
+    }
+
+    /// Create a cache with an explicit configuration (e.g. a non-default eviction policy)
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self {
+            backend: config.backend,
+            admission: Arc::new(Mutex::new(())),
+            max_entries: config.max_entries,
+            max_memory_bytes: config.max_memory_bytes,
+            eviction_policy: config.eviction_policy,
+            pinned: Arc::new(RwLock::new(HashMap::new())),
+            ttl: config.ttl,
+            stale_while_revalidate: config.stale_while_revalidate,
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            listeners: Arc::new(RwLock::new(Vec::new())),
+            invalidation_bus: config.invalidation_bus,
+            embedding_quantization: config.embedding_quantization,
+            neighbor_provider: config.neighbor_provider,
+            loader: config.loader,
+            prefetch_limit: Arc::new(Semaphore::new(config.prefetch_concurrency.max(1))),
+            load_signal: config.load_signal,
+            prefetch_throttle: config.prefetch_throttle,
+            l2: config.l2_backend,
+            l2_max_entries: config.l2_max_entries,
+            l2_hits: Arc::new(AtomicUsize::new(0)),
+            wal: config.wal,
+            namespace_quotas: Arc::new(RwLock::new(config.namespace_quotas)),
+            namespace_lookups: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            namespace_usage: Arc::new(std::sync::Mutex::new(None)),
+            bloom: config.negative_lookup_filter.map(|c| Arc::new(NegativeLookupFilter::new(c))),
+            events: broadcast::channel(config.event_capacity.max(1)).0,
+            hit_rate_alert: config.hit_rate_alert,
+            hit_rate_window_size: config.hit_rate_window.max(1),
+            hit_rate_window: Arc::new(std::sync::Mutex::new(HitRateWindow::default())),
+            decisions: config.decision_log_capacity.map(|capacity| Arc::new(DecisionLog::new(capacity))),
+            clock: config.clock,
+            ids: config.ids,
+            metrics: Arc::new(CacheMetrics::new(config.metrics_prefix_delimiter, config.max_metric_prefixes)),
+            hits: Arc::new(AtomicUsize::new(0)),
+            misses: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    /// Get cached value for vertex
+    /// Get cached embedding for vertex
+    ///
+    /// Entries holding non-embedding values are reported as `None`; use
+    /// `get_value` to read any value type.
     pub async fn get(&self, vertex_id: &str, key: &str) -> Option<Vec<f64>> {
-        let cache_key = self.make_cache_key(vertex_id, key);
-        let mut cache = self.cache.write().await;
+        self.get_value(vertex_id, key).await.and_then(CacheValue::into_embedding)
+    }
+
+    /// Get cached value of any type for vertex
+    pub async fn get_value(&self, vertex_id: &str, key: &str) -> Option<CacheValue> {
+        self.get_value_in(DEFAULT_NAMESPACE, vertex_id, key).await
+    }
+
+    async fn get_value_in(&self, namespace: &str, vertex_id: &str, key: &str) -> Option<CacheValue> {
+        let cache_key = Self::namespaced_key(namespace, vertex_id, key);
+        let now = self.current_timestamp();
+        self.seed_filter().await;
         
-        if let Some(entry) = cache.get_mut(&cache_key) {
-            // Update access count
-            entry.access_count += 1;
-            entry.timestamp = self.current_timestamp();
-            
-            // Record hit
-            let mut hits = self.hits.write().await;
-            *hits += 1;
-            
-            Some(entry.value.clone())
+        let value = if self.may_contain(&cache_key) {
+            let cached = self.lookup(&cache_key).await;
+            let found = cached.is_some();
+            let value = match cached.filter(|entry| self.freshness(entry, now) == Freshness::Fresh) {
+                Some(entry) => {
+                    // Update access count
+                    self.touch(&cache_key, now).await;
+                    Some(entry.value.dequantized())
+                }
+                None => self.promote(&cache_key, now).await.map(|entry| entry.value.dequantized()),
+            };
+            if !found && value.is_none() {
+                self.record_false_positive();
+            }
+            value
         } else {
-            // Record miss
-            let mut misses = self.misses.write().await;
-            *misses += 1;
             None
-        }
+        };
+        
+        self.record_lookups([(vertex_id, value.is_some())]);
+        self.record_namespace_lookup(namespace, value.is_some());
+        value
     }
 
-    /// Store value in cache
+    /// Store embedding in cache
     pub async fn put(
         &self,
         vertex_id: &str,
@@ -82,148 +729,1731 @@ impl VertexCentricCache {
         value: Vec<f64>,
         computation_cost: f64,
     ) -> Result<()> {
-        let cache_key = self.make_cache_key(vertex_id, key);
-        
-        // Check if cache is full
-        let mut cache = self.cache.write().await;
-        if cache.len() >= self.max_entries {
-            self.evict_lru(&mut cache).await;
-        }
+        self.put_tagged(vertex_id, key, value, computation_cost, Vec::new()).await
+    }
+
+    /// Store value of any type in cache
+    pub async fn put_value(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        value: impl Into<CacheValue>,
+        computation_cost: f64,
+    ) -> Result<()> {
+        self.put_tagged(vertex_id, key, value, computation_cost, Vec::new()).await
+    }
+
+    /// Store value in cache with tags usable by `invalidate_tag`
+    pub async fn put_tagged(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        value: impl Into<CacheValue>,
+        computation_cost: f64,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        self.put_tagged_in(DEFAULT_NAMESPACE, vertex_id, key, value.into(), computation_cost, tags).await
+    }
+
+    async fn put_tagged_in(
+        &self,
+        namespace: &str,
+        vertex_id: &str,
+        key: &str,
+        value: CacheValue,
+        computation_cost: f64,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        let start = std::time::Instant::now();
+        let value = self.quantize(value);
+        let cache_key = Self::namespaced_key(namespace, vertex_id, key);
         
-        let entry = CacheEntry {
+        let now = self.current_timestamp();
+        let mut entry = CacheEntry {
             vertex_id: vertex_id.to_string(),
             key: key.to_string(),
             value,
-            timestamp: self.current_timestamp(),
+            timestamp: now,
             access_count: 1,
             computation_cost,
+            inserted_at: now,
+            tags,
+            size_bytes: 0,
+            namespace: namespace.to_string(),
         };
+        entry.size_bytes = entry.estimated_size();
         
-        cache.insert(cache_key.clone(), entry);
-        
-        // Update vertex index
-        let mut index = self.vertex_index.write().await;
-        index.entry(vertex_id.to_string())
-            .or_insert_with(Vec::new)
-            .push(cache_key);
+        // Serialize admissions so concurrent puts cannot overshoot the budgets
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        self.make_room_in_namespace(namespace, &cache_key, entry.size_bytes).await?;
+        self.make_room(&cache_key, entry.size_bytes).await?;
+        self.log(|| WalOp::Put { cache_key: cache_key.clone(), entry: entry.clone() }).await?;
         
+        // Backend maintains the vertex index alongside the entry
+        let event = Self::inserted_event(&entry);
+        let score = self.eviction_policy.score(&entry);
+        self.store(&cache_key, entry).await?;
+        self.emit(event);
+        self.record_admission(&cache_key, score, DecisionReason::Put);
+        self.rebuild_filter_if_stale().await?;
+        self.metrics.record_put(start.elapsed().as_secs_f64());
         Ok(())
     }
 
-    /// Get all cached entries for a vertex
-    pub async fn get_vertex_entries(&self, vertex_id: &str) -> Vec<CacheEntry> {
-        let index = self.vertex_index.read().await;
-        let cache = self.cache.read().await;
-        
-        if let Some(keys) = index.get(vertex_id) {
-            keys.iter()
-                .filter_map(|k| cache.get(k).cloned())
-                .collect()
-        } else {
-            Vec::new()
-        }
+    /// Look up several values at once, returning results in request order
+    ///
+    /// The backend is queried in a single batch and hits are recorded in a
+    /// second one, instead of two round trips per key.
+    pub async fn get_many(&self, keys: &[(&str, &str)]) -> Vec<Option<CacheValue>> {
+        self.get_many_in(DEFAULT_NAMESPACE, keys).await
     }
 
-    /// Invalidate cache for vertex
-    pub async fn invalidate_vertex(&self, vertex_id: &str) -> Result<()> {
-        let mut index = self.vertex_index.write().await;
-        let mut cache = self.cache.write().await;
+    async fn get_many_in(&self, namespace: &str, keys: &[(&str, &str)]) -> Vec<Option<CacheValue>> {
+        let cache_keys: Vec<String> = keys.iter()
+            .map(|(vertex_id, key)| Self::namespaced_key(namespace, vertex_id, key))
+            .collect();
+        let now = self.current_timestamp();
+        self.seed_filter().await;
         
-        if let Some(keys) = index.remove(vertex_id) {
-            for key in keys {
-                cache.remove(&key);
+        // Definite misses are left out of the backend batch
+        let candidates: Vec<usize> = (0..cache_keys.len())
+            .filter(|&i| self.may_contain(&cache_keys[i]))
+            .collect();
+        let candidate_keys: Vec<String> = candidates.iter().map(|&i| cache_keys[i].clone()).collect();
+        let fetched = match self.backend.get_many(&candidate_keys).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Cache backend {} batch lookup failed: {:?}", self.backend.name(), e);
+                vec![None; candidate_keys.len()]
             }
+        };
+        let mut entries: Vec<Option<CacheEntry>> = vec![None; cache_keys.len()];
+        let mut found = vec![false; cache_keys.len()];
+        for (i, entry) in candidates.iter().copied().zip(fetched) {
+            found[i] = entry.is_some();
+            entries[i] = entry;
         }
         
-        Ok(())
+        let mut hit_keys = Vec::new();
+        let mut values: Vec<Option<CacheValue>> = entries.into_iter()
+            .zip(&cache_keys)
+            .map(|(entry, cache_key)| {
+                let entry = entry.filter(|e| self.freshness(e, now) == Freshness::Fresh)?;
+                hit_keys.push(cache_key.clone());
+                Some(entry.value.dequantized())
+            })
+            .collect();
+        
+        for i in candidates {
+            if values[i].is_none() && self.l2.is_some() {
+                values[i] = self.promote(&cache_keys[i], now).await.map(|entry| entry.value.dequantized());
+            }
+            if !found[i] && values[i].is_none() {
+                self.record_false_positive();
+            }
+        }
+        
+        self.record_lookups(keys.iter().zip(&values).map(|((vertex_id, _), value)| (*vertex_id, value.is_some())));
+        for value in &values {
+            self.record_namespace_lookup(namespace, value.is_some());
+        }
+        if let Err(e) = self.backend.record_access_many(&hit_keys, now).await {
+            tracing::warn!("Cache backend {} failed to record batch access: {:?}", self.backend.name(), e);
+        }
+        
+        values
     }
 
-    /// Get cache statistics
-    pub async fn get_stats(&self) -> CacheStats {
-        let cache = self.cache.read().await;
-        let hits = *self.hits.read().await;
-        let misses = *self.misses.read().await;
+    /// Store several values under one admission
+    ///
+    /// When the whole batch fits under the entry and memory budgets it is
+    /// written with a single backend call; otherwise entries are admitted one
+    /// at a time so eviction can make room between them.
+    pub async fn put_many<V: Into<CacheValue>>(&self, items: Vec<(&str, &str, V, f64)>) -> Result<()> {
+        self.put_many_in(DEFAULT_NAMESPACE, items).await
+    }
+
+    async fn put_many_in<V: Into<CacheValue>>(&self, namespace: &str, items: Vec<(&str, &str, V, f64)>) -> Result<()> {
+        let start = std::time::Instant::now();
+        let now = self.current_timestamp();
+        let entries: Vec<(String, CacheEntry)> = items.into_iter()
+            .map(|(vertex_id, key, value, computation_cost)| {
+                let mut entry = CacheEntry {
+                    vertex_id: vertex_id.to_string(),
+                    key: key.to_string(),
+                    value: self.quantize(value.into()),
+                    timestamp: now,
+                    access_count: 1,
+                    computation_cost,
+                    inserted_at: now,
+                    tags: Vec::new(),
+                    size_bytes: 0,
+                    namespace: namespace.to_string(),
+                };
+                entry.size_bytes = entry.estimated_size();
+                (Self::namespaced_key(namespace, vertex_id, key), entry)
+            })
+            .collect();
         
-        let total_requests = hits + misses;
-        let hit_rate = if total_requests > 0 {
-            hits as f64 / total_requests as f64
-        } else {
-            0.0
-        };
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
         
-        let avg_access_count = if !cache.is_empty() {
-            cache.values()
-                .map(|e| e.access_count as f64)
-                .sum::<f64>() / cache.len() as f64
-        } else {
-            0.0
-        };
+        let cache_keys: Vec<String> = entries.iter().map(|(k, _)| k.clone()).collect();
+        let existing = self.backend.get_many(&cache_keys).await?;
+        let new_keys: HashSet<&String> = cache_keys.iter()
+            .zip(&existing)
+            .filter(|(_, e)| e.is_none())
+            .map(|(k, _)| k)
+            .collect();
+        let replaced_bytes: usize = existing.iter().flatten().map(|e| e.size_bytes).sum();
+        let added_bytes: usize = entries.iter().map(|(_, e)| e.size_bytes).sum();
         
-        // Estimate memory usage (rough approximation)
-        let memory_usage_mb = (cache.len() * 1024) as f64 / (1024.0 * 1024.0);
+        let fits_entries = self.backend.len().await? + new_keys.len() <= self.max_entries;
+        let fits_memory = match self.max_memory_bytes {
+            Some(max_memory) => {
+                self.backend.memory_bytes().await?.saturating_sub(replaced_bytes) + added_bytes <= max_memory
+            }
+            None => true,
+        };
+        let has_quota = self.namespace_quotas.read().await.contains_key(namespace);
+        if fits_entries && fits_memory && !has_quota {
+            for (cache_key, entry) in &entries {
+                self.log(|| WalOp::Put { cache_key: cache_key.clone(), entry: entry.clone() }).await?;
+            }
+            let events: Vec<CacheEvent> = entries.iter().map(|(_, e)| Self::inserted_event(e)).collect();
+            if let Some(filter) = &self.bloom {
+                entries.iter().for_each(|(cache_key, _)| filter.insert(cache_key));
+            }
+            let scores: Vec<(String, f64)> = entries.iter()
+                .map(|(cache_key, e)| (cache_key.clone(), self.eviction_policy.score(e)))
+                .collect();
+            // Each entry replaces the one before it under its key, which for
+            // a key repeated in the batch is the batch's own earlier entry
+            let mut current: HashMap<&String, usize> = cache_keys.iter()
+                .zip(&existing)
+                .filter_map(|(cache_key, e)| Some((cache_key, e.as_ref()?.size_bytes)))
+                .collect();
+            let mut usage_changes = Vec::new();
+            for (cache_key, entry) in &entries {
+                if let Some(replaced_bytes) = current.insert(cache_key, entry.size_bytes) {
+                    usage_changes.push((replaced_bytes, false));
+                }
+                usage_changes.push((entry.size_bytes, true));
+            }
+            self.backend.insert_many(entries).await?;
+            for (size_bytes, added) in usage_changes {
+                self.track_usage(namespace, size_bytes, added);
+            }
+            events.into_iter().for_each(|event| self.emit(event));
+            for (cache_key, score) in scores {
+                self.record_admission(&cache_key, score, DecisionReason::Put);
+            }
+            self.metrics.record_put(start.elapsed().as_secs_f64());
+            return Ok(());
+        }
         
-        CacheStats {
-            total_entries: cache.len(),
-            total_hits: hits,
-            total_misses: misses,
-            hit_rate,
-            avg_access_count,
-            memory_usage_mb,
+        for (cache_key, entry) in entries {
+            self.make_room_in_namespace(namespace, &cache_key, entry.size_bytes).await?;
+            self.make_room(&cache_key, entry.size_bytes).await?;
+            self.log(|| WalOp::Put { cache_key: cache_key.clone(), entry: entry.clone() }).await?;
+            let event = Self::inserted_event(&entry);
+            let score = self.eviction_policy.score(&entry);
+            self.store(&cache_key, entry).await?;
+            self.emit(event);
+            self.record_admission(&cache_key, score, DecisionReason::Put);
         }
+        self.rebuild_filter_if_stale().await?;
+        self.metrics.record_put(start.elapsed().as_secs_f64());
+        Ok(())
     }
 
-    /// Clear entire cache
-    pub async fn clear(&self) -> Result<()> {
-        let mut cache = self.cache.write().await;
-        let mut index = self.vertex_index.write().await;
-        let mut hits = self.hits.write().await;
-        let mut misses = self.misses.write().await;
+    /// Evict until an entry of `size_bytes` fits under both `max_entries` and `max_memory_bytes`
+    async fn make_room(&self, cache_key: &str, size_bytes: usize) -> Result<()> {
+        if let Some(max_memory) = self.max_memory_bytes {
+            if size_bytes > max_memory {
+                return Err(Error::Cache(format!(
+                    "entry {} of {} bytes exceeds the {} byte memory budget",
+                    cache_key, size_bytes, max_memory
+                )));
+            }
+        }
         
-        cache.clear();
-        index.clear();
-        *hits = 0;
-        *misses = 0;
+        // A replaced entry frees its own slot and bytes
+        let replaced = self.backend.get(cache_key).await?;
+        let replaced_bytes = replaced.as_ref().map(|e| e.size_bytes).unwrap_or(0);
+        
+        if replaced.is_none() && self.backend.len().await? >= self.max_entries {
+            self.evict(Some(cache_key), None, DecisionReason::EntryLimit).await?;
+        }
+        if let Some(max_memory) = self.max_memory_bytes {
+            while self.backend.memory_bytes().await?.saturating_sub(replaced_bytes) + size_bytes > max_memory {
+                if !self.evict(Some(cache_key), None, DecisionReason::MemoryLimit).await? {
+                    break;
+                }
+            }
+        }
         
         Ok(())
     }
 
-    fn make_cache_key(&self, vertex_id: &str, key: &str) -> String {
-        format!("{}:{}", vertex_id, key)
-    }
-
-    fn current_timestamp(&self) -> u64 {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+    /// Evict within `namespace` until an entry of `size_bytes` fits its quota
+    async fn make_room_in_namespace(&self, namespace: &str, cache_key: &str, size_bytes: usize) -> Result<()> {
+        let quota = match self.namespace_quotas.read().await.get(namespace).copied() {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        if quota.max_memory_bytes.is_some_and(|max| size_bytes > max) {
+            return Err(Error::Cache(format!(
+                "entry {} of {} bytes exceeds the memory quota of namespace '{}'",
+                cache_key, size_bytes, namespace
+            )));
+        }
+        
+        // The entry being replaced, if any, frees its own slot and bytes
+        let replaced_bytes = self.backend.get(cache_key).await?.map(|e| e.size_bytes);
+        while !self.fits_quota(&quota, namespace, replaced_bytes, size_bytes).await? {
+            let reason = DecisionReason::NamespaceQuota(namespace.to_string());
+            if !self.evict(Some(cache_key), Some(namespace), reason).await? {
+                break;
+            }
+        }
+        Ok(())
     }
 
-    async fn evict_lru(&self, cache: &mut HashMap<String, CacheEntry>) {
-        // Find least recently used entry
-        if let Some((key_to_remove, _)) = cache.iter()
-            .min_by_key(|(_, entry)| entry.timestamp)
-        {
-            let key_to_remove = key_to_remove.clone();
-            cache.remove(&key_to_remove);
+    /// Whether `namespace` can take an entry of `size_bytes`, replacing one
+    /// of `replaced_bytes` if any, without evicting
+    async fn fits_quota(
+        &self,
+        quota: &NamespaceQuota,
+        namespace: &str,
+        replaced_bytes: Option<usize>,
+        size_bytes: usize,
+    ) -> Result<bool> {
+        let (mut count, mut bytes) = self.namespace_usage(namespace).await?;
+        if let Some(replaced_bytes) = replaced_bytes {
+            count = count.saturating_sub(1);
+            bytes = bytes.saturating_sub(replaced_bytes);
         }
+        
+        Ok(quota.max_entries.map_or(true, |max| count < max)
+            && quota.max_memory_bytes.map_or(true, |max| bytes + size_bytes <= max))
     }
 
-    /// Prefetch entries for vertices
-    pub async fn prefetch(&self, vertex_ids: &[String]) -> Result<usize> {
-        let mut prefetched = 0;
-        
-        for vertex_id in vertex_ids {
-            let entries = self.get_vertex_entries(vertex_id).await;
-            prefetched += entries.len();
+    /// Entries and bytes `namespace` holds in the primary tier
+    ///
+    /// The first call counts the backend's entries; later calls read the
+    /// counters kept up by `track_usage`. Callers hold the admission lock,
+    /// as do all writers, so no write lands between the count and the seed.
+    async fn namespace_usage(&self, namespace: &str) -> Result<(usize, usize)> {
+        if let Some(usage) = self.namespace_usage.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return Ok(usage.get(namespace).copied().unwrap_or((0, 0)));
         }
         
-        Ok(prefetched)
+        let mut usage: HashMap<String, (usize, usize)> = HashMap::new();
+        for (_, entry) in self.backend.entries().await? {
+            let (count, bytes) = usage.entry(entry.namespace).or_insert((0, 0));
+            *count += 1;
+            *bytes += entry.size_bytes;
+        }
+        let counts = usage.get(namespace).copied().unwrap_or((0, 0));
+        *self.namespace_usage.lock().unwrap_or_else(|e| e.into_inner()) = Some(usage);
+        Ok(counts)
+    }
+
+    /// Whether writes to the primary tier must be counted
+    fn tracks_usage(&self) -> bool {
+        self.namespace_usage.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+    }
+
+    /// Count an entry of `size_bytes` added to or removed from the primary tier
+    fn track_usage(&self, namespace: &str, size_bytes: usize, added: bool) {
+        let mut usage = self.namespace_usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(usage) = usage.as_mut() {
+            let (count, bytes) = usage.entry(namespace.to_string()).or_insert((0, 0));
+            if added {
+                *count += 1;
+                *bytes += size_bytes;
+            } else {
+                *count = count.saturating_sub(1);
+                *bytes = bytes.saturating_sub(size_bytes);
+            }
+        }
+    }
+
+    /// Forget the counters; the next quota check counts the backend again
+    fn reset_usage(&self) {
+        *self.namespace_usage.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Handle whose reads and writes are scoped to `namespace`
+    pub fn namespace(&self, namespace: &str) -> CacheNamespace {
+        CacheNamespace {
+            cache: self.clone(),
+            name: namespace.to_string(),
+        }
+    }
+
+    /// Set or replace a namespace quota; existing entries over it are trimmed on the next put
+    pub async fn set_namespace_quota(&self, namespace: &str, quota: NamespaceQuota) {
+        self.namespace_quotas.write().await.insert(namespace.to_string(), quota);
+    }
+
+    pub async fn namespace_stats(&self, namespace: &str) -> NamespaceStats {
+        let (total_entries, memory_usage_bytes) = match self.backend.entries().await {
+            Ok(entries) => entries.iter()
+                .filter(|(_, entry)| entry.namespace == namespace)
+                .fold((0, 0), |(count, bytes), (_, entry)| (count + 1, bytes + entry.size_bytes)),
+            Err(e) => {
+                tracing::warn!("Cache backend {} failed to list entries: {:?}", self.backend.name(), e);
+                (0, 0)
+            }
+        };
+        let (hits, misses) = self.namespace_lookups.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(namespace)
+            .copied()
+            .unwrap_or((0, 0));
+        
+        NamespaceStats {
+            namespace: namespace.to_string(),
+            total_entries,
+            memory_usage_bytes,
+            total_hits: hits,
+            total_misses: misses,
+            hit_rate: if hits + misses > 0 { hits as f64 / (hits + misses) as f64 } else { 0.0 },
+            quota: self.namespace_quotas.read().await.get(namespace).copied(),
+        }
+    }
+
+    /// Drop every entry in `namespace` from both tiers, returning how many were removed
+    pub async fn clear_namespace(&self, namespace: &str) -> Result<usize> {
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        let mut removed = 0;
+        
+        for backend in std::iter::once(&self.backend).chain(self.l2.as_ref()) {
+            let keys: Vec<String> = backend.entries().await?
+                .into_iter()
+                .filter(|(_, entry)| entry.namespace == namespace)
+                .map(|(cache_key, _)| cache_key)
+                .collect();
+            let primary = Arc::ptr_eq(backend, &self.backend);
+            for cache_key in keys {
+                self.log(|| WalOp::Remove { cache_key: cache_key.clone() }).await?;
+                if let Some(entry) = backend.remove(&cache_key).await? {
+                    removed += 1;
+                    if primary {
+                        self.track_usage(namespace, entry.size_bytes, false);
+                    }
+                }
+            }
+        }
+        
+        self.record_removals(removed);
+        self.rebuild_filter_if_stale().await?;
+        self.namespace_lookups.lock().unwrap_or_else(|e| e.into_inner()).remove(namespace);
+        Ok(removed)
+    }
+
+    fn record_namespace_lookup(&self, namespace: &str, hit: bool) {
+        let mut lookups = self.namespace_lookups.lock().unwrap_or_else(|e| e.into_inner());
+        let (hits, misses) = lookups.entry(namespace.to_string()).or_insert((0, 0));
+        if hit {
+            *hits += 1;
+        } else {
+            *misses += 1;
+        }
+    }
+
+    /// Get all cached entries for a vertex
+    pub async fn get_vertex_entries(&self, vertex_id: &str) -> Vec<CacheEntry> {
+        match self.backend.vertex_entries(vertex_id).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Cache backend {} failed to list vertex {}: {:?}", self.backend.name(), vertex_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Invalidate cache for vertex
+    pub async fn invalidate_vertex(&self, vertex_id: &str) -> Result<()> {
+        let target = InvalidationTarget::Vertex(vertex_id.to_string());
+        self.remove_target(&target).await?;
+        self.broadcast(target).await
+    }
+
+    /// Invalidate a vertex and every vertex within `depth` hops of it
+    ///
+    /// Neighbors come from the configured `NeighborProvider`; a depth of 0 is
+    /// equivalent to `invalidate_vertex`. The resolved set is broadcast as a
+    /// single message so replicas need no graph access of their own. Returns
+    /// the invalidated vertices in breadth-first order.
+    pub async fn invalidate_neighborhood(&self, vertex_id: &str, depth: usize) -> Result<Vec<String>> {
+        let provider = match (&self.neighbor_provider, depth) {
+            (_, 0) => None,
+            (Some(provider), _) => Some(provider),
+            (None, _) => {
+                return Err(Error::Cache(format!(
+                    "cannot invalidate the neighborhood of {}: no neighbor provider configured",
+                    vertex_id
+                )));
+            }
+        };
+        
+        let mut visited = vec![vertex_id.to_string()];
+        let mut seen: HashSet<String> = visited.iter().cloned().collect();
+        let mut queue = VecDeque::from([(vertex_id.to_string(), 0)]);
+        
+        while let Some((current, distance)) = queue.pop_front() {
+            let provider = match provider {
+                Some(provider) if distance < depth => provider,
+                _ => continue,
+            };
+            for neighbor in provider.neighbors(&current).await? {
+                if seen.insert(neighbor.clone()) {
+                    visited.push(neighbor.clone());
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+        
+        let target = InvalidationTarget::Vertices(visited.clone());
+        self.remove_target(&target).await?;
+        self.broadcast(target).await?;
+        
+        Ok(visited)
+    }
+
+    /// Invalidate every entry carrying `tag`
+    pub async fn invalidate_tag(&self, tag: &str) -> Result<()> {
+        let target = InvalidationTarget::Tag(tag.to_string());
+        self.remove_target(&target).await?;
+        self.broadcast(target).await
+    }
+
+    /// Remove what `target` names from the local cache and publish the event
+    async fn remove_target(&self, target: &InvalidationTarget) -> Result<usize> {
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        self.log(|| WalOp::Invalidate { target: target.clone() }).await?;
+        let mut removed = 0;
+        // Both tiers, so a stale L2 copy cannot be promoted afterwards
+        for backend in std::iter::once(&self.backend).chain(self.l2.as_ref()) {
+            let entries = match target {
+                InvalidationTarget::Vertex(vertex_id) => backend.remove_vertex(vertex_id).await?,
+                InvalidationTarget::Vertices(vertex_ids) => {
+                    let mut entries = Vec::new();
+                    for vertex_id in vertex_ids {
+                        entries.extend(backend.remove_vertex(vertex_id).await?);
+                    }
+                    entries
+                }
+                InvalidationTarget::Tag(tag) => Self::remove_tagged(backend, tag).await?,
+            };
+            if Arc::ptr_eq(backend, &self.backend) {
+                for entry in &entries {
+                    self.track_usage(&entry.namespace, entry.size_bytes, false);
+                }
+            }
+            removed += entries.len();
+        }
+        
+        self.record_removals(removed);
+        self.rebuild_filter_if_stale().await?;
+        
+        self.emit(CacheEvent::Invalidated {
+            target: target.clone(),
+            removed,
+        });
+        Ok(removed)
+    }
+
+    async fn remove_tagged(backend: &Arc<dyn CacheBackend>, tag: &str) -> Result<Vec<CacheEntry>> {
+        let tagged: Vec<String> = backend.entries().await?
+            .into_iter()
+            .filter(|(_, entry)| entry.tags.iter().any(|t| t == tag))
+            .map(|(cache_key, _)| cache_key)
+            .collect();
+        
+        let mut removed = Vec::with_capacity(tagged.len());
+        for cache_key in &tagged {
+            removed.extend(backend.remove(cache_key).await?);
+        }
+        
+        Ok(removed)
+    }
+
+    async fn broadcast(&self, target: InvalidationTarget) -> Result<()> {
+        if let Some(bus) = &self.invalidation_bus {
+            let message = InvalidationMessage::new(bus.replica_id(), target, self.clock.as_ref(), self.ids.as_ref());
+            bus.publish(&message).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply an invalidation received from another replica without re-broadcasting it
+    pub async fn apply_invalidation(&self, message: &InvalidationMessage) -> Result<()> {
+        self.remove_target(&message.target).await?;
+        Ok(())
+    }
+
+    /// Spawn a task applying invalidations from other replicas
+    ///
+    /// Returns `None` when no bus is configured. A delivery is acknowledged only
+    /// after it has been applied, so failures are retried on the next poll.
+    pub fn spawn_invalidation_listener(&self) -> Option<JoinHandle<()>> {
+        let bus = self.invalidation_bus.clone()?;
+        let cache = self.clone();
+        
+        Some(tokio::spawn(async move {
+            loop {
+                let deliveries = match bus.poll(64).await {
+                    Ok(deliveries) => deliveries,
+                    Err(e) => {
+                        tracing::warn!("Invalidation bus poll failed: {:?}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                
+                for delivery in deliveries {
+                    if delivery.message.origin != bus.replica_id() {
+                        if let Err(e) = cache.apply_invalidation(&delivery.message).await {
+                            tracing::warn!("Failed to apply invalidation {}: {:?}", delivery.message.message_id, e);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                    }
+                    if let Err(e) = bus.ack(&delivery.delivery_id).await {
+                        tracing::warn!("Failed to ack invalidation {}: {:?}", delivery.delivery_id, e);
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Get cache statistics
+    pub async fn get_stats(&self) -> CacheStats {
+        let entries = match self.backend.entries().await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Cache backend {} failed to list entries: {:?}", self.backend.name(), e);
+                Vec::new()
+            }
+        };
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        
+        let total_requests = hits + misses;
+        let hit_rate = if total_requests > 0 {
+            hits as f64 / total_requests as f64
+        } else {
+            0.0
+        };
+        
+        let l2_hits = self.l2_hits.load(Ordering::Relaxed);
+        let l1_hits = hits.saturating_sub(l2_hits);
+        let l1_misses = total_requests - l1_hits;
+        let l1_hit_rate = if total_requests > 0 { l1_hits as f64 / total_requests as f64 } else { 0.0 };
+        let l2_hit_rate = if l1_misses > 0 { l2_hits as f64 / l1_misses as f64 } else { 0.0 };
+        let l2_entries = match &self.l2 {
+            Some(l2) => l2.len().await.unwrap_or(0),
+            None => 0,
+        };
+        
+        let avg_access_count = if !entries.is_empty() {
+            entries.iter()
+                .map(|(_, e)| e.access_count as f64)
+                .sum::<f64>() / entries.len() as f64
+        } else {
+            0.0
+        };
+        
+        let memory_usage_bytes = match self.backend.memory_bytes().await {
+            Ok(bytes) => bytes,
+            Err(_) => entries.iter().map(|(_, e)| e.size_bytes).sum(),
+        };
+        let memory_usage_mb = memory_usage_bytes as f64 / (1024.0 * 1024.0);
+        
+        let (raw_bytes, stored_bytes) = entries.iter()
+            .fold((0, 0), |(raw, stored), (_, e)| match &e.value {
+                CacheValue::Embedding(values) => {
+                    let bytes = values.len() * std::mem::size_of::<f64>();
+                    (raw + bytes, stored + bytes)
+                }
+                CacheValue::Quantized(quantized) => (
+                    raw + quantized.dimensions() * std::mem::size_of::<f64>(),
+                    stored + quantized.stored_bytes(),
+                ),
+                _ => (raw, stored),
+            });
+        let embedding_compression_ratio = if stored_bytes > 0 {
+            raw_bytes as f64 / stored_bytes as f64
+        } else {
+            1.0
+        };
+        let metrics = self.metrics.snapshot();
+        
+        CacheStats {
+            total_entries: entries.len(),
+            total_hits: hits,
+            total_misses: misses,
+            hit_rate,
+            avg_access_count,
+            memory_usage_mb,
+            memory_usage_bytes,
+            l1_hit_rate,
+            l2_hit_rate,
+            l2_entries,
+            embedding_compression_ratio,
+            prefixes: metrics.prefixes,
+            evictions: metrics.evictions,
+            demotions: metrics.demotions,
+            put_latency: metrics.put_latency,
+            lock_waits: metrics.lock_waits,
+            prefetch: metrics.prefetch,
+            negative_lookup_filter: self.bloom.as_ref().map(|filter| filter.stats()),
+        }
+    }
+
+    /// Export lookup, eviction and put latency metrics to a Prometheus registry
+    ///
+    /// Collectors are named `<namespace>_cache_*` and update as the cache is
+    /// used, so the registry needs no polling. Each cache registers once.
+    pub fn register_metrics(&self, registry: &prometheus::Registry, namespace: &str) -> Result<()> {
+        self.metrics.register(registry, namespace)
+    }
+
+    /// Clear entire cache
+    pub async fn clear(&self) -> Result<()> {
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        self.log(|| WalOp::Clear).await?;
+        self.backend.clear().await?;
+        self.reset_usage();
+        if let Some(l2) = &self.l2 {
+            l2.clear().await?;
+        }
+        if let Some(filter) = &self.bloom {
+            filter.rebuild(std::iter::empty());
+        }
+        
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.l2_hits.store(0, Ordering::Relaxed);
+        self.metrics.reset();
+        
+        Ok(())
+    }
+
+    /// Subscribe to cache events
+    ///
+    /// Receivers that fall more than `event_capacity` events behind skip the
+    /// oldest ones and observe `RecvError::Lagged`.
+    pub fn subscribe(&self) -> broadcast::Receiver<CacheEvent> {
+        self.events.subscribe()
+    }
+
+    /// Admission and eviction decisions made within the last `window`, oldest first
+    ///
+    /// Always empty unless `CacheConfig::decision_log_capacity` is set.
+    pub fn recent_decisions(&self, window: Duration) -> Vec<CacheDecision> {
+        let now = self.clock.now_ms();
+        self.decisions_between(now.saturating_sub(window.as_millis() as u64), now + 1)
+    }
+
+    /// Decisions timestamped in `from_ms..to_ms`, in milliseconds since the Unix epoch
+    pub fn decisions_between(&self, from_ms: u64, to_ms: u64) -> Vec<CacheDecision> {
+        self.decisions.as_ref()
+            .map(|log| log.between(from_ms, to_ms))
+            .unwrap_or_default()
+    }
+
+    /// Append to the WAL ahead of applying a mutation, compacting in the background when due
+    async fn log(&self, op: impl FnOnce() -> WalOp) -> Result<()> {
+        let wal = match &self.wal {
+            Some(wal) => wal,
+            None => return Ok(()),
+        };
+        if wal.append(&op()).await? {
+            let wal = wal.clone();
+            tokio::spawn(async move {
+                if let Err(e) = wal.compact().await {
+                    tracing::warn!("WAL compaction failed: {:?}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn emit(&self, event: CacheEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+
+    /// Await a lock acquisition, recording the wait in the metrics and the
+    /// current request's contention profile
+    async fn acquire<G>(&self, lock: &'static str, acquisition: impl Future<Output = G>) -> G {
+        let start = std::time::Instant::now();
+        let guard = acquisition.await;
+        let waited = start.elapsed();
+        self.metrics.record_lock_wait(lock, waited.as_secs_f64());
+        contention::record_wait(lock, waited);
+        guard
+    }
+
+    /// Build and keep a decision, only when the decision log is enabled
+    fn record_decision(&self, decision: impl FnOnce() -> CacheDecision) {
+        if let Some(log) = &self.decisions {
+            log.record(decision());
+        }
+    }
+
+    fn record_admission(&self, cache_key: &str, score: f64, reason: DecisionReason) {
+        self.record_decision(|| CacheDecision {
+            timestamp_ms: self.clock.now_ms(),
+            kind: DecisionKind::Admitted,
+            cache_key: cache_key.to_string(),
+            reason,
+            policy: self.eviction_policy.name().to_string(),
+            score,
+            trigger: None,
+            candidates_considered: 0,
+            runners_up: Vec::new(),
+            pinned_fallback: false,
+        });
+    }
+
+    fn inserted_event(entry: &CacheEntry) -> CacheEvent {
+        CacheEvent::Inserted {
+            vertex_id: entry.vertex_id.clone(),
+            key: entry.key.clone(),
+            size_bytes: entry.size_bytes,
+        }
+    }
+
+    /// Count `(vertex_id, hit)` lookups and emit `HitRateDrop` when a window crosses below the alert threshold
+    fn record_lookups<'a>(&self, lookups: impl IntoIterator<Item = (&'a str, bool)>) {
+        let (mut hits, mut misses) = (0, 0);
+        for (vertex_id, hit) in lookups {
+            self.metrics.record_lookup(vertex_id, hit);
+            if hit {
+                hits += 1;
+            } else {
+                misses += 1;
+            }
+        }
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+        
+        let threshold = match self.hit_rate_alert {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let drop_event = {
+            let mut window = self.hit_rate_window.lock().unwrap_or_else(|e| e.into_inner());
+            window.hits += hits;
+            window.lookups += hits + misses;
+            if window.lookups < self.hit_rate_window_size {
+                return;
+            }
+            
+            let hit_rate = window.hits as f64 / window.lookups as f64;
+            let was_below = window.below_threshold;
+            *window = HitRateWindow {
+                below_threshold: hit_rate < threshold,
+                ..HitRateWindow::default()
+            };
+            // Alert on the transition only, not on every low window
+            (hit_rate < threshold && !was_below).then_some(CacheEvent::HitRateDrop { hit_rate, threshold })
+        };
+        if let Some(event) = drop_event {
+            self.emit(event);
+        }
+    }
+
+    /// Append every evicted entry to `path` as JSON Lines of `CacheEntry`
+    ///
+    /// Runs until the cache and all its handles are dropped, returning the
+    /// number of entries written.
+    pub fn spawn_eviction_persister(&self, path: PathBuf) -> JoinHandle<Result<usize>> {
+        let mut events = self.subscribe();
+        
+        tokio::spawn(async move {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await?;
+            let mut persisted = 0;
+            
+            loop {
+                match events.recv().await {
+                    Ok(CacheEvent::Evicted(entry)) => {
+                        let mut line = serde_json::to_vec(&entry)?;
+                        line.push(b'\n');
+                        file.write_all(&line).await?;
+                        persisted += 1;
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Eviction persister lagged; {} events skipped", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            
+            file.flush().await?;
+            Ok(persisted)
+        })
+    }
+
+    /// Apply the configured embedding quantization to a value about to be stored
+    fn quantize(&self, value: CacheValue) -> CacheValue {
+        match value {
+            CacheValue::Embedding(values) => {
+                match QuantizedEmbedding::quantize(&values, self.embedding_quantization) {
+                    Some(quantized) => CacheValue::Quantized(quantized),
+                    None => CacheValue::Embedding(values),
+                }
+            }
+            value => value,
+        }
+    }
+
+    fn make_cache_key(&self, vertex_id: &str, key: &str) -> String {
+        Self::namespaced_key(DEFAULT_NAMESPACE, vertex_id, key)
+    }
+
+    fn namespaced_key(namespace: &str, vertex_id: &str, key: &str) -> String {
+        if namespace.is_empty() {
+            format!("{}:{}", vertex_id, key)
+        } else {
+            format!("{}/{}:{}", namespace, vertex_id, key)
+        }
+    }
+
+    fn current_timestamp(&self) -> u64 {
+        self.clock.now_secs()
+    }
+
+    async fn lookup(&self, cache_key: &str) -> Option<CacheEntry> {
+        match self.backend.get(cache_key).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Cache backend {} lookup failed for {}: {:?}", self.backend.name(), cache_key, e);
+                None
+            }
+        }
+    }
+
+    /// Insert into memory, keeping the negative-lookup filter in step
+    async fn store(&self, cache_key: &str, entry: CacheEntry) -> Result<()> {
+        // Filter first, so a concurrent lookup never misses a stored entry
+        if let Some(filter) = &self.bloom {
+            filter.insert(cache_key);
+        }
+        
+        let replaced = match self.tracks_usage() {
+            true => self.backend.get(cache_key).await?,
+            false => None,
+        };
+        let (namespace, size_bytes) = (entry.namespace.clone(), entry.size_bytes);
+        self.backend.insert(cache_key, entry).await?;
+        if let Some(replaced) = replaced {
+            self.track_usage(&replaced.namespace, replaced.size_bytes, false);
+        }
+        self.track_usage(&namespace, size_bytes, true);
+        Ok(())
+    }
+
+    /// Fill the filter from both tiers before it answers its first lookup
+    ///
+    /// Backends may hold entries stored before this cache was built, e.g. a
+    /// reopened `FileBackend`. If counting them fails the filter stays
+    /// unseeded and lets every lookup through; the next lookup retries.
+    async fn seed_filter(&self) {
+        let filter = match &self.bloom {
+            Some(filter) if !filter.is_seeded() => filter,
+            _ => return,
+        };
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        if filter.is_seeded() {
+            return;
+        }
+        if let Err(e) = self.rebuild_filter().await {
+            tracing::warn!("Failed to seed the negative-lookup filter: {:?}", e);
+        }
+    }
+
+    /// `false` when the filter rules `cache_key` out of every tier
+    fn may_contain(&self, cache_key: &str) -> bool {
+        self.bloom.as_ref().map_or(true, |filter| filter.may_contain(cache_key))
+    }
+
+    fn record_false_positive(&self) {
+        if let Some(filter) = &self.bloom {
+            filter.record_false_positive();
+        }
+    }
+
+    fn record_removals(&self, count: usize) {
+        if let Some(filter) = &self.bloom {
+            filter.record_removals(count);
+        }
+    }
+
+    /// Rebuild the filter once removals have left too many stale bits
+    async fn rebuild_filter_if_stale(&self) -> Result<()> {
+        match &self.bloom {
+            Some(filter) if filter.needs_rebuild() => self.rebuild_filter().await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Reset the filter to the keys currently held in both tiers
+    ///
+    /// Callers hold the admission lock so no insert lands mid-rebuild.
+    async fn rebuild_filter(&self) -> Result<()> {
+        let filter = match &self.bloom {
+            Some(filter) => filter,
+            None => return Ok(()),
+        };
+        let mut keys: Vec<String> = self.backend.entries().await?.into_iter().map(|(k, _)| k).collect();
+        if let Some(l2) = &self.l2 {
+            keys.extend(l2.entries().await?.into_iter().map(|(k, _)| k));
+        }
+        filter.rebuild(keys.iter().map(String::as_str));
+        Ok(())
+    }
+
+    async fn touch(&self, cache_key: &str, now: u64) {
+        if let Err(e) = self.backend.record_access(cache_key, now).await {
+            tracing::warn!("Cache backend {} failed to record access for {}: {:?}", self.backend.name(), cache_key, e);
+        }
+    }
+
+    /// Evict one entry, never choosing `protected` and only from `namespace` when given
+    ///
+    /// Returns whether anything was evicted.
+    async fn evict(&self, protected: Option<&str>, namespace: Option<&str>, reason: DecisionReason) -> Result<bool> {
+        let entries = self.backend.entries().await?;
+        let pinned = self.acquire("cache.pinned", self.pinned.read()).await;
+        let candidates = |unpinned_only: bool| {
+            entries.iter()
+                .filter(|(key, _)| Some(key.as_str()) != protected)
+                .filter(|(_, entry)| namespace.map_or(true, |ns| entry.namespace == ns))
+                .filter(|(_, entry)| !unpinned_only || !pinned.contains_key(&entry.vertex_id))
+                .map(|(key, entry)| (key, self.eviction_policy.score(entry)))
+                .collect::<Vec<_>>()
+        };
+        
+        // Find the entry with the lowest retention score under the active policy,
+        // sparing pinned vertices unless every entry is pinned
+        let mut scored = candidates(true);
+        let pinned_fallback = scored.is_empty();
+        if pinned_fallback {
+            scored = candidates(false);
+        }
+        drop(pinned);
+        
+        fn by_score(a: &(&String, f64), b: &(&String, f64)) -> std::cmp::Ordering {
+            a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
+        }
+        let victim = match scored.iter().enumerate().min_by(|(_, a), (_, b)| by_score(a, b)) {
+            Some((index, _)) => index,
+            None => return Ok(false),
+        };
+        let candidates_considered = scored.len();
+        let (key_to_remove, score) = scored.swap_remove(victim);
+        let key_to_remove = key_to_remove.clone();
+        
+        self.log(|| WalOp::Remove { cache_key: key_to_remove.clone() }).await?;
+        if let Some(entry) = self.backend.remove(&key_to_remove).await? {
+            self.track_usage(&entry.namespace, entry.size_bytes, false);
+            self.metrics.record_eviction(self.l2.is_some());
+            self.record_decision(|| {
+                scored.sort_by(by_score);
+                CacheDecision {
+                    timestamp_ms: self.clock.now_ms(),
+                    kind: DecisionKind::Evicted,
+                    cache_key: key_to_remove.clone(),
+                    reason,
+                    policy: self.eviction_policy.name().to_string(),
+                    score,
+                    trigger: protected.map(str::to_string),
+                    candidates_considered,
+                    runners_up: scored.iter().take(RUNNERS_UP).map(|(k, s)| (k.to_string(), *s)).collect(),
+                    pinned_fallback,
+                }
+            });
+            match &self.l2 {
+                Some(l2) => self.demote(l2, &key_to_remove, entry).await?,
+                None => {
+                    self.record_removals(1);
+                    self.notify_evicted(entry).await;
+                }
+            }
+        }
+        
+        Ok(true)
+    }
+
+    /// Entry left every tier: tell listeners and subscribers
+    async fn notify_evicted(&self, entry: CacheEntry) {
+        for listener in self.listeners.read().await.iter() {
+            listener.on_evict(&entry);
+        }
+        self.emit(CacheEvent::Evicted(entry));
+    }
+
+    /// Move an entry evicted from memory into L2, evicting from L2 if it is full
+    async fn demote(&self, l2: &Arc<dyn CacheBackend>, cache_key: &str, entry: CacheEntry) -> Result<()> {
+        if l2.len().await? >= self.l2_max_entries && !l2.contains(cache_key).await? {
+            let victim = l2.entries().await?
+                .into_iter()
+                .min_by(|(_, a), (_, b)| {
+                    self.eviction_policy.score(a)
+                        .partial_cmp(&self.eviction_policy.score(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|(key, _)| key);
+            if let Some(victim) = victim {
+                if let Some(evicted) = l2.remove(&victim).await? {
+                    self.metrics.record_eviction(false);
+                    self.record_removals(1);
+                    self.notify_evicted(evicted).await;
+                }
+            }
+        }
+        
+        let event = CacheEvent::Demoted {
+            vertex_id: entry.vertex_id.clone(),
+            key: entry.key.clone(),
+        };
+        l2.insert(cache_key, entry).await?;
+        self.emit(event);
+        Ok(())
+    }
+
+    /// Serve an L1 miss from L2, moving the entry back into memory
+    ///
+    /// Expired L2 entries are dropped. Failing to re-admit the entry to memory
+    /// is logged but the value is still returned.
+    async fn promote(&self, cache_key: &str, now: u64) -> Option<CacheEntry> {
+        let l2 = self.l2.as_ref()?;
+        let mut entry = match l2.remove(cache_key).await {
+            Ok(entry) => entry?,
+            Err(e) => {
+                tracing::warn!("Cache L2 backend {} lookup failed for {}: {:?}", l2.name(), cache_key, e);
+                return None;
+            }
+        };
+        if self.freshness(&entry, now) == Freshness::Expired {
+            return None;
+        }
+        
+        entry.access_count += 1;
+        entry.timestamp = now;
+        self.l2_hits.fetch_add(1, Ordering::Relaxed);
+        
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        let admitted = match self.make_room(cache_key, entry.size_bytes).await {
+            Ok(()) => match self.log(|| WalOp::Put { cache_key: cache_key.to_string(), entry: entry.clone() }).await {
+                Ok(()) => self.store(cache_key, entry.clone()).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        match admitted {
+            Ok(()) => {
+                self.record_admission(cache_key, self.eviction_policy.score(&entry), DecisionReason::Promotion);
+                self.emit(CacheEvent::Promoted {
+                    vertex_id: entry.vertex_id.clone(),
+                    key: entry.key.clone(),
+                });
+            }
+            Err(e) => tracing::warn!("Failed to promote {} from L2: {:?}", cache_key, e),
+        }
+        Some(entry)
+    }
+
+    fn freshness(&self, entry: &CacheEntry, now: u64) -> Freshness {
+        let ttl = match self.ttl {
+            Some(ttl) => ttl.as_secs(),
+            None => return Freshness::Fresh,
+        };
+        let age = now.saturating_sub(entry.inserted_at);
+        let stale_window = self.stale_while_revalidate.map(|d| d.as_secs()).unwrap_or(0);
+        
+        if age < ttl {
+            Freshness::Fresh
+        } else if age < ttl + stale_window {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+
+    /// Register a listener for eviction and refresh notifications
+    pub async fn add_listener(&self, listener: Arc<dyn CacheListener>) {
+        self.listeners.write().await.push(listener);
+    }
+
+    /// Read an entry without updating access statistics or hit counters
+    pub async fn peek(&self, vertex_id: &str, key: &str) -> Option<CacheEntry> {
+        let cache_key = self.make_cache_key(vertex_id, key);
+        self.lookup(&cache_key).await
+    }
+
+    /// Get a value with stale-while-revalidate semantics
+    ///
+    /// Fresh entries are returned directly. Entries past their TTL but inside
+    /// the stale window are returned immediately while a single background
+    /// task recomputes them; listeners receive `on_refresh` once it lands.
+    /// Missing or fully expired entries are computed inline, with the measured
+    /// wall-clock seconds recorded as the computation cost.
+    pub async fn get_or_revalidate<V, F, Fut>(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        compute: F,
+    ) -> Result<CacheValue>
+    where
+        V: Into<CacheValue> + Send + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        let cache_key = self.make_cache_key(vertex_id, key);
+        let now = self.current_timestamp();
+        
+        let maybe_cached = self.may_contain(&cache_key);
+        let cached = match maybe_cached {
+            true => self.lookup(&cache_key).await,
+            false => None,
+        };
+        let cached = cached
+            .map(|entry| (self.freshness(&entry, now), entry))
+            .filter(|(freshness, _)| *freshness != Freshness::Expired);
+        
+        match cached {
+            Some((freshness, entry)) => {
+                self.touch(&cache_key, now).await;
+                self.record_lookups([(vertex_id, true)]);
+                let (value, cost) = (entry.value.dequantized(), entry.computation_cost);
+                if freshness == Freshness::Stale {
+                    self.spawn_refresh(cache_key, vertex_id, key, cost, compute).await;
+                }
+                Ok(value)
+            }
+            None => {
+                let promoted = match maybe_cached {
+                    true => self.promote(&cache_key, now).await,
+                    false => None,
+                };
+                if let Some(entry) = promoted {
+                    self.record_lookups([(vertex_id, true)]);
+                    return Ok(entry.value.dequantized());
+                }
+                self.record_lookups([(vertex_id, false)]);
+                let start = std::time::Instant::now();
+                let value: CacheValue = compute().await?.into();
+                self.put_value(vertex_id, key, value.clone(), start.elapsed().as_secs_f64()).await?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Get a value, computing and inserting it on a miss
+    ///
+    /// Concurrent callers that miss on the same key share one computation:
+    /// the first runs `compute`, the rest wait for its result. If the running
+    /// caller is cancelled, a waiting caller takes over with its own closure.
+    /// Errors are reported to every waiter as `Error::Cache`.
+    pub async fn get_or_compute<V, F, Fut>(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        compute: F,
+    ) -> Result<CacheValue>
+    where
+        V: Into<CacheValue>,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        if let Some(value) = self.get_value(vertex_id, key).await {
+            return Ok(value);
+        }
+        
+        let cache_key = self.make_cache_key(vertex_id, key);
+        let flight = self.acquire("cache.in_flight", self.in_flight.lock()).await
+            .entry(cache_key.clone())
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+        
+        let outcome = flight.get_or_init(|| async {
+            // A flight that finished between our miss and joining may already have stored it
+            let now = self.current_timestamp();
+            if let Some(entry) = self.lookup(&cache_key).await {
+                if self.freshness(&entry, now) == Freshness::Fresh {
+                    return Ok(entry.value.dequantized());
+                }
+            }
+            
+            let start = std::time::Instant::now();
+            let value: CacheValue = compute().await
+                .map_err(|e| format!("computation for {} failed: {:?}", cache_key, e))?
+                .into();
+            self.put_value(vertex_id, key, value.clone(), start.elapsed().as_secs_f64()).await
+                .map_err(|e| format!("failed to store {}: {:?}", cache_key, e))?;
+            Ok(value)
+        }).await.clone();
+        
+        // Retire the flight so later misses (e.g. after eviction) compute afresh
+        let mut in_flight = self.acquire("cache.in_flight", self.in_flight.lock()).await;
+        if in_flight.get(&cache_key).is_some_and(|f| Arc::ptr_eq(f, &flight)) {
+            in_flight.remove(&cache_key);
+        }
+        
+        outcome.map_err(Error::Cache)
+    }
+
+    async fn spawn_refresh<V, F, Fut>(
+        &self,
+        cache_key: String,
+        vertex_id: &str,
+        key: &str,
+        computation_cost: f64,
+        compute: F,
+    )
+    where
+        V: Into<CacheValue> + Send + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        // Only one refresh per key may be in flight
+        if !self.refreshing.lock().await.insert(cache_key.clone()) {
+            return;
+        }
+        
+        let cache = self.clone();
+        let vertex_id = vertex_id.to_string();
+        let key = key.to_string();
+        
+        tokio::spawn(async move {
+            match compute().await {
+                Ok(value) => {
+                    if let Err(e) = cache.put_value(&vertex_id, &key, value, computation_cost).await {
+                        tracing::warn!("Failed to store refreshed entry {}: {:?}", cache_key, e);
+                    } else if let Some(entry) = cache.peek(&vertex_id, &key).await {
+                        for listener in cache.listeners.read().await.iter() {
+                            listener.on_refresh(&entry);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Background refresh failed for {}: {:?}", cache_key, e),
+            }
+            cache.refreshing.lock().await.remove(&cache_key);
+        });
+    }
+
+    /// Copy all entries and the vertex index from the backend
+    pub async fn export_snapshot(&self) -> Result<CacheSnapshot> {
+        // Backend locks are held only for the copy, never for serialization or IO
+        let entries = self.backend.entries().await?
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect();
+        let vertex_index = self.backend.vertex_index().await?;
+        
+        Ok(CacheSnapshot {
+            created_at: self.current_timestamp(),
+            entries,
+            vertex_index,
+        })
+    }
+
+    /// Replace cache contents with a snapshot, evicting down to `max_entries`
+    ///
+    /// The vertex index is rebuilt by the backend from the restored entries.
+    pub async fn import_snapshot(&self, snapshot: CacheSnapshot) -> Result<usize> {
+        let entries = snapshot.entries.into_iter()
+            .map(|entry| (Self::namespaced_key(&entry.namespace, &entry.vertex_id, &entry.key), entry))
+            .collect();
+        self.replace_contents(entries, true).await
+    }
+
+    /// Rebuild the cache from its write-ahead log after a restart
+    ///
+    /// Current contents are replaced; if the log holds more than the budgets
+    /// allow, the lowest-scoring entries are evicted. Returns the entry count.
+    pub async fn recover_from_wal(&self) -> Result<usize> {
+        let wal = self.wal.as_ref()
+            .ok_or_else(|| Error::Cache("no write-ahead log configured".to_string()))?;
+        let entries = wal.replay().await?.into_iter().collect();
+        self.replace_contents(entries, false).await
+    }
+
+    /// Merge closed WAL segments into the WAL snapshot
+    pub async fn compact_wal(&self) -> Result<usize> {
+        match &self.wal {
+            Some(wal) => wal.compact().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Swap in `entries`, logging them unless they were just replayed from the WAL
+    async fn replace_contents(&self, entries: Vec<(String, CacheEntry)>, log: bool) -> Result<usize> {
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        
+        if log {
+            self.log(|| WalOp::Clear).await?;
+        }
+        self.backend.clear().await?;
+        self.reset_usage();
+        for (cache_key, mut entry) in entries {
+            entry.size_bytes = entry.estimated_size();
+            if log {
+                self.log(|| WalOp::Put { cache_key: cache_key.clone(), entry: entry.clone() }).await?;
+            }
+            self.store(&cache_key, entry).await?;
+        }
+        let over_memory = |bytes: usize| self.max_memory_bytes.is_some_and(|max| bytes > max);
+        while self.backend.len().await? > self.max_entries || over_memory(self.backend.memory_bytes().await?) {
+            let reason = if self.backend.len().await? > self.max_entries {
+                DecisionReason::EntryLimit
+            } else {
+                DecisionReason::MemoryLimit
+            };
+            if !self.evict(None, None, reason).await? {
+                break;
+            }
+        }
+        // Entries dropped by the clear above were never recorded as removals
+        self.rebuild_filter().await?;
+        
+        self.backend.len().await
+    }
+
+    /// Serialize the cache to disk so a warmed cache survives restarts
+    ///
+    /// The file is written to a temporary sibling and renamed into place,
+    /// so a crash mid-dump never corrupts an existing snapshot.
+    pub async fn snapshot(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref().to_path_buf();
+        let snapshot = self.export_snapshot().await?;
+        let count = snapshot.entries.len();
+        
+        let bytes = tokio::task::spawn_blocking(move || serde_json::to_vec(&snapshot))
+            .await
+            .map_err(|e| Error::Cache(format!("snapshot task failed: {}", e)))??;
+        
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, bytes).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        
+        Ok(count)
+    }
+
+    /// Run `snapshot` on a background task without blocking the caller
+    pub fn spawn_snapshot(&self, path: PathBuf) -> JoinHandle<Result<usize>> {
+        let cache = self.clone();
+        tokio::spawn(async move { cache.snapshot(path).await })
+    }
+
+    /// Load a snapshot written by `snapshot`, replacing current contents
+    pub async fn restore(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let bytes = tokio::fs::read(path.as_ref()).await?;
+        let snapshot: CacheSnapshot = tokio::task::spawn_blocking(move || serde_json::from_slice(&bytes))
+            .await
+            .map_err(|e| Error::Cache(format!("restore task failed: {}", e)))??;
+        
+        self.import_snapshot(snapshot).await
+    }
+
+    /// Bulk-insert entries ahead of traffic, keeping current contents
+    ///
+    /// Entries are stamped as freshly inserted. Loading stops once the cache
+    /// is full, so warm-up never evicts entries that were already present or
+    /// loaded earlier in the same batch; entries over their namespace quota
+    /// are skipped. Returns the number of entries loaded.
+    pub async fn warm_up(&self, entries: impl Iterator<Item = CacheEntry>) -> Result<usize> {
+        let _admission = self.acquire("cache.admission", self.admission.lock()).await;
+        let now = self.current_timestamp();
+        let mut loaded = 0;
+        
+        for mut entry in entries {
+            let cache_key = Self::namespaced_key(&entry.namespace, &entry.vertex_id, &entry.key);
+            entry.timestamp = now;
+            entry.inserted_at = now;
+            entry.value = self.quantize(entry.value);
+            entry.size_bytes = entry.estimated_size();
+            
+            let replaced_bytes = self.backend.get(&cache_key).await?
+                .map(|e| e.size_bytes);
+            
+            // A namespace at its quota skips entries; others may still fit
+            let quota = self.namespace_quotas.read().await.get(&entry.namespace).copied();
+            if let Some(quota) = quota {
+                if !self.fits_quota(&quota, &entry.namespace, replaced_bytes, entry.size_bytes).await? {
+                    continue;
+                }
+            }
+            
+            if replaced_bytes.is_none() && self.backend.len().await? >= self.max_entries {
+                break;
+            }
+            if let Some(max_memory) = self.max_memory_bytes {
+                let bytes = self.backend.memory_bytes().await?.saturating_sub(replaced_bytes.unwrap_or(0));
+                if bytes + entry.size_bytes > max_memory {
+                    break;
+                }
+            }
+            
+            self.log(|| WalOp::Put { cache_key: cache_key.clone(), entry: entry.clone() }).await?;
+            let event = Self::inserted_event(&entry);
+            let score = self.eviction_policy.score(&entry);
+            self.store(&cache_key, entry).await?;
+            self.emit(event);
+            self.record_admission(&cache_key, score, DecisionReason::WarmUp);
+            loaded += 1;
+        }
+        
+        Ok(loaded)
+    }
+
+    /// Read precomputed embeddings from a JSON Lines file of `EmbeddingRecord`s
+    pub async fn load_embeddings(path: impl AsRef<Path>) -> Result<Vec<CacheEntry>> {
+        let path = path.as_ref().to_path_buf();
+        let contents = tokio::fs::read_to_string(&path).await?;
+        
+        tokio::task::spawn_blocking(move || {
+            contents.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    serde_json::from_str::<EmbeddingRecord>(line)
+                        .map(CacheEntry::from)
+                        .map_err(|e| Error::Cache(format!("{}:{}: {}", path.display(), i + 1, e)))
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| Error::Cache(format!("embedding load task failed: {}", e)))?
+    }
+
+    /// Warm the cache from an embeddings file written as JSON Lines
+    pub async fn warm_up_from_file(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let entries = Self::load_embeddings(path).await?;
+        self.warm_up(entries.into_iter()).await
+    }
+
+    /// Apply planner locality hints: pin the frontier and prefetch its neighborhood
+    ///
+    /// Pins are reference-counted, so overlapping chains can hint the same
+    /// vertices; each call must be paired with `release_locality_hint`.
+    pub async fn apply_locality_hint(&self, hint: &LocalityHint) -> Result<usize> {
+        {
+            let mut pinned = self.acquire("cache.pinned", self.pinned.write()).await;
+            for vertex_id in &hint.frontier {
+                *pinned.entry(vertex_id.clone()).or_insert(0) += 1;
+            }
+        }
+        
+        let mut targets = hint.frontier.clone();
+        targets.extend(hint.neighbors.iter().cloned());
+        self.prefetch(&targets).await
+    }
+
+    /// Release pins taken by a previous `apply_locality_hint`
+    pub async fn release_locality_hint(&self, hint: &LocalityHint) {
+        let mut pinned = self.acquire("cache.pinned", self.pinned.write()).await;
+        for vertex_id in &hint.frontier {
+            if let Some(count) = pinned.get_mut(vertex_id) {
+                *count -= 1;
+                if *count == 0 {
+                    pinned.remove(vertex_id);
+                }
+            }
+        }
+    }
+
+    /// Check whether a vertex is currently pinned against eviction
+    pub async fn is_pinned(&self, vertex_id: &str) -> bool {
+        self.acquire("cache.pinned", self.pinned.read()).await.contains_key(vertex_id)
+    }
+
+    /// Prefetch entries for vertices, returning how many are cached afterwards
+    ///
+    /// Vertices with no cached entries are populated through the configured
+    /// `CacheLoader`, at most `prefetch_concurrency` at a time. Without a
+    /// loader only already-cached entries are counted. A failed load is
+    /// logged and skipped so one bad vertex does not abort the batch.
+    ///
+    /// Under pressure (see `PrefetchThrottle`) loads run one at a time, and
+    /// above the pause level they are skipped; both show in `CacheStats::prefetch`.
+    pub async fn prefetch(&self, vertex_ids: &[String]) -> Result<usize> {
+        let mut prefetched = 0;
+        let mut loads = Vec::new();
+        let mut seen = HashSet::new();
+        
+        for vertex_id in vertex_ids {
+            if !seen.insert(vertex_id) {
+                continue;
+            }
+            let entries = self.get_vertex_entries(vertex_id).await;
+            if !entries.is_empty() {
+                prefetched += entries.len();
+                continue;
+            }
+            let Some(loader) = self.loader.clone() else {
+                continue;
+            };
+            
+            match self.prefetch_mode().await {
+                PrefetchMode::Paused => {
+                    self.metrics.record_prefetch(PrefetchOutcome::Skipped);
+                    continue;
+                }
+                PrefetchMode::Throttled => {
+                    self.metrics.record_prefetch(PrefetchOutcome::Throttled);
+                    match self.load_vertex(loader.as_ref(), vertex_id).await {
+                        Ok(loaded) => prefetched += loaded,
+                        Err(e) => tracing::warn!("Prefetch of vertex {} failed: {:?}", vertex_id, e),
+                    }
+                    continue;
+                }
+                PrefetchMode::Normal => {}
+            }
+            
+            let cache = self.clone();
+            let vertex_id = vertex_id.clone();
+            loads.push(tokio::spawn(async move {
+                let loaded = cache.load_vertex(loader.as_ref(), &vertex_id).await;
+                (vertex_id, loaded)
+            }));
+        }
+        
+        for load in loads {
+            match load.await {
+                Ok((_, Ok(loaded))) => prefetched += loaded,
+                Ok((vertex_id, Err(e))) => tracing::warn!("Prefetch of vertex {} failed: {:?}", vertex_id, e),
+                Err(e) => tracing::warn!("Prefetch task panicked: {:?}", e),
+            }
+        }
+        
+        Ok(prefetched)
+    }
+
+    async fn load_vertex(&self, loader: &dyn CacheLoader, vertex_id: &str) -> Result<usize> {
+        let _permit = self.prefetch_limit.acquire().await
+            .map_err(|e| Error::Cache(format!("prefetch limiter closed: {}", e)))?;
+        // Pressure may have risen while this load waited for a permit
+        if self.prefetch_mode().await == PrefetchMode::Paused {
+            self.metrics.record_prefetch(PrefetchOutcome::Skipped);
+            return Ok(0);
+        }
+        let start = std::time::Instant::now();
+        let entries = loader.load(vertex_id).await?;
+        let cost = start.elapsed().as_secs_f64();
+        let loaded = entries.len();
+        for (key, value) in entries {
+            self.put_value(vertex_id, &key, value, cost).await?;
+        }
+        self.metrics.record_prefetch(PrefetchOutcome::Loaded);
+        Ok(loaded)
+    }
+
+    /// Current prefetch pressure in `0.0..=1.0`
+    pub async fn prefetch_pressure(&self) -> f64 {
+        let load = self.load_signal.as_ref().map_or(0.0, |signal| signal.load());
+        let memory = match self.max_memory_bytes {
+            Some(max) if max > 0 => match self.backend.memory_bytes().await {
+                Ok(bytes) => bytes as f64 / max as f64,
+                Err(_) => 0.0,
+            },
+            _ => 0.0,
+        };
+        load.max(memory).clamp(0.0, 1.0)
+    }
+
+    async fn prefetch_mode(&self) -> PrefetchMode {
+        let pressure = self.prefetch_pressure().await;
+        if pressure > self.prefetch_throttle.pause_above {
+            PrefetchMode::Paused
+        } else if pressure > self.prefetch_throttle.throttle_above {
+            PrefetchMode::Throttled
+        } else {
+            PrefetchMode::Normal
+        }
+    }
+}
+
+/// Cache view scoped to one namespace, sharing storage and budgets with its parent
+#[derive(Clone)]
+pub struct CacheNamespace {
+    cache: VertexCentricCache,
+    name: String,
+}
+
+impl CacheNamespace {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn get(&self, vertex_id: &str, key: &str) -> Option<Vec<f64>> {
+        self.get_value(vertex_id, key).await.and_then(CacheValue::into_embedding)
+    }
+
+    pub async fn get_value(&self, vertex_id: &str, key: &str) -> Option<CacheValue> {
+        self.cache.get_value_in(&self.name, vertex_id, key).await
+    }
+
+    pub async fn put(&self, vertex_id: &str, key: &str, value: Vec<f64>, computation_cost: f64) -> Result<()> {
+        self.put_tagged(vertex_id, key, value, computation_cost, Vec::new()).await
+    }
+
+    pub async fn put_value(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        value: impl Into<CacheValue>,
+        computation_cost: f64,
+    ) -> Result<()> {
+        self.put_tagged(vertex_id, key, value, computation_cost, Vec::new()).await
+    }
+
+    pub async fn put_tagged(
+        &self,
+        vertex_id: &str,
+        key: &str,
+        value: impl Into<CacheValue>,
+        computation_cost: f64,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        self.cache.put_tagged_in(&self.name, vertex_id, key, value.into(), computation_cost, tags).await
+    }
+
+    pub async fn get_many(&self, keys: &[(&str, &str)]) -> Vec<Option<CacheValue>> {
+        self.cache.get_many_in(&self.name, keys).await
+    }
+
+    pub async fn put_many<V: Into<CacheValue>>(&self, items: Vec<(&str, &str, V, f64)>) -> Result<()> {
+        self.cache.put_many_in(&self.name, items).await
+    }
+
+    pub async fn stats(&self) -> NamespaceStats {
+        self.cache.namespace_stats(&self.name).await
+    }
+
+    pub async fn clear(&self) -> Result<usize> {
+        self.cache.clear_namespace(&self.name).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::level4::agents::cache_backend::FileBackend;
+    use crate::level4::agents::cache_wal::WalConfig;
+    use crate::level4::agents::cache_bloom::BloomConfig;
 
     #[tokio::test]
     async fn test_cache_put_get() {
@@ -249,4 +2479,567 @@ mod tests {
         assert_eq!(stats.total_misses, 1);
         assert_eq!(stats.hit_rate, 0.5);
     }
+
+    #[tokio::test]
+    async fn test_lfu_eviction() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_entries: 2,
+            eviction_policy: Arc::new(LfuPolicy),
+            ..CacheConfig::default()
+        });
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        cache.get("v1", "key1").await;
+        cache.put("v3", "key1", vec![3.0], 0.5).await.unwrap();
+        
+        assert!(cache.get("v1", "key1").await.is_some());
+        assert!(cache.get("v2", "key1").await.is_none());
+        assert!(cache.get("v3", "key1").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_decision_log_explains_eviction() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_entries: 2,
+            eviction_policy: Arc::new(LfuPolicy),
+            decision_log_capacity: Some(16),
+            ..CacheConfig::default()
+        });
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        cache.get("v1", "key1").await;
+        cache.put("v3", "key1", vec![3.0], 0.5).await.unwrap();
+        
+        let decisions = cache.recent_decisions(Duration::from_secs(60));
+        assert_eq!(decisions.iter().filter(|d| d.kind == DecisionKind::Admitted).count(), 3);
+        let eviction = decisions.iter().find(|d| d.kind == DecisionKind::Evicted).unwrap();
+        assert!(eviction.cache_key.contains("v2"));
+        assert_eq!(eviction.reason, DecisionReason::EntryLimit);
+        assert_eq!(eviction.policy, "lfu");
+        assert!(eviction.trigger.as_deref().is_some_and(|k| k.contains("v3")));
+        assert_eq!(eviction.candidates_considered, 2);
+        assert_eq!(eviction.runners_up.len(), 1);
+        assert!(eviction.runners_up[0].0.contains("v1"));
+        
+        assert!(VertexCentricCache::new(2).recent_decisions(Duration::from_secs(60)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore() {
+        let path = std::env::temp_dir().join(format!("cache_{}.json", uuid::Uuid::new_v4()));
+        
+        let cache = VertexCentricCache::new(100);
+        cache.put("v1", "key1", vec![1.0, 2.0], 0.5).await.unwrap();
+        cache.put("v1", "key2", vec![3.0], 0.5).await.unwrap();
+        assert_eq!(cache.snapshot(&path).await.unwrap(), 2);
+        
+        let restored = VertexCentricCache::new(100);
+        assert_eq!(restored.restore(&path).await.unwrap(), 2);
+        assert_eq!(restored.get("v1", "key1").await, Some(vec![1.0, 2.0]));
+        assert_eq!(restored.get_vertex_entries("v1").await.len(), 2);
+        
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingLoader {
+        loads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CacheLoader for CountingLoader {
+        async fn load(&self, vertex_id: &str) -> Result<Vec<(String, CacheValue)>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            if vertex_id == "broken" {
+                return Err(Error::Cache("no such vertex".to_string()));
+            }
+            Ok(vec![("embedding".to_string(), CacheValue::from(vec![1.0, 2.0]))])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_loads_missing_vertices() {
+        let loader = Arc::new(CountingLoader::default());
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            loader: Some(loader.clone()),
+            prefetch_concurrency: 2,
+            ..CacheConfig::default()
+        });
+        cache.put("v1", "embedding", vec![0.5], 0.5).await.unwrap();
+        
+        let ids: Vec<String> = ["v1", "v2", "v3", "v3", "broken"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(cache.prefetch(&ids).await.unwrap(), 3);
+        // Cached and duplicate vertices are not reloaded
+        assert_eq!(loader.loads.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.get("v2", "embedding").await, Some(vec![1.0, 2.0]));
+        assert_eq!(cache.get("v1", "embedding").await, Some(vec![0.5]));
+        
+        assert_eq!(cache.prefetch(&ids).await.unwrap(), 3);
+        assert_eq!(loader.loads.load(Ordering::SeqCst), 4);
+    }
+
+    #[derive(Debug)]
+    struct FixedLoad(std::sync::Mutex<f64>);
+
+    impl LoadSignal for FixedLoad {
+        fn load(&self) -> f64 {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_backs_off_under_load() {
+        let loader = Arc::new(CountingLoader::default());
+        let load = Arc::new(FixedLoad(std::sync::Mutex::new(1.0)));
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            loader: Some(loader.clone()),
+            load_signal: Some(load.clone()),
+            ..CacheConfig::default()
+        });
+        let ids: Vec<String> = ["v1", "v2"].iter().map(|s| s.to_string()).collect();
+        
+        assert_eq!(cache.prefetch(&ids).await.unwrap(), 0);
+        assert_eq!(loader.loads.load(Ordering::SeqCst), 0);
+        
+        *load.0.lock().unwrap() = 0.8;
+        assert_eq!(cache.prefetch(&ids).await.unwrap(), 2);
+        
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.prefetch, PrefetchStats { loaded: 2, throttled: 2, skipped: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_locality_hint_pins_frontier() {
+        let cache = VertexCentricCache::new(2);
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        
+        let hint = LocalityHint {
+            frontier: vec!["v1".to_string()],
+            neighbors: vec!["v2".to_string()],
+        };
+        assert_eq!(cache.apply_locality_hint(&hint).await.unwrap(), 2);
+        
+        cache.put("v3", "key1", vec![3.0], 0.5).await.unwrap();
+        assert!(cache.get("v1", "key1").await.is_some());
+        assert!(cache.get("v2", "key1").await.is_none());
+        
+        cache.release_locality_hint(&hint).await;
+        assert!(!cache.is_pinned("v1").await);
+    }
+
+    #[derive(Default)]
+    struct RefreshCounter(std::sync::atomic::AtomicUsize);
+
+    impl CacheListener for RefreshCounter {
+        fn on_refresh(&self, _entry: &CacheEntry) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_revalidate() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            ttl: Some(Duration::from_secs(0)),
+            stale_while_revalidate: Some(Duration::from_secs(60)),
+            ..CacheConfig::default()
+        });
+        let counter = Arc::new(RefreshCounter::default());
+        cache.add_listener(counter.clone()).await;
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        assert!(cache.get("v1", "key1").await.is_none());
+        
+        let value = cache.get_or_revalidate("v1", "key1", || async { Ok(vec![2.0]) }).await.unwrap();
+        assert_eq!(value, CacheValue::Embedding(vec![1.0]));
+        
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.peek("v1", "key1").await.unwrap().value, CacheValue::Embedding(vec![2.0]));
+        assert_eq!(counter.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_follows_injected_clock() {
+        let clock = Arc::new(clock::ManualClock::new(Duration::from_secs(1_700_000_000)));
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            ttl: Some(Duration::from_secs(60)),
+            stale_while_revalidate: Some(Duration::from_secs(30)),
+            clock: clock.clone(),
+            ..CacheConfig::default()
+        });
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        assert_eq!(cache.peek("v1", "key1").await.unwrap().inserted_at, 1_700_000_000);
+        
+        clock.advance(Duration::from_secs(59));
+        assert!(cache.get("v1", "key1").await.is_some());
+        clock.advance(Duration::from_secs(1));
+        assert!(cache.get("v1", "key1").await.is_none());
+        
+        let value = cache.get_or_revalidate("v1", "key1", || async { Ok(vec![2.0]) }).await.unwrap();
+        assert_eq!(value, CacheValue::Embedding(vec![1.0]));
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingBus(Mutex<Vec<InvalidationMessage>>);
+
+    #[async_trait::async_trait]
+    impl InvalidationBus for RecordingBus {
+        fn replica_id(&self) -> &str {
+            "replica-a"
+        }
+        
+        async fn publish(&self, message: &InvalidationMessage) -> Result<()> {
+            self.0.lock().await.push(message.clone());
+            Ok(())
+        }
+        
+        async fn poll(&self, _max_messages: usize) -> Result<Vec<crate::level4::agents::cache_invalidation::InvalidationDelivery>> {
+            Ok(Vec::new())
+        }
+        
+        async fn ack(&self, _delivery_id: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_coalesces_callers() {
+        let cache = VertexCentricCache::new(100);
+        let computations = Arc::new(AtomicUsize::new(0));
+        
+        let mut handles = Vec::new();
+        for _ in 0..8 {
+            let cache = cache.clone();
+            let computations = computations.clone();
+            handles.push(tokio::spawn(async move {
+                cache.get_or_compute("v1", "embedding", || async move {
+                    computations.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok(vec![1.0, 2.0])
+                }).await
+            }));
+        }
+        
+        for handle in handles {
+            let value = handle.await.unwrap().unwrap();
+            assert_eq!(value.into_embedding(), Some(vec![1.0, 2.0]));
+        }
+        assert_eq!(computations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_propagates_to_replica() {
+        let bus = Arc::new(RecordingBus::default());
+        let origin = VertexCentricCache::with_config(CacheConfig {
+            invalidation_bus: Some(bus.clone()),
+            ..CacheConfig::default()
+        });
+        let replica = VertexCentricCache::new(100);
+        
+        replica.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        replica.put_tagged("v2", "key1", vec![2.0], 0.5, vec!["paper".to_string()]).await.unwrap();
+        
+        origin.invalidate_vertex("v1").await.unwrap();
+        origin.invalidate_tag("paper").await.unwrap();
+        
+        for message in bus.0.lock().await.iter() {
+            replica.apply_invalidation(message).await.unwrap();
+        }
+        assert!(replica.peek("v1", "key1").await.is_none());
+        assert!(replica.peek("v2", "key1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_neighborhood() {
+        let graph: HashMap<String, Vec<String>> = [
+            ("v1", vec!["v2"]),
+            ("v2", vec!["v1", "v3"]),
+            ("v3", vec!["v4"]),
+        ]
+        .into_iter()
+        .map(|(v, n)| (v.to_string(), n.into_iter().map(String::from).collect()))
+        .collect();
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            neighbor_provider: Some(Arc::new(graph)),
+            ..CacheConfig::default()
+        });
+        for v in ["v1", "v2", "v3", "v4"] {
+            cache.put(v, "key1", vec![1.0], 0.5).await.unwrap();
+        }
+        
+        let invalidated = cache.invalidate_neighborhood("v1", 2).await.unwrap();
+        
+        assert_eq!(invalidated, vec!["v1", "v2", "v3"]);
+        assert!(cache.peek("v3", "key1").await.is_none());
+        assert!(cache.peek("v4", "key1").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_many_preserves_order() {
+        let cache = VertexCentricCache::new(2);
+        
+        cache.put_many(vec![("v1", "key1", vec![1.0], 0.5), ("v2", "key1", vec![2.0], 0.5)]).await.unwrap();
+        let values = cache.get_many(&[("v2", "key1"), ("v3", "key1"), ("v1", "key1")]).await;
+        
+        assert_eq!(values[0], Some(CacheValue::Embedding(vec![2.0])));
+        assert_eq!(values[1], None);
+        assert_eq!(values[2], Some(CacheValue::Embedding(vec![1.0])));
+        assert_eq!(cache.get_stats().await.total_misses, 1);
+        
+        // Over budget: falls back to per-entry admission with eviction
+        cache.put_many(vec![("v3", "key1", vec![3.0], 0.5), ("v4", "key1", vec![4.0], 0.5)]).await.unwrap();
+        assert_eq!(cache.get_stats().await.total_entries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cache_events() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_entries: 1,
+            hit_rate_alert: Some(0.5),
+            hit_rate_window: 2,
+            ..CacheConfig::default()
+        });
+        let mut events = cache.subscribe();
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        cache.get("v1", "key1").await;
+        cache.get("v3", "key1").await;
+        cache.invalidate_vertex("v2").await.unwrap();
+        
+        assert!(matches!(events.recv().await.unwrap(), CacheEvent::Inserted { ref vertex_id, .. } if vertex_id == "v1"));
+        assert!(matches!(events.recv().await.unwrap(), CacheEvent::Evicted(ref entry) if entry.vertex_id == "v1"));
+        assert!(matches!(events.recv().await.unwrap(), CacheEvent::Inserted { ref vertex_id, .. } if vertex_id == "v2"));
+        assert!(matches!(events.recv().await.unwrap(), CacheEvent::HitRateDrop { hit_rate, .. } if hit_rate == 0.0));
+        assert!(matches!(events.recv().await.unwrap(), CacheEvent::Invalidated { removed: 1, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_l2_demotion_and_promotion() {
+        let dir = std::env::temp_dir().join(format!("cache_l2_{}", uuid::Uuid::new_v4()));
+        let l2 = FileBackend::open(&dir).await.unwrap();
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_entries: 1,
+            l2_backend: Some(Arc::new(l2)),
+            ..CacheConfig::default()
+        });
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        
+        // v1 was demoted, not lost; reading it promotes it and demotes v2
+        assert_eq!(cache.get("v1", "key1").await, Some(vec![1.0]));
+        assert_eq!(cache.get("v2", "key1").await, Some(vec![2.0]));
+        
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.l1_hit_rate, 0.0);
+        assert_eq!(stats.l2_hit_rate, 1.0);
+        assert_eq!(stats.l2_entries, 1);
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_recover_from_wal() {
+        let dir = std::env::temp_dir().join(format!("cache_wal_{}", uuid::Uuid::new_v4()));
+        let config = WalConfig { dir: dir.clone(), ..WalConfig::default() };
+        let with_wal = |wal: WriteAheadLog| VertexCentricCache::with_config(CacheConfig {
+            wal: Some(Arc::new(wal)),
+            ..CacheConfig::default()
+        });
+        
+        let cache = with_wal(WriteAheadLog::open(config.clone()).await.unwrap());
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        cache.invalidate_vertex("v1").await.unwrap();
+        drop(cache);
+        
+        let recovered = with_wal(WriteAheadLog::open(config).await.unwrap());
+        assert_eq!(recovered.recover_from_wal().await.unwrap(), 1);
+        assert_eq!(recovered.get("v2", "key1").await, Some(vec![2.0]));
+        assert_eq!(recovered.get("v1", "key1").await, None);
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_namespace_quota_and_clear() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            namespace_quotas: HashMap::from([(
+                "tenant_a".to_string(),
+                NamespaceQuota { max_entries: Some(1), max_memory_bytes: None },
+            )]),
+            ..CacheConfig::default()
+        });
+        let tenant_a = cache.namespace("tenant_a");
+        let tenant_b = cache.namespace("tenant_b");
+        
+        tenant_a.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        tenant_b.put("v1", "key1", vec![2.0], 0.5).await.unwrap();
+        // Over quota: evicts tenant_a's own entry, not tenant_b's
+        tenant_a.put("v2", "key1", vec![3.0], 0.5).await.unwrap();
+        
+        assert_eq!(tenant_a.get("v1", "key1").await, None);
+        assert_eq!(tenant_b.get("v1", "key1").await, Some(vec![2.0]));
+        assert_eq!(cache.get("v1", "key1").await, None);
+        assert_eq!(tenant_a.stats().await.total_entries, 1);
+        
+        assert_eq!(tenant_b.clear().await.unwrap(), 1);
+        assert_eq!(tenant_b.get("v1", "key1").await, None);
+        assert_eq!(tenant_a.get("v2", "key1").await, Some(vec![3.0]));
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_batches() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            eviction_policy: Arc::new(CostWeightedPolicy),
+            namespace_quotas: HashMap::from([(
+                "tenant_a".to_string(),
+                NamespaceQuota { max_entries: Some(2), max_memory_bytes: None },
+            )]),
+            ..CacheConfig::default()
+        });
+        let tenant_a = cache.namespace("tenant_a");
+        
+        tenant_a.put_many(vec![
+            ("v1", "key1", vec![1.0], 0.1),
+            ("v2", "key1", vec![2.0], 0.9),
+            ("v3", "key1", vec![3.0], 0.8),
+        ]).await.unwrap();
+        let values = tenant_a.get_many(&[("v1", "key1"), ("v2", "key1"), ("v3", "key1")]).await;
+        assert_eq!(values.into_iter().map(|v| v.and_then(CacheValue::into_embedding)).collect::<Vec<_>>(),
+            vec![None, Some(vec![2.0]), Some(vec![3.0])]);
+        assert_eq!(cache.get_many(&[("v2", "key1")]).await, vec![None]);
+        
+        let stats = tenant_a.stats().await;
+        assert_eq!((stats.total_entries, stats.total_hits, stats.total_misses), (2, 2, 1));
+        assert_eq!(cache.namespace_stats(DEFAULT_NAMESPACE).await.total_misses, 1);
+        
+        // Invalidation frees quota without a rescan of the backend
+        cache.invalidate_vertex("v2").await.unwrap();
+        tenant_a.put("v4", "key1", vec![4.0], 0.1).await.unwrap();
+        assert_eq!(tenant_a.get("v3", "key1").await, Some(vec![3.0]));
+        assert_eq!(tenant_a.get("v4", "key1").await, Some(vec![4.0]));
+    }
+
+    #[tokio::test]
+    async fn test_negative_lookup_filter() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_entries: 2,
+            negative_lookup_filter: Some(BloomConfig::default()),
+            ..CacheConfig::default()
+        });
+        
+        cache.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        assert_eq!(cache.get("v1", "key1").await, Some(vec![1.0]));
+        assert_eq!(cache.get("v2", "key1").await, None);
+        
+        // One of v1/v2 is evicted here; whichever it was must read as a miss
+        cache.put("v2", "key1", vec![2.0], 0.5).await.unwrap();
+        cache.put("v3", "key1", vec![3.0], 0.5).await.unwrap();
+        let values = cache.get_many(&[("v1", "key1"), ("v3", "key1")]).await;
+        assert_eq!(values[1].as_ref().and_then(CacheValue::as_embedding), Some(&[3.0][..]));
+        
+        let filter = cache.get_stats().await.negative_lookup_filter.unwrap();
+        assert!(filter.negatives >= 1);
+        assert_eq!(filter.negatives + filter.false_positives, 1 + values[0].is_none() as usize);
+    }
+
+    #[tokio::test]
+    async fn test_negative_lookup_filter_sees_persisted_entries() {
+        let dir = std::env::temp_dir().join(format!("cache_bloom_{}", uuid::Uuid::new_v4()));
+        let first = VertexCentricCache::with_config(CacheConfig {
+            backend: Arc::new(FileBackend::open(&dir).await.unwrap()),
+            negative_lookup_filter: Some(BloomConfig::default()),
+            ..CacheConfig::default()
+        });
+        first.put("v1", "key1", vec![1.0], 0.5).await.unwrap();
+        
+        // A restart reopens the same files behind an empty filter
+        let restarted = VertexCentricCache::with_config(CacheConfig {
+            backend: Arc::new(FileBackend::open(&dir).await.unwrap()),
+            negative_lookup_filter: Some(BloomConfig::default()),
+            ..CacheConfig::default()
+        });
+        assert_eq!(restarted.get("v1", "key1").await, Some(vec![1.0]));
+        assert_eq!(restarted.get("v2", "key1").await, None);
+        assert_eq!(restarted.get_stats().await.negative_lookup_filter.unwrap().rebuilds, 1);
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_text_and_bytes_values() {
+        let cache = VertexCentricCache::new(100);
+        
+        cache.put_value("v1", "answer", "Inferred answer", 0.5).await.unwrap();
+        cache.put_value("v1", "subgraph", vec![0u8, 1, 2], 0.5).await.unwrap();
+        
+        assert_eq!(cache.get_value("v1", "answer").await, Some(CacheValue::Text("Inferred answer".to_string())));
+        assert_eq!(cache.get_value("v1", "subgraph").await.and_then(CacheValue::into_bytes), Some(vec![0, 1, 2]));
+        assert!(cache.get("v1", "answer").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_bounded_eviction() {
+        let entry_size = {
+            let cache = VertexCentricCache::new(100);
+            cache.put("v0", "key1", vec![0.0; 128], 0.5).await.unwrap();
+            cache.get_stats().await.memory_usage_bytes
+        };
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            max_memory_bytes: Some(entry_size * 2),
+            ..CacheConfig::default()
+        });
+        
+        for i in 1..=3 {
+            cache.put(&format!("v{}", i), "key1", vec![0.0; 128], 0.5).await.unwrap();
+        }
+        
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.total_entries, 2);
+        assert!(stats.memory_usage_bytes <= entry_size * 2);
+        assert!(cache.put("v4", "key1", vec![0.0; 1024], 0.5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_int8_quantized_embeddings() {
+        let cache = VertexCentricCache::with_config(CacheConfig {
+            embedding_quantization: EmbeddingQuantization::Int8,
+            ..CacheConfig::default()
+        });
+        let embedding: Vec<f64> = (0..64).map(|i| (i as f64 - 32.0) / 10.0).collect();
+        
+        cache.put("v1", "embedding", embedding.clone(), 0.5).await.unwrap();
+        
+        let restored = cache.get("v1", "embedding").await.unwrap();
+        let max_error = embedding.iter().zip(&restored)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        assert!(max_error < 3.2 / 127.0);
+        assert!(cache.get_stats().await.embedding_compression_ratio > 7.0);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_from_file() {
+        let path = std::env::temp_dir().join(format!("embeddings_{}.jsonl", uuid::Uuid::new_v4()));
+        let lines = (0..3)
+            .map(|i| format!(r#"{{"vertex_id": "v{}", "embedding": [{}.0, 1.0]}}"#, i, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        tokio::fs::write(&path, lines).await.unwrap();
+        
+        let cache = VertexCentricCache::new(2);
+        cache.put("v9", "embedding", vec![9.0], 0.5).await.unwrap();
+        
+        // Warm-up fills the remaining slot without evicting v9
+        assert_eq!(cache.warm_up_from_file(&path).await.unwrap(), 1);
+        assert_eq!(cache.get("v0", "embedding").await, Some(vec![0.0, 1.0]));
+        assert!(cache.get("v9", "embedding").await.is_some());
+        
+        let _ = tokio::fs::remove_file(&path).await;
+    }
 }
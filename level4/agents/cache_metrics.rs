@@ -0,0 +1,333 @@
+// -*- coding: utf-8 -*-
+//! Cache Metrics
+//! 
+//! Labelled counters behind `CacheStats`, with optional export to Prometheus.
+
+use crate::error::{Error, Result};
+use prometheus::{Histogram, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Label used once `max_prefixes` distinct prefixes have been seen
+pub const OVERFLOW_PREFIX: &str = "_other";
+
+/// Upper bounds (seconds) of the put latency buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Lookups for vertices sharing an id prefix
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefixStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub hit_rate: f64,
+}
+
+/// Outcomes of loads started by `prefetch`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchStats {
+    /// Vertices populated through the loader
+    pub loaded: usize,
+    /// Loads run one at a time because the system was under pressure
+    pub throttled: usize,
+    /// Loads skipped because prefetch was paused
+    pub skipped: usize,
+}
+
+/// Prefetch outcome counted by `record_prefetch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PrefetchOutcome {
+    Loaded,
+    Throttled,
+    Skipped,
+}
+
+impl PrefetchOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            PrefetchOutcome::Loaded => "loaded",
+            PrefetchOutcome::Throttled => "throttled",
+            PrefetchOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// Fixed-bucket latency histogram
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Bucket upper bounds in seconds
+    pub bounds: Vec<f64>,
+    /// Observations per bucket; the last slot counts values above every bound
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bounds: LATENCY_BUCKETS.to_vec(),
+            counts: vec![0; LATENCY_BUCKETS.len() + 1],
+            count: 0,
+            sum_seconds: 0.0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, seconds: f64) {
+        let bucket = self.bounds.iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_seconds += seconds;
+    }
+
+    pub fn mean_seconds(&self) -> f64 {
+        if self.count > 0 {
+            self.sum_seconds / self.count as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Upper bound of the bucket holding quantile `q`, or `None` when empty
+    ///
+    /// Observations above the last bound report `f64::INFINITY`.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(self.bounds.get(i).copied().unwrap_or(f64::INFINITY));
+            }
+        }
+        Some(f64::INFINITY)
+    }
+}
+
+/// Snapshot of the labelled metrics, merged into `CacheStats`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MetricsSnapshot {
+    pub prefixes: HashMap<String, PrefixStats>,
+    pub evictions: usize,
+    pub demotions: usize,
+    pub put_latency: LatencyHistogram,
+    /// Time spent waiting to acquire each internal lock
+    pub lock_waits: HashMap<String, LatencyHistogram>,
+    pub prefetch: PrefetchStats,
+}
+
+/// Collectors mirrored into a Prometheus registry
+struct PrometheusMetrics {
+    lookups: IntCounterVec,
+    evictions: IntCounterVec,
+    put_latency: Histogram,
+    lock_waits: HistogramVec,
+    prefetch: IntCounterVec,
+}
+
+/// Labelled metrics recorded by `VertexCentricCache`
+///
+/// Prefixes are the part of a vertex id before the first delimiter
+/// (`"doc_42"` is counted under `"doc"`). Distinct prefixes are capped so
+/// free-form vertex ids cannot grow the label set without bound.
+pub(crate) struct CacheMetrics {
+    prefix_delimiter: char,
+    max_prefixes: usize,
+    state: Mutex<MetricsSnapshot>,
+    exporter: OnceLock<PrometheusMetrics>,
+}
+
+impl CacheMetrics {
+    pub fn new(prefix_delimiter: char, max_prefixes: usize) -> Self {
+        Self {
+            prefix_delimiter,
+            max_prefixes,
+            state: Mutex::new(MetricsSnapshot::default()),
+            exporter: OnceLock::new(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MetricsSnapshot> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count one lookup under the vertex's prefix
+    pub fn record_lookup(&self, vertex_id: &str, hit: bool) {
+        let prefix = vertex_id.split(self.prefix_delimiter).next().unwrap_or(vertex_id);
+        // Exported under the state lock so `register` cannot backfill a lookup twice
+        let mut state = self.state();
+        let label = if state.prefixes.contains_key(prefix) || state.prefixes.len() < self.max_prefixes {
+            prefix
+        } else {
+            OVERFLOW_PREFIX
+        };
+        let stats = state.prefixes.entry(label.to_string()).or_default();
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        stats.hit_rate = stats.hits as f64 / (stats.hits + stats.misses) as f64;
+        
+        if let Some(exporter) = self.exporter.get() {
+            let result = if hit { "hit" } else { "miss" };
+            exporter.lookups.with_label_values(&[label, result]).inc();
+        }
+    }
+
+    /// Count an entry dropped from the cache, or demoted from memory to L2
+    pub fn record_eviction(&self, demoted: bool) {
+        let mut state = self.state();
+        if demoted {
+            state.demotions += 1;
+        } else {
+            state.evictions += 1;
+        }
+        if let Some(exporter) = self.exporter.get() {
+            let outcome = if demoted { "demoted" } else { "dropped" };
+            exporter.evictions.with_label_values(&[outcome]).inc();
+        }
+    }
+
+    pub fn record_put(&self, seconds: f64) {
+        let mut state = self.state();
+        state.put_latency.observe(seconds);
+        if let Some(exporter) = self.exporter.get() {
+            exporter.put_latency.observe(seconds);
+        }
+    }
+
+    /// Record `seconds` spent waiting to acquire `lock`
+    pub fn record_lock_wait(&self, lock: &str, seconds: f64) {
+        let mut state = self.state();
+        state.lock_waits.entry(lock.to_string()).or_default().observe(seconds);
+        if let Some(exporter) = self.exporter.get() {
+            exporter.lock_waits.with_label_values(&[lock]).observe(seconds);
+        }
+    }
+
+    pub fn record_prefetch(&self, outcome: PrefetchOutcome) {
+        let mut state = self.state();
+        match outcome {
+            PrefetchOutcome::Loaded => state.prefetch.loaded += 1,
+            PrefetchOutcome::Throttled => state.prefetch.throttled += 1,
+            PrefetchOutcome::Skipped => state.prefetch.skipped += 1,
+        }
+        if let Some(exporter) = self.exporter.get() {
+            exporter.prefetch.with_label_values(&[outcome.label()]).inc();
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state().clone()
+    }
+
+    /// Reset the local snapshot; exported Prometheus counters stay monotonic
+    pub fn reset(&self) {
+        *self.state() = MetricsSnapshot::default();
+    }
+
+    /// Register collectors with `registry` under `namespace`
+    ///
+    /// Counters start from the totals recorded so far; put latencies and lock
+    /// waits observed before registration are not replayed. A cache can be registered once.
+    pub fn register(&self, registry: &Registry, namespace: &str) -> Result<()> {
+        let metrics_error = |e: prometheus::Error| Error::Cache(format!("metrics registration failed: {}", e));
+        
+        let lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by vertex prefix and result").namespace(namespace),
+            &["prefix", "result"],
+        ).map_err(metrics_error)?;
+        let evictions = IntCounterVec::new(
+            Opts::new("cache_evictions_total", "Entries dropped or demoted to L2").namespace(namespace),
+            &["outcome"],
+        ).map_err(metrics_error)?;
+        let put_latency = Histogram::with_opts(
+            HistogramOpts::new("cache_put_duration_seconds", "Latency of cache puts")
+                .namespace(namespace)
+                .buckets(LATENCY_BUCKETS.to_vec()),
+        ).map_err(metrics_error)?;
+        let lock_waits = HistogramVec::new(
+            HistogramOpts::new("cache_lock_wait_seconds", "Time spent waiting on internal cache locks")
+                .namespace(namespace)
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["lock"],
+        ).map_err(metrics_error)?;
+        let prefetch = IntCounterVec::new(
+            Opts::new("cache_prefetch_total", "Prefetch loads by outcome").namespace(namespace),
+            &["outcome"],
+        ).map_err(metrics_error)?;
+        
+        // Hold the state lock so no update lands between backfill and publishing the exporter
+        let state = self.state();
+        if self.exporter.get().is_some() {
+            return Err(Error::Cache("cache metrics are already registered".to_string()));
+        }
+        registry.register(Box::new(lookups.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(evictions.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(put_latency.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(lock_waits.clone())).map_err(metrics_error)?;
+        registry.register(Box::new(prefetch.clone())).map_err(metrics_error)?;
+        
+        for (prefix, stats) in &state.prefixes {
+            lookups.with_label_values(&[prefix, "hit"]).inc_by(stats.hits as u64);
+            lookups.with_label_values(&[prefix, "miss"]).inc_by(stats.misses as u64);
+        }
+        evictions.with_label_values(&["dropped"]).inc_by(state.evictions as u64);
+        evictions.with_label_values(&["demoted"]).inc_by(state.demotions as u64);
+        prefetch.with_label_values(&["loaded"]).inc_by(state.prefetch.loaded as u64);
+        prefetch.with_label_values(&["throttled"]).inc_by(state.prefetch.throttled as u64);
+        prefetch.with_label_values(&["skipped"]).inc_by(state.prefetch.skipped as u64);
+        
+        let _ = self.exporter.set(PrometheusMetrics { lookups, evictions, put_latency, lock_waits, prefetch });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_cap_and_histogram() {
+        let metrics = CacheMetrics::new('_', 2);
+        metrics.record_lookup("doc_1", true);
+        metrics.record_lookup("doc_2", false);
+        metrics.record_lookup("user_1", true);
+        metrics.record_lookup("topic_1", false);
+        metrics.record_put(0.002);
+        metrics.record_put(10.0);
+        
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.prefixes["doc"].hit_rate, 0.5);
+        assert_eq!(snapshot.prefixes["user"].hits, 1);
+        assert_eq!(snapshot.prefixes[OVERFLOW_PREFIX].misses, 1);
+        assert_eq!(snapshot.put_latency.quantile(0.5), Some(0.005));
+        assert_eq!(snapshot.put_latency.quantile(1.0), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_register_backfills_counters() {
+        let metrics = CacheMetrics::new('_', 16);
+        metrics.record_lookup("doc_1", true);
+        metrics.record_eviction(false);
+        
+        let registry = Registry::new();
+        metrics.register(&registry, "level4").unwrap();
+        metrics.record_lookup("doc_2", true);
+        assert!(metrics.register(&registry, "level4").is_err());
+        
+        let families = registry.gather();
+        let lookups = families.iter()
+            .find(|f| f.get_name() == "level4_cache_lookups_total")
+            .unwrap();
+        assert_eq!(lookups.get_metric()[0].get_counter().get_value(), 2.0);
+    }
+}
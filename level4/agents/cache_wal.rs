@@ -0,0 +1,343 @@
+// -*- coding: utf-8 -*-
+//! Cache Write-Ahead Log
+//! 
+//! Durable record of cache mutations, replayed to rebuild the cache after a crash.
+
+use crate::error::{Error, Result};
+use crate::level4::agents::cache_invalidation::InvalidationTarget;
+use crate::level4::agents::cache_manager::CacheEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const SEGMENT_EXTENSION: &str = "wal";
+
+/// Mutation recorded before it is applied to the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalOp {
+    Put { cache_key: String, entry: CacheEntry },
+    /// Single entry leaving memory, e.g. on eviction
+    Remove { cache_key: String },
+    Invalidate { target: InvalidationTarget },
+    Clear,
+}
+
+impl WalOp {
+    /// Apply this operation to a replayed key -> entry map
+    pub fn apply(self, entries: &mut HashMap<String, CacheEntry>) {
+        match self {
+            WalOp::Put { cache_key, entry } => {
+                entries.insert(cache_key, entry);
+            }
+            WalOp::Remove { cache_key } => {
+                entries.remove(&cache_key);
+            }
+            WalOp::Invalidate { target } => entries.retain(|_, entry| match &target {
+                InvalidationTarget::Vertex(vertex_id) => &entry.vertex_id != vertex_id,
+                InvalidationTarget::Vertices(vertex_ids) => !vertex_ids.contains(&entry.vertex_id),
+                InvalidationTarget::Tag(tag) => !entry.tags.contains(tag),
+            }),
+            WalOp::Clear => entries.clear(),
+        }
+    }
+}
+
+/// One line of a WAL segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WalRecord {
+    seq: u64,
+    op: WalOp,
+}
+
+/// Compacted state: every record up to `last_seq` merged into `entries`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WalSnapshot {
+    last_seq: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// WAL configuration
+#[derive(Debug, Clone)]
+pub struct WalConfig {
+    pub dir: PathBuf,
+    /// Start a new segment once the current one reaches this size
+    pub max_segment_bytes: u64,
+    /// Closed segments allowed to accumulate before compaction is due
+    pub compact_after_segments: usize,
+    /// fsync after every record; off trades durability of the last writes for throughput
+    pub sync_on_append: bool,
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("cache_wal"),
+            max_segment_bytes: 16 * 1024 * 1024,
+            compact_after_segments: 4,
+            sync_on_append: true,
+        }
+    }
+}
+
+struct WalWriter {
+    file: tokio::fs::File,
+    segment: u64,
+    segment_bytes: u64,
+    next_seq: u64,
+    closed_segments: usize,
+}
+
+/// Segmented, append-only log of `WalOp`s plus a compacted snapshot
+///
+/// Appends go to the newest segment as JSON Lines. A record torn by a crash
+/// mid-write is dropped on replay; any other corruption is reported.
+pub struct WriteAheadLog {
+    config: WalConfig,
+    writer: Mutex<WalWriter>,
+    compaction: Mutex<()>,
+}
+
+impl std::fmt::Debug for WriteAheadLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteAheadLog")
+            .field("dir", &self.config.dir)
+            .finish()
+    }
+}
+
+impl WriteAheadLog {
+    /// Open the log in `config.dir`, creating it if needed
+    ///
+    /// Appends always start a fresh segment, so a torn tail left by a crash
+    /// is never followed by new records.
+    pub async fn open(config: WalConfig) -> Result<Self> {
+        tokio::fs::create_dir_all(&config.dir).await?;
+        
+        let segments = Self::segments(&config.dir).await?;
+        let snapshot = Self::read_snapshot(&config.dir).await?;
+        let mut last_seq = snapshot.last_seq;
+        for (_, path) in &segments {
+            if let Some(seq) = Self::read_segment(path).await?.last().map(|r| r.seq) {
+                last_seq = last_seq.max(seq);
+            }
+        }
+        
+        let segment = segments.last().map(|(index, _)| index + 1).unwrap_or(0);
+        let file = Self::create_segment(&config.dir, segment).await?;
+        
+        Ok(Self {
+            writer: Mutex::new(WalWriter {
+                file,
+                segment,
+                segment_bytes: 0,
+                next_seq: last_seq + 1,
+                closed_segments: segments.len(),
+            }),
+            compaction: Mutex::new(()),
+            config,
+        })
+    }
+
+    /// Durably append `op`; returns whether compaction is now due
+    pub async fn append(&self, op: &WalOp) -> Result<bool> {
+        let mut writer = self.writer.lock().await;
+        let record = WalRecord { seq: writer.next_seq, op: op.clone() };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        
+        writer.file.write_all(&line).await?;
+        writer.file.flush().await?;
+        if self.config.sync_on_append {
+            writer.file.sync_data().await?;
+        }
+        writer.next_seq += 1;
+        writer.segment_bytes += line.len() as u64;
+        
+        if writer.segment_bytes >= self.config.max_segment_bytes {
+            self.rotate(&mut writer).await?;
+        }
+        Ok(writer.closed_segments >= self.config.compact_after_segments)
+    }
+
+    /// Rebuild cache contents from the snapshot and every segment after it
+    pub async fn replay(&self) -> Result<HashMap<String, CacheEntry>> {
+        let _compaction = self.compaction.lock().await;
+        let mut snapshot = Self::read_snapshot(&self.config.dir).await?;
+        for (_, path) in Self::segments(&self.config.dir).await? {
+            for record in Self::read_segment(&path).await? {
+                if record.seq > snapshot.last_seq {
+                    record.op.apply(&mut snapshot.entries);
+                }
+            }
+        }
+        Ok(snapshot.entries)
+    }
+
+    /// Merge closed segments into the snapshot and delete them
+    ///
+    /// Appends continue into a fresh segment while compaction runs. Returns
+    /// the number of segments merged.
+    pub async fn compact(&self) -> Result<usize> {
+        let _compaction = self.compaction.lock().await;
+        let active = {
+            let mut writer = self.writer.lock().await;
+            if writer.segment_bytes > 0 {
+                self.rotate(&mut writer).await?;
+            }
+            writer.segment
+        };
+        
+        let closed: Vec<(u64, PathBuf)> = Self::segments(&self.config.dir).await?
+            .into_iter()
+            .filter(|(index, _)| *index < active)
+            .collect();
+        if closed.is_empty() {
+            return Ok(0);
+        }
+        
+        let mut snapshot = Self::read_snapshot(&self.config.dir).await?;
+        for (_, path) in &closed {
+            for record in Self::read_segment(path).await? {
+                if record.seq > snapshot.last_seq {
+                    snapshot.last_seq = record.seq;
+                    record.op.apply(&mut snapshot.entries);
+                }
+            }
+        }
+        
+        let snapshot_path = self.config.dir.join(SNAPSHOT_FILE);
+        let tmp_path = snapshot_path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&serde_json::to_vec(&snapshot)?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, &snapshot_path).await?;
+        
+        // Only delete segments once the snapshot covering them is in place
+        for (_, path) in &closed {
+            tokio::fs::remove_file(path).await?;
+        }
+        let mut writer = self.writer.lock().await;
+        writer.closed_segments = writer.closed_segments.saturating_sub(closed.len());
+        
+        Ok(closed.len())
+    }
+
+    async fn rotate(&self, writer: &mut WalWriter) -> Result<()> {
+        writer.file.sync_all().await?;
+        writer.segment += 1;
+        writer.file = Self::create_segment(&self.config.dir, writer.segment).await?;
+        writer.segment_bytes = 0;
+        writer.closed_segments += 1;
+        Ok(())
+    }
+
+    async fn create_segment(dir: &Path, index: u64) -> Result<tokio::fs::File> {
+        let path = dir.join(format!("{:020}.{}", index, SEGMENT_EXTENSION));
+        Ok(tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?)
+    }
+
+    /// Segment files in `dir`, oldest first
+    async fn segments(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+        let mut segments = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(dir).await?;
+        while let Some(dir_entry) = read_dir.next_entry().await? {
+            let path = dir_entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(index) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) {
+                segments.push((index, path));
+            }
+        }
+        segments.sort_by_key(|(index, _)| *index);
+        Ok(segments)
+    }
+
+    async fn read_snapshot(dir: &Path) -> Result<WalSnapshot> {
+        match tokio::fs::read(dir.join(SNAPSHOT_FILE)).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(WalSnapshot::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parse a segment, dropping a final line torn by a crash mid-write
+    ///
+    /// Every segment may end torn: a restart opens a new segment rather than
+    /// appending after the damaged one.
+    async fn read_segment(path: &Path) -> Result<Vec<WalRecord>> {
+        let contents = tokio::fs::read_to_string(path).await?;
+        let lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+        let mut records = Vec::with_capacity(lines.len());
+        
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str::<WalRecord>(line) {
+                Ok(record) => records.push(record),
+                Err(e) if i + 1 == lines.len() && !contents.ends_with('\n') => {
+                    tracing::warn!("Dropping torn WAL record at end of {}: {}", path.display(), e);
+                }
+                Err(e) => {
+                    return Err(Error::Cache(format!("corrupt WAL record {}:{}: {}", path.display(), i + 1, e)));
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::cache_manager::CacheValue;
+
+    fn entry(vertex_id: &str) -> CacheEntry {
+        CacheEntry {
+            vertex_id: vertex_id.to_string(),
+            key: "key1".to_string(),
+            value: CacheValue::Embedding(vec![1.0]),
+            timestamp: 0,
+            access_count: 1,
+            computation_cost: 0.5,
+            inserted_at: 0,
+            tags: Vec::new(),
+            size_bytes: 0,
+            namespace: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotation_compaction_and_torn_tail() {
+        let dir = std::env::temp_dir().join(format!("cache_wal_{}", uuid::Uuid::new_v4()));
+        let config = WalConfig {
+            dir: dir.clone(),
+            max_segment_bytes: 1,
+            compact_after_segments: 2,
+            sync_on_append: false,
+        };
+        let wal = WriteAheadLog::open(config.clone()).await.unwrap();
+        
+        let put = |v: &str| WalOp::Put { cache_key: format!("{}:key1", v), entry: entry(v) };
+        assert!(!wal.append(&put("v1")).await.unwrap());
+        assert!(wal.append(&put("v2")).await.unwrap());
+        wal.append(&WalOp::Invalidate { target: InvalidationTarget::Vertex("v1".to_string()) }).await.unwrap();
+        
+        assert_eq!(wal.compact().await.unwrap(), 3);
+        wal.append(&put("v3")).await.unwrap();
+        drop(wal);
+        
+        // Simulate a crash halfway through writing a record, then a restart
+        let torn = dir.join(format!("{:020}.{}", 99, SEGMENT_EXTENSION));
+        tokio::fs::write(&torn, b"{\"seq\":99,\"op\":{\"op\":\"cl").await.unwrap();
+        let reopened = WriteAheadLog::open(config.clone()).await.unwrap();
+        
+        let mut keys: Vec<String> = reopened.replay().await.unwrap().into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["v2:key1".to_string(), "v3:key1".to_string()]);
+        
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
@@ -0,0 +1,160 @@
+// -*- coding: utf-8 -*-
+//! Clocks and Id Generators
+//!
+//! Injectable time and id sources, so chains, streams and cache entries can
+//! be produced deterministically in tests.
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of wall-clock and monotonic time
+pub trait Clock: Send + Sync + Debug {
+    /// Time since the Unix epoch; zero if the clock reads earlier
+    fn now(&self) -> Duration;
+
+    /// Monotonic instant, for measuring elapsed time
+    fn instant(&self) -> Instant;
+
+    fn now_secs(&self) -> u64 {
+        self.now().as_secs()
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.now().as_millis() as u64
+    }
+}
+
+/// Source of unique ids, e.g. for reasoning chains
+pub trait IdGenerator: Send + Sync + Debug {
+    fn next_id(&self) -> String;
+}
+
+/// The operating system's clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when advanced
+#[derive(Debug)]
+pub struct ManualClock {
+    origin: Instant,
+    wall_start: Duration,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Clock reading `wall_start` since the Unix epoch
+    pub fn new(wall_start: Duration) -> Self {
+        Self {
+            origin: Instant::now(),
+            wall_start,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move both the wall clock and the monotonic clock forward
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.wall_start + self.elapsed()
+    }
+
+    fn instant(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
+
+/// Random v4 UUIDs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn next_id(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// `<prefix>-0`, `<prefix>-1`, ... in call order
+#[derive(Debug)]
+pub struct SequentialIds {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            next: AtomicU64::new(0),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self) -> String {
+        format!("{}-{}", self.prefix, self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub(crate) fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+pub(crate) fn uuid_ids() -> Arc<dyn IdGenerator> {
+    Arc::new(UuidGenerator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_moves_only_when_advanced() {
+        let clock = ManualClock::new(Duration::from_secs(1_000));
+        let start = clock.instant();
+        assert_eq!(clock.now(), Duration::from_secs(1_000));
+        assert_eq!(clock.now_secs(), 1_000);
+        assert_eq!(clock.now_ms(), 1_000_000);
+        assert_eq!(clock.instant(), start);
+
+        clock.advance(Duration::from_millis(1_500));
+        assert_eq!(clock.now_ms(), 1_001_500);
+        assert_eq!(clock.now_secs(), 1_001);
+        assert_eq!(clock.instant() - start, Duration::from_millis(1_500));
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(clock.now_secs(), 1_002);
+        assert_eq!(clock.instant() - start, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_sequential_ids_count_up() {
+        let ids = SequentialIds::new("chain");
+        assert_eq!(ids.next_id(), "chain-0");
+        assert_eq!(ids.next_id(), "chain-1");
+        assert_eq!(ids.next_id(), "chain-2");
+
+        // Each generator has its own sequence
+        assert_eq!(SequentialIds::new("stream").next_id(), "stream-0");
+        assert_eq!(ids.next_id(), "chain-3");
+    }
+}
@@ -0,0 +1,184 @@
+// -*- coding: utf-8 -*-
+//! Generation Constraints
+//!
+//! Organisation coding policies checked against generated code: a size
+//! limit, forbidden imports and functions, and the error-handling style.
+//! Checks run on the code with comments and string literals removed, so
+//! only code that is actually there counts.
+
+use crate::level4::agents::code_safety::{contains_token, strip_comments_and_strings};
+use crate::level4::agents::generate_code::ProgrammingLanguage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How generated code may deal with errors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorHandling {
+    #[default]
+    Any,
+    /// Errors are returned, never panicked on: no `.unwrap()`, `.expect(..)`,
+    /// `panic!`, `todo!`, `unimplemented!` or `unreachable!`. Only checked
+    /// for Rust; exceptions are the normal error path of the script languages.
+    NoPanics,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationConstraints {
+    /// Longest allowed code, in lines
+    pub max_lines: Option<usize>,
+    /// Crates, modules or packages the code may not import, e.g. `reqwest`
+    /// or `subprocess`
+    pub forbidden_crates: Vec<String>,
+    /// Functions, methods or paths the code may not use, e.g. `eval` or
+    /// `std::process::exit`
+    pub forbidden_functions: Vec<String>,
+    pub error_handling: ErrorHandling,
+}
+
+/// One way generated code breaks its constraints; lines are 1-based
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConstraintViolation {
+    TooManyLines { lines: usize, max: usize },
+    ForbiddenCrate { name: String, line: usize },
+    ForbiddenFunction { name: String, line: usize },
+    /// A panicking construct under `ErrorHandling::NoPanics`
+    Panics { construct: String, line: usize },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::TooManyLines { lines, max } => write!(f, "{} lines, over the limit of {}", lines, max),
+            ConstraintViolation::ForbiddenCrate { name, line } => write!(f, "line {}: imports forbidden '{}'", line, name),
+            ConstraintViolation::ForbiddenFunction { name, line } => write!(f, "line {}: uses forbidden '{}'", line, name),
+            ConstraintViolation::Panics { construct, line } => write!(f, "line {}: '{}' can panic", line, construct),
+        }
+    }
+}
+
+/// Constructs `ErrorHandling::NoPanics` rules out
+const PANICKING: &[&str] = &[".unwrap(", ".expect(", "panic!", "todo!", "unimplemented!", "unreachable!"];
+
+impl GenerationConstraints {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Every violation in `code`, in line order after any size violation
+    pub fn check(&self, code: &str, language: &ProgrammingLanguage) -> Vec<ConstraintViolation> {
+        let mut violations = Vec::new();
+        let line_count = code.lines().count();
+        if let Some(max) = self.max_lines.filter(|max| line_count > *max) {
+            violations.push(ConstraintViolation::TooManyLines { lines: line_count, max });
+        }
+
+        let stripped = strip_comments_and_strings(code, language);
+        for (index, (line, original)) in stripped.lines().zip(code.lines()).enumerate() {
+            let number = index + 1;
+            for name in &self.forbidden_crates {
+                if imports(line, original, name, language) {
+                    violations.push(ConstraintViolation::ForbiddenCrate { name: name.clone(), line: number });
+                }
+            }
+            for name in self.forbidden_functions.iter().filter(|name| contains_token(line, name)) {
+                violations.push(ConstraintViolation::ForbiddenFunction { name: name.clone(), line: number });
+            }
+            if self.error_handling == ErrorHandling::NoPanics && *language == ProgrammingLanguage::Rust {
+                for construct in PANICKING.iter().filter(|construct| contains_token(line, construct)) {
+                    violations.push(ConstraintViolation::Panics {
+                        construct: construct.trim_matches(|c| c == '.' || c == '(').to_string(),
+                        line: number,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Whether a line imports `name`; `line` is stripped of comments and
+/// strings, `original` is not, since script imports name packages in strings
+fn imports(line: &str, original: &str, name: &str, language: &ProgrammingLanguage) -> bool {
+    let is_module = |path: &str| path == name || path.starts_with(&format!("{}.", name));
+    match language {
+        ProgrammingLanguage::Rust => {
+            let name = name.replace('-', "_");
+            contains_token(line, &format!("extern crate {}", name)) || is_path_root(line, &name)
+        }
+        ProgrammingLanguage::Python => {
+            let line = line.trim();
+            if let Some(modules) = line.strip_prefix("import ") {
+                modules.split(',').any(|module| is_module(module.split(" as ").next().unwrap_or("").trim()))
+            } else if let Some(rest) = line.strip_prefix("from ") {
+                rest.split_whitespace().next().is_some_and(is_module)
+            } else {
+                false
+            }
+        }
+        ProgrammingLanguage::JavaScript | ProgrammingLanguage::Rhai => {
+            ["require", "import", "from"].iter().any(|keyword| contains_token(line, keyword))
+                && ["\"", "'"].iter().any(|quote| {
+                    let quoted = format!("{}{}{}", quote, name, quote);
+                    ["require(", "from ", "import "].iter()
+                        .any(|keyword| original.contains(&format!("{}{}", keyword, quoted)))
+                })
+        }
+    }
+}
+
+/// Whether a path in `line` starts at crate `name`, as in `name::item`
+/// but not `other::name::item`
+fn is_path_root(line: &str, name: &str) -> bool {
+    let root = format!("{}::", name);
+    line.match_indices(&root).any(|(at, _)| {
+        let before = &line[..at];
+        !before.ends_with("::") && !before.chars().next_back().is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_constraints() {
+        let constraints = GenerationConstraints {
+            max_lines: Some(4),
+            forbidden_crates: vec!["reqwest".to_string()],
+            forbidden_functions: vec!["std::process::exit".to_string()],
+            error_handling: ErrorHandling::NoPanics,
+        };
+        let code = "// reqwest::get would be nicer\n\
+                    fn fetch(url: &str) -> String {\n    \
+                        let body = reqwest::blocking::get(url).unwrap();\n    \
+                        if body.is_empty() { std::process::exit(1) }\n    \
+                        my::reqwest::wrap(body)\n\
+                    }";
+        assert_eq!(constraints.check(code, &ProgrammingLanguage::Rust), vec![
+            ConstraintViolation::TooManyLines { lines: 6, max: 4 },
+            ConstraintViolation::ForbiddenCrate { name: "reqwest".to_string(), line: 3 },
+            ConstraintViolation::Panics { construct: "unwrap".to_string(), line: 3 },
+            ConstraintViolation::ForbiddenFunction { name: "std::process::exit".to_string(), line: 4 },
+        ]);
+        assert!(GenerationConstraints::default().check(code, &ProgrammingLanguage::Rust).is_empty());
+    }
+
+    #[test]
+    fn test_script_imports() {
+        let constraints = GenerationConstraints {
+            forbidden_crates: vec!["subprocess".to_string(), "child_process".to_string()],
+            ..Default::default()
+        };
+        let python = "import os, subprocess as sp\n# import subprocess\nfrom subprocess.run import x\n";
+        let lines: Vec<usize> = constraints.check(python, &ProgrammingLanguage::Python).iter()
+            .map(|v| match v {
+                ConstraintViolation::ForbiddenCrate { line, .. } => *line,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(lines, vec![1, 3]);
+
+        let js = "const { exec } = require('child_process');\n// require('child_process')\n";
+        assert_eq!(constraints.check(js, &ProgrammingLanguage::JavaScript).len(), 1);
+    }
+}
@@ -0,0 +1,250 @@
+// -*- coding: utf-8 -*-
+//! Code Documentation
+//!
+//! Doc blocks and a usage example for generated functions, kept beside the
+//! code rather than in it so tools can render them separately.
+//!
+//! The first function other than `main` is taken as the entry point: it is
+//! summarised by the generation description and called by the example.
+
+use crate::level4::agents::generate_code::ProgrammingLanguage;
+use crate::level4::agents::test_synthesis::{self, ParamKind};
+use serde::{Deserialize, Serialize};
+
+/// Doc block for one generated function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDoc {
+    pub function: String,
+    /// In the language's own syntax: `///` lines for Rust and Rhai, a JSDoc
+    /// block for JavaScript, a docstring for Python
+    pub doc_block: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeDocs {
+    pub functions: Vec<FunctionDoc>,
+    /// Code calling the entry point with ordinary arguments, runnable when
+    /// appended to the generated code
+    pub usage_example: Option<String>,
+}
+
+impl CodeDocs {
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.usage_example.is_none()
+    }
+}
+
+/// Docs for the functions declared in `code`
+pub fn document(code: &str, language: &ProgrammingLanguage, description: &str) -> CodeDocs {
+    let signatures = test_synthesis::signatures(code, language);
+    let mut functions = signatures.iter().filter(|s| s.name != "main");
+    let Some(entry) = functions.next() else {
+        return CodeDocs::default();
+    };
+
+    let summary = summary(description);
+    let mut docs = vec![FunctionDoc {
+        function: entry.name.clone(),
+        doc_block: doc_block(&summary, &entry.params, language),
+    }];
+    for helper in functions {
+        docs.push(FunctionDoc {
+            function: helper.name.clone(),
+            doc_block: doc_block(&format!("Helper for `{}`.", entry.name), &helper.params, language),
+        });
+    }
+
+    let usage_example = match language {
+        ProgrammingLanguage::Rust => rust_usage(code, &entry.name),
+        _ => Some(script_usage(&entry.name, &entry.params, language)),
+    };
+    CodeDocs {
+        functions: docs,
+        usage_example,
+    }
+}
+
+/// First line of `description` as a capitalised sentence
+fn summary(description: &str) -> String {
+    let line = description.lines().next().unwrap_or("").trim().trim_end_matches('.');
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else {
+        return "Generated function.".to_string();
+    };
+    format!("{}{}.", first.to_uppercase(), chars.as_str())
+}
+
+fn describe(kind: &ParamKind) -> String {
+    match kind {
+        ParamKind::Int(_) => "integer".to_string(),
+        ParamKind::Float => "number".to_string(),
+        ParamKind::Str => "string".to_string(),
+        ParamKind::Char => "character".to_string(),
+        ParamKind::Bool => "boolean".to_string(),
+        ParamKind::Seq(_) => "sequence".to_string(),
+        ParamKind::Option(inner) => format!("optional {}", describe(inner)),
+        ParamKind::Map(..) => "mapping".to_string(),
+        ParamKind::Dynamic | ParamKind::Unknown => "value".to_string(),
+    }
+}
+
+fn doc_block(summary: &str, params: &[(String, ParamKind)], language: &ProgrammingLanguage) -> String {
+    let mut lines = Vec::new();
+    match language {
+        ProgrammingLanguage::Python => {
+            lines.push(format!("\"\"\"{}", summary));
+            if !params.is_empty() {
+                lines.push(String::new());
+                lines.push("Args:".to_string());
+                lines.extend(params.iter().map(|(name, kind)| format!("    {}: {}", name, describe(kind))));
+            }
+            lines.push("\"\"\"".to_string());
+        }
+        ProgrammingLanguage::JavaScript => {
+            lines.push("/**".to_string());
+            lines.push(format!(" * {}", summary));
+            if !params.is_empty() {
+                lines.push(" *".to_string());
+                lines.extend(params.iter().map(|(name, _)| format!(" * @param {{*}} {}", name)));
+            }
+            lines.push(" */".to_string());
+        }
+        ProgrammingLanguage::Rust | ProgrammingLanguage::Rhai => {
+            lines.push(format!("/// {}", summary));
+            if !params.is_empty() {
+                lines.push("///".to_string());
+                lines.push("/// # Arguments".to_string());
+                lines.push("///".to_string());
+                lines.extend(params.iter().map(|(name, kind)| format!("/// * `{}` - {}", name, describe(kind))));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// `main` calling `entry`; `None` when the code has its own `main`
+fn rust_usage(code: &str, entry: &str) -> Option<String> {
+    let file = syn::parse_file(code).ok()?;
+    let functions: Vec<&syn::Signature> = file.items.iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(function) => Some(&function.sig),
+            _ => None,
+        })
+        .collect();
+    if functions.iter().any(|sig| sig.ident == "main") {
+        return None;
+    }
+    let sig = functions.iter().find(|sig| sig.ident == entry)?;
+    let generics: Vec<String> = sig.generics.type_params().map(|p| p.ident.to_string()).collect();
+    let args: Vec<String> = sig.inputs.iter()
+        .filter_map(|input| match input {
+            syn::FnArg::Typed(typed) => Some(rust_argument(&typed.ty, &generics)),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    Some(format!(
+        "fn main() {{\n    let result = {}({});\n    println!(\"{{:?}}\", result);\n}}",
+        entry,
+        args.join(", "),
+    ))
+}
+
+/// Ordinary argument expression of type `ty`
+fn rust_argument(ty: &syn::Type, generics: &[String]) -> String {
+    match ty {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            // String literals are references already
+            syn::Type::Path(path) if path.path.is_ident("str") => "\"abc\"".to_string(),
+            elem => format!("&{}", rust_argument(elem, generics)),
+        },
+        syn::Type::Slice(slice) => format!("[{}]", rust_samples(&slice.elem, generics)),
+        syn::Type::Path(path) => {
+            let Some(segment) = path.path.segments.last() else {
+                return "Default::default()".to_string();
+            };
+            let arg = match &segment.arguments {
+                syn::PathArguments::AngleBracketed(angle) => angle.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                }),
+                _ => None,
+            };
+            let name = segment.ident.to_string();
+            match (name.as_str(), arg) {
+                ("i8" | "i16" | "i32" | "i64" | "i128" | "isize"
+                | "u8" | "u16" | "u32" | "u64" | "u128" | "usize", _) => "3".to_string(),
+                ("f32" | "f64", _) => "2.5".to_string(),
+                ("String", _) => "String::from(\"abc\")".to_string(),
+                ("char", _) => "'a'".to_string(),
+                ("bool", _) => "true".to_string(),
+                ("Vec", Some(item)) => format!("vec![{}]", rust_samples(item, generics)),
+                ("Option", Some(inner)) => format!("Some({})", rust_argument(inner, generics)),
+                ("Box", Some(inner)) => format!("Box::new({})", rust_argument(inner, generics)),
+                // Generic parameters are most often numeric, e.g. `T: Ord`
+                _ if generics.contains(&name) => "3".to_string(),
+                _ => "Default::default()".to_string(),
+            }
+        }
+        _ => "Default::default()".to_string(),
+    }
+}
+
+/// Three comma-separated elements of type `ty`, ascending where the type
+/// allows, so functions expecting sorted input get it
+fn rust_samples(ty: &syn::Type, generics: &[String]) -> String {
+    match rust_argument(ty, generics).as_str() {
+        "3" => "1, 2, 3".to_string(),
+        "2.5" => "0.5, 1.5, 2.5".to_string(),
+        "\"abc\"" => "\"a\", \"b\", \"c\"".to_string(),
+        other => vec![other; 3].join(", "),
+    }
+}
+
+/// Call of `entry` that prints its result
+fn script_usage(entry: &str, params: &[(String, ParamKind)], language: &ProgrammingLanguage) -> String {
+    let args: Vec<String> = params.iter().map(|(_, kind)| kind.typical(language)).collect();
+    let call = format!("{}({})", entry, args.join(", "));
+    match language {
+        ProgrammingLanguage::Python => format!("result = {}\nprint(result)", call),
+        ProgrammingLanguage::JavaScript => format!("const result = {};\nconsole.log(result);", call),
+        _ => format!("let result = {};\nprint(result);", call),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_docs_and_example() {
+        let code = "fn binary_search<T: Ord>(arr: &[T], target: &T) -> Option<usize> { None }\n\
+                    fn midpoint(low: usize, high: usize) -> usize { low + (high - low) / 2 }";
+        let docs = document(code, &ProgrammingLanguage::Rust, "find an element in a sorted array");
+
+        assert_eq!(docs.functions.len(), 2);
+        assert_eq!(docs.functions[0].doc_block, "/// Find an element in a sorted array.\n///\n/// # Arguments\n///\n\
+            /// * `arr` - sequence\n/// * `target` - integer");
+        assert!(docs.functions[1].doc_block.starts_with("/// Helper for `binary_search`."));
+        assert_eq!(
+            docs.usage_example.as_deref(),
+            Some("fn main() {\n    let result = binary_search(&[1, 2, 3], &3);\n    println!(\"{:?}\", result);\n}"),
+        );
+
+        // Code with its own `main` cannot take another
+        let with_main = format!("{}\nfn main() {{}}", code);
+        assert!(document(&with_main, &ProgrammingLanguage::Rust, "search").usage_example.is_none());
+        assert!(document("fn main() {}", &ProgrammingLanguage::Rust, "stub").is_empty());
+    }
+
+    #[test]
+    fn test_script_docs_and_example() {
+        let python = "def mean(xs: list[float], strict: bool = False):\n    return sum(xs) / len(xs)\n";
+        let docs = document(python, &ProgrammingLanguage::Python, "average of a list");
+        assert_eq!(docs.functions[0].doc_block, "\"\"\"Average of a list.\n\nArgs:\n    xs: sequence\n    strict: boolean\n\"\"\"");
+        assert_eq!(docs.usage_example.as_deref(), Some("result = mean([1, 2, 3], True)\nprint(result)"));
+
+        let rhai = "fn calculate(operation, a, b) {\n    a + b\n}";
+        let docs = document(rhai, &ProgrammingLanguage::Rhai, "calculator");
+        assert_eq!(docs.usage_example.as_deref(), Some("let result = calculate(3, 3, 3);\nprint(result);"));
+    }
+}
@@ -0,0 +1,136 @@
+// -*- coding: utf-8 -*-
+//! Code Formatting
+//!
+//! Formatting stage run on generated code before it is returned.
+
+use crate::error::{Error, Result};
+use crate::level4::agents::generate_code::ProgrammingLanguage;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a `CommandFormatter` may run before it is killed
+pub const DEFAULT_FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running formatter is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Formats generated code in the languages it supports
+///
+/// `format` may block; async callers run it on the blocking thread pool.
+pub trait CodeFormatter: Send + Sync + std::fmt::Debug {
+    fn supports(&self, language: &ProgrammingLanguage) -> bool;
+
+    /// `code` formatted, or an error if it could not be, e.g. on a syntax error
+    fn format(&self, code: &str, language: &ProgrammingLanguage) -> Result<String>;
+}
+
+/// Formatter that pipes code through an external program's stdin and stdout
+#[derive(Debug, Clone)]
+pub struct CommandFormatter {
+    program: String,
+    args: Vec<String>,
+    languages: Vec<ProgrammingLanguage>,
+    timeout: Duration,
+}
+
+impl CommandFormatter {
+    pub fn new(program: &str, args: &[&str], languages: Vec<ProgrammingLanguage>) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            languages,
+            timeout: DEFAULT_FORMAT_TIMEOUT,
+        }
+    }
+
+    /// Kill the program and fail if it runs longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// `rustfmt` for Rust
+    pub fn rustfmt() -> Self {
+        Self::new("rustfmt", &["--edition", "2021"], vec![ProgrammingLanguage::Rust])
+    }
+
+    /// `prettier`, or any tool taking the same arguments, for JavaScript
+    pub fn prettier() -> Self {
+        Self::new("prettier", &["--stdin-filepath", "generated.js"], vec![ProgrammingLanguage::JavaScript])
+    }
+}
+
+impl CodeFormatter for CommandFormatter {
+    fn supports(&self, language: &ProgrammingLanguage) -> bool {
+        self.languages.contains(language)
+    }
+
+    fn format(&self, code: &str, _language: &ProgrammingLanguage) -> Result<String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::CodeGeneration(format!("failed to start {}: {}", self.program, e)))?;
+        // Each pipe gets its own thread, so a program that stops reading or
+        // writing cannot hold us past the timeout; dropping stdin once the
+        // code is written signals end of input
+        let stdin = child.stdin.take();
+        let input = code.to_string();
+        let writer = thread::spawn(move || match stdin {
+            Some(mut stdin) => stdin.write_all(input.as_bytes()),
+            None => Ok(()),
+        });
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+        
+        let Some(status) = wait_timeout(&mut child, self.timeout)? else {
+            return Err(Error::CodeGeneration(format!("{} timed out after {:?}", self.program, self.timeout)));
+        };
+        let joined = |name: &str| Error::CodeGeneration(format!("{} {} thread panicked", self.program, name));
+        let stdout = stdout.join().map_err(|_| joined("stdout"))??;
+        let stderr = stderr.join().map_err(|_| joined("stderr"))??;
+        if !status.success() {
+            return Err(Error::CodeGeneration(format!(
+                "{} failed: {}",
+                self.program,
+                String::from_utf8_lossy(&stderr).trim(),
+            )));
+        }
+        writer.join().map_err(|_| joined("stdin"))??;
+        String::from_utf8(stdout)
+            .map_err(|e| Error::CodeGeneration(format!("{} wrote invalid UTF-8: {}", self.program, e)))
+    }
+}
+
+/// Everything `pipe` yields until it closes, read on its own thread
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut bytes)?;
+        }
+        Ok(bytes)
+    })
+}
+
+/// Exit status of `child`, or `None` if it was killed for running past
+/// `timeout`
+fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            // It may have exited since `try_wait`; either way it is reaped
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
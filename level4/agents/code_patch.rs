@@ -0,0 +1,245 @@
+// -*- coding: utf-8 -*-
+//! Code Patches
+//!
+//! Line-level edits between two versions of a piece of code, as a
+//! structured edit list and as a unified diff, so agent loops can make
+//! targeted changes instead of replacing whole files.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// Unchanged lines shown around each change in the unified diff
+const CONTEXT_LINES: usize = 3;
+
+/// One step of a line diff, by index into the old or new lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineOp {
+    Keep(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Shortest edit script from `old` to `new`, via their longest common
+/// subsequence; deletions come before insertions within a change
+pub(crate) fn line_ops(old: &[&str], new: &[&str]) -> Vec<LineOp> {
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(LineOp::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(LineOp::Delete(i));
+            i += 1;
+        } else {
+            ops.push(LineOp::Insert(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Replace `remove` at `line` with `insert`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeEdit {
+    /// 1-based line in the original code where the edit starts; for pure
+    /// insertions, the line the new lines go before
+    pub line: usize,
+    pub remove: Vec<String>,
+    pub insert: Vec<String>,
+}
+
+/// Edits turning one version of some code into another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodePatch {
+    pub edits: Vec<CodeEdit>,
+    /// The same edits as a unified diff
+    pub unified_diff: String,
+}
+
+impl CodePatch {
+    /// Patch from `old` to `new`; `path` names the file in the diff headers
+    pub fn between(old: &str, new: &str, path: &str) -> Self {
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        let ops = line_ops(&old_lines, &new_lines);
+
+        let mut edits: Vec<CodeEdit> = Vec::new();
+        let mut old_at = 0;
+        let mut extending = false;
+        for op in &ops {
+            match *op {
+                LineOp::Keep(i, _) => {
+                    old_at = i + 1;
+                    extending = false;
+                    continue;
+                }
+                LineOp::Delete(i) => old_at = i,
+                LineOp::Insert(_) => {}
+            }
+            if !extending {
+                edits.push(CodeEdit {
+                    line: old_at + 1,
+                    remove: Vec::new(),
+                    insert: Vec::new(),
+                });
+                extending = true;
+            }
+            let edit = edits.last_mut().expect("edit was just pushed");
+            match *op {
+                LineOp::Delete(i) => {
+                    edit.remove.push(old_lines[i].to_string());
+                    old_at = i + 1;
+                }
+                LineOp::Insert(j) => edit.insert.push(new_lines[j].to_string()),
+                LineOp::Keep(..) => {}
+            }
+        }
+
+        Self {
+            unified_diff: unified_diff(&ops, &old_lines, &new_lines, path),
+            edits,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Apply the edits to `code`, which must still contain the removed lines
+    /// where the patch expects them
+    pub fn apply(&self, code: &str) -> Result<String> {
+        let lines: Vec<&str> = code.lines().collect();
+        let mut patched: Vec<&str> = Vec::with_capacity(lines.len());
+        let mut next = 0;
+        for edit in &self.edits {
+            let does_not_apply = || Error::CodeGeneration(format!("patch does not apply at line {}", edit.line));
+            // Lines are 1-based, so a hand-built or deserialized line 0 is invalid
+            let start = edit.line.checked_sub(1).ok_or_else(does_not_apply)?;
+            let end = start + edit.remove.len();
+            if start < next || end > lines.len() || lines[start..end] != edit.remove[..] {
+                return Err(does_not_apply());
+            }
+            patched.extend(&lines[next..start]);
+            patched.extend(edit.insert.iter().map(String::as_str));
+            next = end;
+        }
+        patched.extend(&lines[next..]);
+
+        let mut result = patched.join("\n");
+        if code.ends_with('\n') {
+            result.push('\n');
+        }
+        Ok(result)
+    }
+}
+
+/// `ops` as a unified diff with `CONTEXT_LINES` of context per hunk
+fn unified_diff(ops: &[LineOp], old: &[&str], new: &[&str], path: &str) -> String {
+    let changes: Vec<usize> = ops.iter().enumerate()
+        .filter(|(_, op)| !matches!(op, LineOp::Keep(..)))
+        .map(|(at, _)| at)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes close enough that their context would touch share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &at in &changes {
+        match hunks.last_mut() {
+            Some((_, last)) if at - *last <= 2 * CONTEXT_LINES + 1 => *last = at,
+            _ => hunks.push((at, at)),
+        }
+    }
+
+    // Line positions before each op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        positions.push((i, j));
+        match op {
+            LineOp::Keep(..) => { i += 1; j += 1; }
+            LineOp::Delete(_) => i += 1,
+            LineOp::Insert(_) => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    for (first, last) in hunks {
+        let from = first.saturating_sub(CONTEXT_LINES);
+        let to = (last + 1 + CONTEXT_LINES).min(ops.len());
+        let (old_start, new_start) = positions[from];
+        let (old_end, new_end) = positions[to];
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start),
+        ));
+        for op in &ops[from..to] {
+            match *op {
+                LineOp::Keep(i, _) => diff.push_str(&format!(" {}\n", old[i])),
+                LineOp::Delete(i) => diff.push_str(&format!("-{}\n", old[i])),
+                LineOp::Insert(j) => diff.push_str(&format!("+{}\n", new[j])),
+            }
+        }
+    }
+    diff
+}
+
+/// `start,count` as written in hunk headers; empty ranges name the line before
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_roundtrip_and_diff() {
+        let old = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn main() {}\n";
+        let new = "/// Sum of two numbers\nfn add(a: i64, b: i64) -> i64 {\n    a + b\n}\n\nfn main() {}\n";
+        let patch = CodePatch::between(old, new, "lib.rs");
+
+        assert_eq!(patch.edits, vec![CodeEdit {
+            line: 1,
+            remove: vec!["fn add(a: i32, b: i32) -> i32 {".to_string()],
+            insert: vec![
+                "/// Sum of two numbers".to_string(),
+                "fn add(a: i64, b: i64) -> i64 {".to_string(),
+            ],
+        }]);
+        assert_eq!(patch.apply(old).unwrap(), new);
+        assert_eq!(patch.unified_diff, "--- a/lib.rs\n+++ b/lib.rs\n@@ -1,4 +1,5 @@\n\
+            -fn add(a: i32, b: i32) -> i32 {\n\
+            +/// Sum of two numbers\n\
+            +fn add(a: i64, b: i64) -> i64 {\n     a + b\n }\n \n");
+
+        // The code moved on since the patch was made
+        assert!(patch.apply("fn other() {}\n").is_err());
+        assert!(CodePatch::between(old, old, "lib.rs").is_empty());
+
+        let mut zero = patch.clone();
+        zero.edits[0].line = 0;
+        assert!(zero.apply(old).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+// -*- coding: utf-8 -*-
+//! Code Safety Analysis
+//!
+//! Scores generated code by the constructs it actually uses. Rust is parsed
+//! with `syn`; other languages, and Rust that does not parse, are scanned
+//! with comments and string literals removed, so a mention of `unwrap()` in
+//! a comment costs nothing.
+
+use crate::level4::agents::generate_code::ProgrammingLanguage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use syn::visit::{self, Visit};
+
+/// Construct that moves the safety score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CodeConstruct {
+    /// `unsafe` blocks, functions or impls
+    Unsafe,
+    /// Spawning processes or shelling out
+    ProcessCall,
+    /// Reading or writing the filesystem
+    FilesystemCall,
+    /// `panic!`, `todo!`, `unimplemented!`, `unreachable!`
+    PanickingMacro,
+    /// `.unwrap()` or `.expect(..)`
+    Unwrap,
+    /// A loop with no way out: no `break`, `return` or `?`
+    UnboundedLoop,
+    /// Failures surfaced through `Result`
+    ResultType,
+    /// Absence surfaced through `Option`
+    OptionType,
+}
+
+impl CodeConstruct {
+    /// Change to the score when the construct appears, however often
+    pub fn weight(&self) -> f64 {
+        match self {
+            CodeConstruct::Unsafe => -0.3,
+            CodeConstruct::ProcessCall => -0.3,
+            CodeConstruct::FilesystemCall => -0.1,
+            CodeConstruct::PanickingMacro => -0.2,
+            CodeConstruct::Unwrap => -0.1,
+            CodeConstruct::UnboundedLoop => -0.2,
+            CodeConstruct::ResultType => 0.1,
+            CodeConstruct::OptionType => 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyAnalysis {
+    /// In `0.0..=1.0`, starting from `1.0`
+    pub score: f64,
+    pub constructs: BTreeSet<CodeConstruct>,
+    /// Whether the code was parsed rather than scanned
+    pub parsed: bool,
+}
+
+/// Analyze `code` written in `language`
+pub fn analyze(code: &str, language: &ProgrammingLanguage) -> SafetyAnalysis {
+    let parsed = match language {
+        ProgrammingLanguage::Rust => syn::parse_file(code).ok().map(|file| {
+            let mut visitor = ConstructVisitor::default();
+            visitor.visit_file(&file);
+            visitor.found
+        }),
+        _ => None,
+    };
+    let is_parsed = parsed.is_some();
+    let constructs = parsed.unwrap_or_else(|| scan(code, language));
+    let score = 1.0 + constructs.iter().map(CodeConstruct::weight).sum::<f64>();
+    SafetyAnalysis {
+        score: score.clamp(0.0, 1.0),
+        constructs,
+        parsed: is_parsed,
+    }
+}
+
+#[derive(Default)]
+struct ConstructVisitor {
+    found: BTreeSet<CodeConstruct>,
+    /// Enclosing loops, innermost last: (has no condition, has an exit)
+    loops: Vec<(bool, bool)>,
+}
+
+impl ConstructVisitor {
+    fn in_loop<F: FnOnce(&mut Self)>(&mut self, unconditional: bool, body: F) {
+        self.loops.push((unconditional, false));
+        body(self);
+        if let Some((true, false)) = self.loops.pop() {
+            self.found.insert(CodeConstruct::UnboundedLoop);
+        }
+    }
+
+    /// An exit that leaves every enclosing loop
+    fn exit_all(&mut self) {
+        self.loops.iter_mut().for_each(|(_, exits)| *exits = true);
+    }
+}
+
+impl<'ast> Visit<'ast> for ConstructVisitor {
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.found.insert(CodeConstruct::Unsafe);
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    fn visit_signature(&mut self, node: &'ast syn::Signature) {
+        if node.unsafety.is_some() {
+            self.found.insert(CodeConstruct::Unsafe);
+        }
+        visit::visit_signature(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if node.unsafety.is_some() {
+            self.found.insert(CodeConstruct::Unsafe);
+        }
+        visit::visit_item_impl(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if node.method == "unwrap" || node.method == "expect" {
+            self.found.insert(CodeConstruct::Unwrap);
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Some(name) = node.path.segments.last() {
+            if ["panic", "todo", "unimplemented", "unreachable"].iter().any(|m| name.ident == m) {
+                self.found.insert(CodeConstruct::PanickingMacro);
+            }
+        }
+        visit::visit_macro(self, node);
+    }
+
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        // Single segments are locals as often as not; `fs::write` and
+        // `Command::new` are not
+        if node.segments.len() > 1 {
+            for segment in &node.segments {
+                if let Some(construct) = module_construct(&segment.ident.to_string()) {
+                    self.found.insert(construct);
+                }
+            }
+        }
+        visit::visit_path(self, node);
+    }
+
+    fn visit_use_path(&mut self, node: &'ast syn::UsePath) {
+        if let Some(construct) = module_construct(&node.ident.to_string()) {
+            self.found.insert(construct);
+        }
+        visit::visit_use_path(self, node);
+    }
+
+    fn visit_type_path(&mut self, node: &'ast syn::TypePath) {
+        match node.path.segments.last().map(|s| s.ident.to_string()).as_deref() {
+            Some("Result") => { self.found.insert(CodeConstruct::ResultType); }
+            Some("Option") => { self.found.insert(CodeConstruct::OptionType); }
+            _ => {}
+        }
+        visit::visit_type_path(self, node);
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast syn::ExprLoop) {
+        self.in_loop(true, |v| visit::visit_expr_loop(v, node));
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast syn::ExprWhile) {
+        let unconditional = matches!(
+            &*node.cond,
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Bool(b), .. }) if b.value
+        );
+        self.in_loop(unconditional, |v| visit::visit_expr_while(v, node));
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        self.in_loop(false, |v| visit::visit_expr_for_loop(v, node));
+    }
+
+    fn visit_expr_break(&mut self, node: &'ast syn::ExprBreak) {
+        if node.label.is_some() {
+            // Conservatively treat a labeled break as leaving every loop
+            self.exit_all();
+        } else if let Some((_, exits)) = self.loops.last_mut() {
+            *exits = true;
+        }
+        visit::visit_expr_break(self, node);
+    }
+
+    fn visit_expr_return(&mut self, node: &'ast syn::ExprReturn) {
+        self.exit_all();
+        visit::visit_expr_return(self, node);
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast syn::ExprTry) {
+        self.exit_all();
+        visit::visit_expr_try(self, node);
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast syn::ExprClosure) {
+        // Loops inside a closure cannot be left by anything outside it, and
+        // a `return` inside it does not leave the loops around it
+        let loops = std::mem::take(&mut self.loops);
+        visit::visit_expr_closure(self, node);
+        self.loops = loops;
+    }
+}
+
+fn module_construct(name: &str) -> Option<CodeConstruct> {
+    match name {
+        "process" | "Command" => Some(CodeConstruct::ProcessCall),
+        "fs" | "File" | "OpenOptions" => Some(CodeConstruct::FilesystemCall),
+        _ => None,
+    }
+}
+
+/// Markers for the lexical scan, matched on identifier boundaries
+fn markers(language: &ProgrammingLanguage) -> &'static [(&'static str, CodeConstruct)] {
+    match language {
+        ProgrammingLanguage::Rust => &[
+            ("unsafe", CodeConstruct::Unsafe),
+            ("unwrap()", CodeConstruct::Unwrap),
+            (".expect(", CodeConstruct::Unwrap),
+            ("panic!", CodeConstruct::PanickingMacro),
+            ("todo!", CodeConstruct::PanickingMacro),
+            ("unimplemented!", CodeConstruct::PanickingMacro),
+            ("process::", CodeConstruct::ProcessCall),
+            ("Command::new", CodeConstruct::ProcessCall),
+            ("fs::", CodeConstruct::FilesystemCall),
+            ("File::", CodeConstruct::FilesystemCall),
+            ("Result<", CodeConstruct::ResultType),
+            ("Option<", CodeConstruct::OptionType),
+        ],
+        ProgrammingLanguage::Python => &[
+            ("subprocess", CodeConstruct::ProcessCall),
+            ("os.system", CodeConstruct::ProcessCall),
+            ("os.popen", CodeConstruct::ProcessCall),
+            ("open(", CodeConstruct::FilesystemCall),
+            ("shutil", CodeConstruct::FilesystemCall),
+            ("os.remove", CodeConstruct::FilesystemCall),
+            ("while True", CodeConstruct::UnboundedLoop),
+        ],
+        ProgrammingLanguage::JavaScript => &[
+            ("child_process", CodeConstruct::ProcessCall),
+            ("eval(", CodeConstruct::ProcessCall),
+            ("fs.", CodeConstruct::FilesystemCall),
+            ("while (true)", CodeConstruct::UnboundedLoop),
+            ("for (;;)", CodeConstruct::UnboundedLoop),
+        ],
+        ProgrammingLanguage::Rhai => &[
+            ("throw", CodeConstruct::PanickingMacro),
+            ("eval(", CodeConstruct::ProcessCall),
+        ],
+    }
+}
+
+fn scan(code: &str, language: &ProgrammingLanguage) -> BTreeSet<CodeConstruct> {
+    let code = strip_comments_and_strings(code, language);
+    let mut found: BTreeSet<CodeConstruct> = markers(language).iter()
+        .filter(|(marker, _)| contains_token(&code, marker))
+        .map(|(_, construct)| *construct)
+        .collect();
+    // `loop` without any `break` anywhere can never end
+    let loops = matches!(language, ProgrammingLanguage::Rust | ProgrammingLanguage::Rhai);
+    if loops && contains_token(&code, "loop") && !contains_token(&code, "break") {
+        found.insert(CodeConstruct::UnboundedLoop);
+    }
+    found
+}
+
+/// `code` with comments removed and string literals emptied, keeping line
+/// breaks so lines still match the original
+pub(crate) fn strip_comments_and_strings(code: &str, language: &ProgrammingLanguage) -> String {
+    let hash_comments = *language == ProgrammingLanguage::Python;
+    // Rust uses `'` for lifetimes and chars, which are harmless to keep
+    let quotes: &[char] = match language {
+        ProgrammingLanguage::Rust => &['"'],
+        ProgrammingLanguage::JavaScript => &['"', '\'', '`'],
+        _ => &['"', '\''],
+    };
+
+    let mut out = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quotes.contains(&c) {
+            out.push(c);
+            while let Some(inner) = chars.next() {
+                match inner {
+                    '\\' => { chars.next(); }
+                    _ if inner == c => break,
+                    '\n' => out.push('\n'),
+                    _ => {}
+                }
+            }
+            out.push(c);
+        } else if (hash_comments && c == '#') || (!hash_comments && c == '/' && chars.peek() == Some(&'/')) {
+            for inner in chars.by_ref() {
+                if inner == '\n' {
+                    out.push('\n');
+                    break;
+                }
+            }
+        } else if !hash_comments && c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut previous = ' ';
+            for inner in chars.by_ref() {
+                if previous == '*' && inner == '/' {
+                    break;
+                }
+                if inner == '\n' {
+                    out.push('\n');
+                }
+                previous = inner;
+            }
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `marker` occurs in `code` without running into a longer identifier
+pub(crate) fn contains_token(code: &str, marker: &str) -> bool {
+    let starts_ident = marker.starts_with(is_ident_char);
+    let ends_ident = marker.ends_with(is_ident_char);
+    code.match_indices(marker).any(|(at, _)| {
+        let before = code[..at].chars().next_back();
+        let after = code[at + marker.len()..].chars().next();
+        !(starts_ident && before.is_some_and(is_ident_char))
+            && !(ends_ident && after.is_some_and(is_ident_char))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constructs(code: &str, language: ProgrammingLanguage) -> Vec<CodeConstruct> {
+        analyze(code, &language).constructs.into_iter().collect()
+    }
+
+    #[test]
+    fn test_rust_is_scored_by_construct() {
+        let commented = r#"
+// Never call unwrap() or panic! here
+fn parse(s: &str) -> Option<u32> {
+    let message = "unsafe { std::process::exit(1) }";
+    s.parse().ok()
+}
+"#;
+        let analysis = analyze(commented, &ProgrammingLanguage::Rust);
+        assert!(analysis.parsed);
+        assert_eq!(analysis.constructs.into_iter().collect::<Vec<_>>(), vec![CodeConstruct::OptionType]);
+
+        let risky = r#"
+use std::process::Command;
+fn run() {
+    let out = Command::new("ls").output().unwrap();
+    loop {
+        let _ = std::fs::read("/etc/passwd");
+    }
+}
+"#;
+        assert_eq!(constructs(risky, ProgrammingLanguage::Rust), vec![
+            CodeConstruct::ProcessCall,
+            CodeConstruct::FilesystemCall,
+            CodeConstruct::Unwrap,
+            CodeConstruct::UnboundedLoop,
+        ]);
+
+        let bounded = "fn f() -> Result<(), ()> { loop { if g() { break; } } }";
+        assert_eq!(constructs(bounded, ProgrammingLanguage::Rust), vec![CodeConstruct::ResultType]);
+    }
+
+    #[test]
+    fn test_other_languages_skip_comments_and_strings() {
+        let python = "# subprocess is not used\nprint('while True')\nwhile True:\n    pass\n";
+        assert_eq!(constructs(python, ProgrammingLanguage::Python), vec![CodeConstruct::UnboundedLoop]);
+
+        let rhai = "// loop forever\nlet x = \"throw\";\nx";
+        assert!(constructs(rhai, ProgrammingLanguage::Rhai).is_empty());
+
+        // Rust that does not parse falls back to the scan
+        let fragment = "let v = x.unwrap(); // not unsafe";
+        let analysis = analyze(fragment, &ProgrammingLanguage::Rust);
+        assert!(!analysis.parsed);
+        assert_eq!(analysis.constructs.into_iter().collect::<Vec<_>>(), vec![CodeConstruct::Unwrap]);
+    }
+}
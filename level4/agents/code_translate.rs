@@ -0,0 +1,139 @@
+// -*- coding: utf-8 -*-
+//! Template Translations
+//!
+//! Translates code rendered from a template into one of the template's
+//! translations without an LLM backend. The placeholder bindings are read
+//! back out of the code, ignoring layout, and the translation is rendered
+//! with them. Code that is not a rendering of any template is left
+//! untranslated.
+
+use crate::level4::agents::generate_code::{placeholder_markers, CodeTemplate, ProgrammingLanguage};
+use std::collections::HashMap;
+
+/// `code` in `to`, if it was rendered from one of `templates` in `from` and
+/// that template also has code in `to`
+///
+/// When several templates could have rendered `code`, the one with the most
+/// literal text wins, so a template that is mostly placeholders does not
+/// claim code a more specific one produced.
+pub fn translate<'a>(
+    templates: impl IntoIterator<Item = &'a CodeTemplate>,
+    code: &str,
+    from: &ProgrammingLanguage,
+    to: &ProgrammingLanguage,
+) -> Option<String> {
+    if from == to {
+        return None;
+    }
+    templates.into_iter()
+        .filter(|template| template.code_in(to).is_some())
+        .filter_map(|template| {
+            let source = template.code_in(from)?;
+            let bindings = bindings_in(source, code)?;
+            Some((literal_len(source), template.render_in(to, &bindings).ok()?))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, translated)| translated)
+}
+
+/// Length of `template_code` outside its markers, ignoring layout
+fn literal_len(template_code: &str) -> usize {
+    let template = normalize(template_code);
+    let markers: usize = placeholder_markers(&template).iter().map(|(span, _)| span.len()).sum();
+    template.len() - markers
+}
+
+/// Placeholder values that render `template_code` as `code`, up to layout
+///
+/// Values are matched shortest first and come back with their whitespace
+/// collapsed, as everything else is compared that way.
+fn bindings_in(template_code: &str, code: &str) -> Option<HashMap<String, String>> {
+    let template = normalize(template_code);
+    let code = normalize(code);
+    let markers = placeholder_markers(&template);
+
+    let first = markers.first().map_or(template.len(), |(span, _)| span.start);
+    let mut rest = code.strip_prefix(&template[..first])?;
+    let mut bindings = HashMap::new();
+    for (i, (span, name)) in markers.iter().enumerate() {
+        let value = match markers.get(i + 1) {
+            Some((next, _)) => {
+                let literal = &template[span.end..next.start];
+                let end = rest.find(literal)?;
+                let value = &rest[..end];
+                rest = &rest[end + literal.len()..];
+                value
+            }
+            None => {
+                let value = rest.strip_suffix(&template[span.end..])?;
+                rest = "";
+                value
+            }
+        };
+        // A placeholder used twice has to have the same value both times
+        match bindings.get(*name) {
+            Some(bound) if bound != value => return None,
+            Some(_) => {}
+            None => {
+                bindings.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    rest.is_empty().then_some(bindings)
+}
+
+/// `code` with every run of whitespace collapsed to one space
+fn normalize(code: &str) -> String {
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::generate_code::TemplateTranslation;
+
+    #[test]
+    fn test_bindings_read_back_from_code() {
+        let template = "fn main() {\n    println!(\"{:?}\", calculate(\"{{operation}}\", {{a}}, {{b}}));\n}\n";
+        let code = "fn main() { println!(\"{:?}\", calculate(\"divide\", 9,   3)); }";
+        let bindings = bindings_in(template, code).unwrap();
+        assert_eq!(bindings["operation"], "divide");
+        assert_eq!(bindings["a"], "9");
+        assert_eq!(bindings["b"], "3");
+
+        assert!(bindings_in(template, "fn main() {}").is_none());
+        assert!(bindings_in("{{x}} + {{x}}", "1 + 2").is_none());
+        assert_eq!(bindings_in("{{x}} + {{x}}", "1 + 1").unwrap()["x"], "1");
+        // Code beyond the template is not part of a rendering
+        assert!(bindings_in("fn f() {}", "fn f() {} fn g() {}").is_none());
+    }
+
+    #[test]
+    fn test_most_specific_template_wins() {
+        let template = |template_id: &str, rhai: &str, rust: &str| CodeTemplate {
+            template_id: template_id.to_string(),
+            name: template_id.to_string(),
+            language: ProgrammingLanguage::Rhai,
+            template_code: rhai.to_string(),
+            placeholders: vec!["a".to_string(), "b".to_string()],
+            defaults: HashMap::new(),
+            description: String::new(),
+            tags: vec![],
+            dependencies: vec![],
+            version: Default::default(),
+            translations: vec![TemplateTranslation {
+                language: ProgrammingLanguage::Rust,
+                template_code: rust.to_string(),
+            }],
+        };
+        let loose = template("loose", "{{a}} + {{b}}", "{{a}} + {{b}}");
+        let sum = template("sum", "sum({{a}}, {{b}}) + 1", "sum({{a}}, {{b}}) + 1i64");
+
+        let code = "sum(2, 3) + 1";
+        let (rhai, rust) = (ProgrammingLanguage::Rhai, ProgrammingLanguage::Rust);
+        assert_eq!(translate([&loose, &sum], code, &rhai, &rust).unwrap(), "sum(2, 3) + 1i64");
+        assert_eq!(translate([&sum, &loose], code, &rhai, &rust).unwrap(), "sum(2, 3) + 1i64");
+        assert_eq!(translate([&loose], code, &rhai, &rust).unwrap(), "sum(2, 3) + 1");
+        assert!(translate([&sum], "2 + 3", &rhai, &rust).is_none());
+    }
+}
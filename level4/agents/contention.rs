@@ -0,0 +1,102 @@
+// -*- coding: utf-8 -*-
+//! Lock Contention Profiling
+//!
+//! Time spent waiting on shared locks and channels, attributed to the request
+//! being served.
+//!
+//! Attribution follows the task rather than the thread: waits recorded by
+//! anything awaited inside `profiled` land in that request's profile, however
+//! the runtime schedules it.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static PROFILE: RefCell<ContentionProfile>;
+}
+
+/// Waits at one site, e.g. the cache admission lock
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WaitStats {
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+/// Waits recorded while serving one request, keyed by site
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContentionProfile {
+    pub sites: BTreeMap<String, WaitStats>,
+}
+
+impl ContentionProfile {
+    pub fn total_wait(&self) -> Duration {
+        Duration::from_micros(self.sites.values().map(|s| s.total_us).sum())
+    }
+
+    fn record(&mut self, site: &str, waited: Duration) {
+        let micros = waited.as_micros() as u64;
+        let stats = self.sites.entry(site.to_string()).or_default();
+        stats.count += 1;
+        stats.total_us += micros;
+        stats.max_us = stats.max_us.max(micros);
+    }
+}
+
+/// Run `future` with a fresh profile, returning its output and the waits it recorded
+pub async fn profiled<F: Future>(future: F) -> (F::Output, ContentionProfile) {
+    PROFILE.scope(RefCell::new(ContentionProfile::default()), async {
+        let output = future.await;
+        (output, current().unwrap_or_default())
+    }).await
+}
+
+/// Waits recorded so far by the enclosing `profiled` call, if any
+pub fn current() -> Option<ContentionProfile> {
+    PROFILE.try_with(|profile| profile.borrow().clone()).ok()
+}
+
+/// Add a wait to the enclosing profile; a no-op outside `profiled`
+pub fn record_wait(site: &str, waited: Duration) {
+    let _ = PROFILE.try_with(|profile| profile.borrow_mut().record(site, waited));
+}
+
+/// Await `future`, recording how long it took as a wait at `site`
+pub async fn timed<F: Future>(site: &str, future: F) -> F::Output {
+    let start = Instant::now();
+    let output = future.await;
+    record_wait(site, start.elapsed());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_waits_are_attributed_to_the_waiting_task() {
+        let lock = Arc::new(Mutex::new(()));
+        let guard = lock.clone().lock_owned().await;
+
+        let waiter = tokio::spawn(profiled({
+            let lock = lock.clone();
+            async move {
+                drop(timed("lock", lock.lock()).await);
+            }
+        }));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(guard);
+
+        let ((), profile) = waiter.await.unwrap();
+        assert_eq!(profile.sites["lock"].count, 1);
+        assert!(profile.total_wait() >= Duration::from_millis(10));
+        // Outside any profile, recording is a no-op
+        record_wait("lock", Duration::from_secs(1));
+        assert!(current().is_none());
+    }
+}
@@ -0,0 +1,147 @@
+// -*- coding: utf-8 -*-
+//! Answer Freshness
+//!
+//! A graph version counter with the version at which each vertex last
+//! changed, so an answer can tell whether the vertices it was built from
+//! have changed since.
+
+use crate::level4::agents::cache_manager::{CacheEvent, VertexCentricCache};
+use crate::level4::agents::cache_invalidation::InvalidationTarget;
+use crate::level4::agents::reasoning::ReasoningChain;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Attached to answers whose vertices changed after they were reasoned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StalenessWarning {
+    /// Graph version the answer was reasoned against
+    pub answer_version: u64,
+    pub current_version: u64,
+    /// Vertices the answer used that have changed since, sorted
+    pub changed_vertices: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct VersionState {
+    version: u64,
+    changed_at: HashMap<String, u64>,
+    /// Version at which every vertex is assumed to have changed
+    floor: u64,
+}
+
+/// Graph version, bumped once per batch of vertex changes
+#[derive(Debug, Default)]
+pub struct VertexVersions {
+    state: RwLock<VersionState>,
+}
+
+impl VertexVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).version
+    }
+
+    /// Record that `vertices` changed, returning the new graph version
+    pub fn record_change<I, S>(&self, vertices: I) -> u64
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.version += 1;
+        let version = state.version;
+        for vertex_id in vertices {
+            state.changed_at.insert(vertex_id.into(), version);
+        }
+        version
+    }
+
+    /// Record that some unknown set of vertices changed, so every answer
+    /// reasoned before now is stale
+    pub fn record_unknown_change(&self) -> u64 {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.version += 1;
+        state.floor = state.version;
+        state.version
+    }
+
+    /// Which of `vertices` changed after graph version `since`, sorted
+    pub fn changed_since<'a>(&self, vertices: impl IntoIterator<Item = &'a str>, since: u64) -> Vec<String> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let changed: BTreeSet<&str> = vertices.into_iter()
+            .filter(|vertex_id| {
+                let changed_at = state.changed_at.get(*vertex_id).copied().unwrap_or(0);
+                changed_at.max(state.floor) > since
+            })
+            .collect();
+        changed.into_iter().map(str::to_string).collect()
+    }
+
+    /// Warning for `chain` if a vertex it accessed changed after it was
+    /// reasoned; `None` for fresh chains and chains without a graph version
+    pub fn check(&self, chain: &ReasoningChain) -> Option<StalenessWarning> {
+        let answer_version = chain.graph_version?;
+        let vertices = chain.steps.iter()
+            .flat_map(|s| &s.graph_nodes_accessed)
+            .map(String::as_str);
+        let changed_vertices = self.changed_since(vertices, answer_version);
+        (!changed_vertices.is_empty()).then(|| StalenessWarning {
+            answer_version,
+            current_version: self.version(),
+            changed_vertices,
+        })
+    }
+
+    /// Record a change for every vertex `cache` invalidates, until the cache
+    /// is dropped
+    ///
+    /// Tag invalidations name no vertices and are not tracked. If the
+    /// listener falls behind, every vertex is marked changed, since the
+    /// missed events cannot be recovered.
+    pub fn follow(self: &Arc<Self>, cache: &VertexCentricCache) -> tokio::task::JoinHandle<()> {
+        let versions = Arc::clone(self);
+        let mut events = cache.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(CacheEvent::Invalidated { target: InvalidationTarget::Vertex(vertex_id), .. }) => {
+                        versions.record_change([vertex_id]);
+                    }
+                    Ok(CacheEvent::Invalidated { target: InvalidationTarget::Vertices(vertex_ids), .. }) => {
+                        versions.record_change(vertex_ids);
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Freshness tracking missed {} cache events", missed);
+                        versions.record_unknown_change();
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_since() {
+        let versions = VertexVersions::new();
+        let before = versions.version();
+        assert_eq!(versions.record_change(["b", "a"]), before + 1);
+
+        assert_eq!(versions.changed_since(["a", "b", "c"], before), vec!["a", "b"]);
+        assert!(versions.changed_since(["a", "b"], versions.version()).is_empty());
+
+        let after = versions.version();
+        versions.record_unknown_change();
+        assert_eq!(versions.changed_since(["c"], after), vec!["c"]);
+    }
+}
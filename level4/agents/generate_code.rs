@@ -3,9 +3,29 @@
 //! 
 //! Generates executable code based on natural language descriptions.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::level4::agents::clock::{self, Clock, IdGenerator};
+use crate::level4::agents::code_constraints::GenerationConstraints;
+use crate::level4::agents::code_docs::{self, CodeDocs};
+use crate::level4::agents::code_format::CodeFormatter;
+use crate::level4::agents::code_patch::CodePatch;
+use crate::level4::agents::code_safety;
+use crate::level4::agents::code_translate;
+use crate::level4::agents::prompt_lint;
+use crate::level4::agents::reasoning::{DesiredFormat, InformationRequest};
+use crate::level4::agents::template_match::{self, TemplateMatch, DEFAULT_MATCH_THRESHOLD};
+use crate::level4::agents::template_pack::{PackFormat, TemplatePack};
+use crate::level4::agents::test_synthesis;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
 
 /// Generated code with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +37,12 @@ pub struct GeneratedCode {
     pub dependencies: Vec<String>,
     pub test_cases: Vec<TestCase>,
     pub safety_score: f64,
+    /// Whether the code went through the generator's formatter
+    #[serde(default)]
+    pub formatted: bool,
+    /// Doc blocks and usage example, empty when documentation is off
+    #[serde(default)]
+    pub docs: CodeDocs,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -27,7 +53,30 @@ pub enum ProgrammingLanguage {
     Rhai, // Embedded scripting
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ProgrammingLanguage {
+    /// Info string used to tag markdown code fences
+    pub fn fence_tag(&self) -> &'static str {
+        match self {
+            ProgrammingLanguage::Rust => "rust",
+            ProgrammingLanguage::Python => "python",
+            ProgrammingLanguage::JavaScript => "javascript",
+            ProgrammingLanguage::Rhai => "rhai",
+        }
+    }
+
+    /// Inverse of `fence_tag`, also accepting `js`; case-insensitive
+    pub fn from_fence_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_lowercase().as_str() {
+            "rust" => Some(ProgrammingLanguage::Rust),
+            "python" => Some(ProgrammingLanguage::Python),
+            "javascript" | "js" => Some(ProgrammingLanguage::JavaScript),
+            "rhai" => Some(ProgrammingLanguage::Rhai),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TestCase {
     pub input: String,
     pub expected_output: String,
@@ -35,32 +84,733 @@ pub struct TestCase {
 }
 
 /// Code template for common patterns
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Placeholders appear in `template_code` as `{{name}}` markers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CodeTemplate {
     pub template_id: String,
     pub name: String,
     pub language: ProgrammingLanguage,
     pub template_code: String,
     pub placeholders: Vec<String>,
+    /// Values used for placeholders the caller leaves unbound
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+    /// What the template does, used when matching descriptions
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Crates or modules the generated code needs
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub version: TemplateVersion,
+    /// The same code in other languages, which `CodeGenerator::translate`
+    /// converts renderings to
+    #[serde(default)]
+    pub translations: Vec<TemplateTranslation>,
+}
+
+/// `CodeTemplate` code in another language, with the same placeholders
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateTranslation {
+    pub language: ProgrammingLanguage,
+    pub template_code: String,
+}
+
+/// Semantic version of a template, written `MAJOR.MINOR.PATCH`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TemplateVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl TemplateVersion {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split('.').map(|part| part.parse::<u64>().ok());
+        let version = Self::new(parts.next()??, parts.next()??, parts.next()??);
+        parts.next().is_none().then_some(version)
+    }
+}
+
+/// Templates without a version are `1.0.0`
+impl Default for TemplateVersion {
+    fn default() -> Self {
+        Self::new(1, 0, 0)
+    }
+}
+
+impl fmt::Display for TemplateVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for TemplateVersion {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text).ok_or_else(|| Error::CodeGeneration(format!("invalid template version '{}'", text)))
+    }
+}
+
+impl TryFrom<String> for TemplateVersion {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, String> {
+        Self::parse(&text).ok_or_else(|| format!("invalid template version '{}'", text))
+    }
+}
+
+impl From<TemplateVersion> for String {
+    fn from(version: TemplateVersion) -> Self {
+        version.to_string()
+    }
+}
+
+impl CodeTemplate {
+    /// Check that the id is set and that markers, placeholders and defaults agree
+    pub fn validate(&self) -> Result<()> {
+        let template_error = |problem: String| Error::CodeGeneration(format!("template '{}': {}", self.template_id, problem));
+        if self.template_id.trim().is_empty() {
+            return Err(Error::CodeGeneration("template id is empty".to_string()));
+        }
+        
+        let markers: Vec<&str> = placeholder_markers(&self.template_code).into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        if let Some(marker) = markers.iter().find(|m| !self.placeholders.iter().any(|p| p == *m)) {
+            return Err(template_error(format!("marker '{}' is not a declared placeholder", marker)));
+        }
+        if let Some(unused) = self.placeholders.iter().find(|p| !markers.contains(&p.as_str())) {
+            return Err(template_error(format!("placeholder '{}' never appears in the code", unused)));
+        }
+        if let Some(stray) = self.defaults.keys().find(|d| !self.placeholders.contains(d)) {
+            return Err(template_error(format!("default for undeclared placeholder '{}'", stray)));
+        }
+        let includes = self.includes();
+        if includes.iter().any(|(template_id, _)| *template_id == self.template_id) {
+            return Err(template_error("includes itself".to_string()));
+        }
+        for (i, (_, scope)) in includes.iter().enumerate() {
+            if includes[..i].iter().any(|(_, earlier)| earlier == scope) {
+                return Err(template_error(format!("include scope '{}' is used twice", scope)));
+            }
+        }
+        
+        let mut declared: Vec<&str> = self.placeholders.iter().map(String::as_str).collect();
+        declared.sort_unstable();
+        declared.dedup();
+        for (i, translation) in self.translations.iter().enumerate() {
+            let language = &translation.language;
+            if *language == self.language || self.translations[..i].iter().any(|t| t.language == *language) {
+                return Err(template_error(format!("more than one {:?} version", language)));
+            }
+            let translated = template_markers(&translation.template_code);
+            if translated.iter().any(|(_, marker)| matches!(marker, Marker::Include { .. })) {
+                return Err(template_error(format!("{:?} translation has an include", language)));
+            }
+            let mut used: Vec<&str> = placeholder_markers(&translation.template_code).into_iter()
+                .map(|(_, name)| name)
+                .collect();
+            used.sort_unstable();
+            used.dedup();
+            if used != declared {
+                return Err(template_error(format!(
+                    "{:?} translation uses placeholders [{}], not [{}]",
+                    language, used.join(", "), declared.join(", "),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Template id and binding scope of each `{{> template_id}}` or
+    /// `{{> template_id as scope}}` include, in order of appearance
+    pub fn includes(&self) -> Vec<(&str, &str)> {
+        template_markers(&self.template_code).into_iter()
+            .filter_map(|(_, marker)| match marker {
+                Marker::Include { template_id, scope } => Some((template_id, scope)),
+                Marker::Placeholder(_) => None,
+            })
+            .collect()
+    }
+
+    /// Whether every placeholder is bound by `bindings` or has a default
+    fn is_satisfied_by(&self, bindings: &HashMap<String, String>) -> bool {
+        self.placeholders.iter().all(|name| bindings.contains_key(name) || self.defaults.contains_key(name))
+    }
+
+    /// Substitute every `{{name}}` marker, preferring `bindings` over `defaults`
+    ///
+    /// Fails if a declared placeholder is left unbound, a binding names no
+    /// declared placeholder, or the code uses a marker that is not declared.
+    /// Templates with includes are rendered through
+    /// [`CodeGenerator::render_template`], which can resolve them.
+    pub fn render(&self, bindings: &HashMap<String, String>) -> Result<String> {
+        self.render_with(bindings, &mut |template_id, _| {
+            Err(Error::CodeGeneration(format!(
+                "template '{}': include of '{}' needs a CodeGenerator to resolve it",
+                self.template_id, template_id,
+            )))
+        })
+    }
+
+    /// Code of this template in `language`: `template_code` or a translation
+    pub fn code_in(&self, language: &ProgrammingLanguage) -> Option<&str> {
+        if *language == self.language {
+            return Some(&self.template_code);
+        }
+        self.translations.iter()
+            .find(|t| t.language == *language)
+            .map(|t| t.template_code.as_str())
+    }
+
+    /// `render` of this template's code in `language` (see `code_in`)
+    pub fn render_in(&self, language: &ProgrammingLanguage, bindings: &HashMap<String, String>) -> Result<String> {
+        let template_code = self.code_in(language).ok_or_else(|| {
+            Error::CodeGeneration(format!("template '{}' has no {:?} version", self.template_id, language))
+        })?;
+        CodeTemplate {
+            language: language.clone(),
+            template_code: template_code.to_string(),
+            translations: vec![],
+            ..self.clone()
+        }.render(bindings)
+    }
+
+    /// `render`, with each include replaced by `expand(template_id, bindings)`
+    ///
+    /// A binding `scope.name` is passed to the include with that scope as
+    /// `name`; unscoped bindings stay with this template.
+    fn render_with(
+        &self,
+        bindings: &HashMap<String, String>,
+        expand: &mut dyn FnMut(&str, &HashMap<String, String>) -> Result<String>,
+    ) -> Result<String> {
+        let template_error = |problem: String| Error::CodeGeneration(format!("template '{}': {}", self.template_id, problem));
+        let declared = |name: &str| self.placeholders.iter().any(|p| p == name);
+        let markers = template_markers(&self.template_code);
+        let scopes: Vec<&str> = self.includes().into_iter().map(|(_, scope)| scope).collect();
+        
+        let mut scoped: HashMap<&str, HashMap<String, String>> = HashMap::new();
+        let mut unknown: Vec<&str> = Vec::new();
+        for (name, value) in bindings {
+            match name.split_once('.') {
+                Some((scope, inner)) if scopes.contains(&scope) => {
+                    scoped.entry(scope).or_default().insert(inner.to_string(), value.clone());
+                }
+                None if declared(name) => {}
+                _ => unknown.push(name),
+            }
+        }
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(template_error(format!("unknown placeholders: {}", unknown.join(", "))));
+        }
+        let undeclared: Vec<&str> = markers.iter()
+            .filter_map(|(_, marker)| match marker {
+                Marker::Placeholder(name) => Some(*name),
+                Marker::Include { .. } => None,
+            })
+            .filter(|name| !declared(*name))
+            .collect();
+        if !undeclared.is_empty() {
+            return Err(template_error(format!("undeclared markers: {}", undeclared.join(", "))));
+        }
+        let unbound: Vec<&str> = self.placeholders.iter()
+            .map(String::as_str)
+            .filter(|name| !bindings.contains_key(*name) && !self.defaults.contains_key(*name))
+            .collect();
+        if !unbound.is_empty() {
+            return Err(template_error(format!("unbound placeholders: {}", unbound.join(", "))));
+        }
+        
+        // One pass, so bound values are never scanned for markers themselves
+        let no_bindings = HashMap::new();
+        let mut rendered = String::with_capacity(self.template_code.len());
+        let mut copied = 0;
+        for (span, marker) in markers {
+            rendered.push_str(&self.template_code[copied..span.start]);
+            match marker {
+                Marker::Placeholder(name) => {
+                    rendered.push_str(bindings.get(name).or_else(|| self.defaults.get(name)).map_or("", String::as_str));
+                }
+                Marker::Include { template_id, scope } => {
+                    rendered.push_str(&expand(template_id, scoped.get(scope).unwrap_or(&no_bindings))?);
+                }
+            }
+            copied = span.end;
+        }
+        rendered.push_str(&self.template_code[copied..]);
+        Ok(rendered)
+    }
+}
+
+/// Template as written in a `.toml` or `.yaml` definition file or a
+/// template pack
+///
+/// `defaults` and `translations` come last so TOML can write them as tables
+/// after the values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TemplateDefinition {
+    pub(crate) id: String,
+    /// Defaults to `id`
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    /// Fence tag, e.g. `rust` or `rhai`
+    pub(crate) language: String,
+    pub(crate) code: String,
+    #[serde(default)]
+    pub(crate) placeholders: Vec<String>,
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) dependencies: Vec<String>,
+    #[serde(default)]
+    pub(crate) version: TemplateVersion,
+    #[serde(default)]
+    pub(crate) defaults: BTreeMap<String, String>,
+    /// Code in other languages, by fence tag
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) translations: BTreeMap<String, String>,
+}
+
+impl TemplateDefinition {
+    pub(crate) fn from_template(template: &CodeTemplate) -> Self {
+        Self {
+            id: template.template_id.clone(),
+            name: Some(template.name.clone()),
+            language: template.language.fence_tag().to_string(),
+            code: template.template_code.clone(),
+            placeholders: template.placeholders.clone(),
+            description: template.description.clone(),
+            tags: template.tags.clone(),
+            dependencies: template.dependencies.clone(),
+            version: template.version,
+            defaults: template.defaults.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            translations: template.translations.iter()
+                .map(|t| (t.language.fence_tag().to_string(), t.template_code.clone()))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn into_template(self) -> Result<CodeTemplate> {
+        let language = ProgrammingLanguage::from_fence_tag(&self.language)
+            .ok_or_else(|| Error::CodeGeneration(format!("template '{}': unknown language '{}'", self.id, self.language)))?;
+        let translations = self.translations.into_iter()
+            .map(|(tag, template_code)| {
+                let language = ProgrammingLanguage::from_fence_tag(&tag)
+                    .ok_or_else(|| Error::CodeGeneration(format!("template '{}': unknown translation language '{}'", self.id, tag)))?;
+                Ok(TemplateTranslation { language, template_code })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let template = CodeTemplate {
+            name: self.name.unwrap_or_else(|| self.id.clone()),
+            template_id: self.id,
+            language,
+            template_code: self.code,
+            placeholders: self.placeholders,
+            defaults: self.defaults.into_iter().collect(),
+            description: self.description,
+            tags: self.tags,
+            dependencies: self.dependencies,
+            version: self.version,
+            translations,
+        };
+        template.validate()?;
+        Ok(template)
+    }
+}
+
+/// Templates loaded from a directory, remembered so it can be reloaded
+#[derive(Debug)]
+struct TemplateDir {
+    path: PathBuf,
+    fingerprint: Vec<(PathBuf, u64, Option<SystemTime>)>,
+    /// Templates as the directory defined them, by id
+    loaded: HashMap<String, CodeTemplate>,
+    /// Templates the directory's versions replaced, restored if they disappear
+    shadowed: HashMap<String, CodeTemplate>,
+}
+
+/// Template definition files in `dir` with their size and modification time, by path
+fn template_files(dir: &Path) -> Result<Vec<(PathBuf, u64, Option<SystemTime>)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_template = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("toml") | Some("yaml") | Some("yml")
+        );
+        if is_template && path.is_file() {
+            let metadata = std::fs::metadata(&path)?;
+            files.push((path, metadata.len(), metadata.modified().ok()));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+fn read_template_file(path: &Path) -> Result<CodeTemplate> {
+    let file_error = |problem: String| Error::CodeGeneration(format!("{}: {}", path.display(), problem));
+    let text = std::fs::read_to_string(path)?;
+    let definition: TemplateDefinition = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(|e| file_error(e.to_string()))?,
+        _ => serde_yaml::from_str(&text).map_err(|e| file_error(e.to_string()))?,
+    };
+    definition.into_template()
+}
+
+/// Rhai array literal of string `items`
+fn rhai_array(items: &[&str]) -> String {
+    let quoted: Vec<String> = items.iter()
+        .map(|item| format!("\"{}\"", item.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Marker<'a> {
+    /// `{{name}}`
+    Placeholder(&'a str),
+    /// `{{> template_id}}`, or `{{> template_id as scope}}`; the scope
+    /// defaults to the template id
+    Include { template_id: &'a str, scope: &'a str },
+}
+
+/// Byte span of each marker in `code`, in order of appearance
+fn template_markers(code: &str) -> Vec<(std::ops::Range<usize>, Marker<'_>)> {
+    let mut markers = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = code[offset..].find("{{") {
+        let start = offset + found;
+        let name_start = start + 2;
+        let marker = code[name_start..].find("}}").and_then(|len| {
+            let inner = &code[name_start..name_start + len];
+            let marker = match inner.strip_prefix('>') {
+                Some(include) => parse_include(include)?,
+                None if is_marker_name(inner) => Marker::Placeholder(inner),
+                None => return None,
+            };
+            Some((name_start + len + 2, marker))
+        });
+        match marker {
+            Some((end, marker)) => {
+                markers.push((start..end, marker));
+                offset = end;
+            }
+            None => offset = name_start,
+        }
+    }
+    markers
+}
+
+/// Byte span and name of each `{{name}}` marker in `code`, in order of appearance
+pub(crate) fn placeholder_markers(code: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    template_markers(code).into_iter()
+        .filter_map(|(span, marker)| match marker {
+            Marker::Placeholder(name) => Some((span, name)),
+            Marker::Include { .. } => None,
+        })
+        .collect()
+}
+
+/// `template_id` or `template_id as scope`
+fn parse_include(text: &str) -> Option<Marker<'_>> {
+    let mut words = text.split_whitespace();
+    let template_id = words.next().filter(|id| is_marker_name(id))?;
+    let scope = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => template_id,
+        (Some("as"), Some(scope), None) if is_marker_name(scope) => scope,
+        _ => return None,
+    };
+    Some(Marker::Include { template_id, scope })
+}
+
+fn is_marker_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Language model used for descriptions no template matches
+#[async_trait]
+pub trait CodeLlmBackend: Send + Sync + std::fmt::Debug {
+    /// Complete `prompt`; the reply should hold one fenced code block
+    async fn generate(&self, prompt: &str) -> Result<String>;
+
+    /// Complete `prompt`, sending pieces of the reply to `tokens` as they
+    /// arrive, and return the whole reply
+    ///
+    /// The default sends the reply from `generate` as a single piece.
+    async fn generate_stream(&self, prompt: &str, tokens: mpsc::Sender<String>) -> Result<String> {
+        let reply = self.generate(prompt).await?;
+        // Nobody listening is no reason to fail the generation
+        let _ = tokens.send(reply.clone()).await;
+        Ok(reply)
+    }
+}
+
+/// Piece of a streamed generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChunk {
+    pub chunk_id: usize,
+    /// Code following the previous chunk's; empty on the final chunk
+    pub content: String,
+    pub is_final: bool,
+    /// The finished code, on the final chunk of a successful generation;
+    /// differs from the streamed content when a formatter rewrote it
+    pub result: Option<GeneratedCode>,
+    /// Set on the final chunk when generation failed
+    pub error: Option<String>,
+}
+
+/// Numbers and sends the chunks of one streamed generation
+struct CodeChunkSender {
+    tx: mpsc::Sender<CodeChunk>,
+    sent: AtomicUsize,
+}
+
+impl CodeChunkSender {
+    async fn code(&self, content: &str) {
+        if !content.is_empty() {
+            self.send(content.to_string(), false, None, None).await;
+        }
+    }
+
+    async fn finish(&self, result: Result<GeneratedCode>) {
+        match result {
+            Ok(generated) => self.send(String::new(), true, Some(generated), None).await,
+            Err(e) => self.send(String::new(), true, None, Some(e.to_string())).await,
+        }
+    }
+
+    async fn send(&self, content: String, is_final: bool, result: Option<GeneratedCode>, error: Option<String>) {
+        let chunk = CodeChunk {
+            chunk_id: self.sent.fetch_add(1, Ordering::Relaxed),
+            content,
+            is_final,
+            result,
+            error,
+        };
+        // A dropped receiver only means nobody is watching any more
+        let _ = self.tx.send(chunk).await;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum FenceState {
+    #[default]
+    Opening,
+    Body,
+    Closed,
+}
+
+/// `extract_code_block` over a reply that arrives in pieces
+#[derive(Debug, Default)]
+struct FenceFilter {
+    pending: String,
+    state: FenceState,
+}
+
+impl FenceFilter {
+    /// Code from `piece` and earlier pieces that is known to be in the block
+    fn push(&mut self, piece: &str) -> String {
+        self.pending.push_str(piece);
+        if self.state == FenceState::Opening {
+            let Some(start) = self.pending.find("```") else {
+                return String::new();
+            };
+            let Some(newline) = self.pending[start..].find('\n') else {
+                return String::new();
+            };
+            self.pending.drain(..start + newline + 1);
+            self.state = FenceState::Body;
+        }
+        match self.state {
+            FenceState::Body => {
+                if let Some(end) = self.pending.find("```") {
+                    self.pending.truncate(end);
+                    self.state = FenceState::Closed;
+                    return std::mem::take(&mut self.pending);
+                }
+                // Trailing backticks may be the start of the closing fence
+                let ready = self.pending.trim_end_matches('`').len();
+                self.pending.drain(..ready).collect()
+            }
+            _ => {
+                self.pending.clear();
+                String::new()
+            }
+        }
+    }
+
+    /// Code still held back once the whole reply has arrived
+    fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        match self.state {
+            // A reply without a fence is code as a whole
+            FenceState::Opening if !rest.contains("```") => rest.trim().to_string(),
+            FenceState::Body => rest,
+            _ => String::new(),
+        }
+    }
+}
+
+/// Code and language from the first fenced block in `reply`
+///
+/// An untagged or unknown fence falls back to `language`; a reply without a
+/// fence is taken as code as a whole.
+fn extract_code_block(reply: &str, language: &ProgrammingLanguage) -> (String, ProgrammingLanguage) {
+    let Some(start) = reply.find("```") else {
+        return (reply.trim().to_string(), language.clone());
+    };
+    let after = &reply[start + 3..];
+    let (tag, body) = after.split_once('\n').unwrap_or(("", after));
+    let body = body.find("```").map_or(body, |end| &body[..end]);
+    let language = ProgrammingLanguage::from_fence_tag(tag.trim()).unwrap_or_else(|| language.clone());
+    (body.trim_end().to_string(), language)
+}
+
+/// `test_cases` followed by the synthesized cases for inputs they do not cover
+fn with_synthesized(mut test_cases: Vec<TestCase>, code: &str, language: &ProgrammingLanguage) -> Vec<TestCase> {
+    for case in test_synthesis::synthesize(code, language) {
+        if !test_cases.iter().any(|known| known.input == case.input) {
+            test_cases.push(case);
+        }
+    }
+    test_cases
 }
 
+/// The formatter's output and `true`, or `code` as generated and `false` if
+/// formatting failed; failures are logged
+fn formatted_or(code: String, language: &ProgrammingLanguage, result: Result<String>) -> (String, bool) {
+    match result {
+        Ok(formatted) => (formatted, true),
+        Err(e) => {
+            tracing::warn!("Formatting generated {:?} code failed: {:?}", language, e);
+            (code, false)
+        }
+    }
+}
+
+/// Prompt asking the LLM backend for `language` code doing `description`
+fn code_prompt(description: &str, language: &ProgrammingLanguage) -> String {
+    format!(
+        "Write {} code for the task described below. Reply with a single fenced code block.\n{}",
+        language.fence_tag(),
+        prompt_lint::delimit(description, "description"),
+    )
+}
+
+/// Template `generate_for_request` renders
+pub const RETRIEVAL_TEMPLATE_ID: &str = "graph_retrieval";
+
 /// Code generator with template-based and LLM-based generation
 pub struct CodeGenerator {
+    /// Active version of each template
     templates: HashMap<String, CodeTemplate>,
+    /// Every version passed to `add_template`, by id
+    history: HashMap<String, BTreeMap<TemplateVersion, CodeTemplate>>,
     safety_checks_enabled: bool,
+    template_dir: Option<TemplateDir>,
+    llm: Option<Arc<dyn CodeLlmBackend>>,
+    formatter: Option<Arc<dyn CodeFormatter>>,
+    format_enabled: bool,
+    docs_enabled: bool,
+    match_threshold: f64,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
 }
 
 impl CodeGenerator {
     pub fn new() -> Self {
         let mut generator = Self {
             templates: HashMap::new(),
+            history: HashMap::new(),
             safety_checks_enabled: true,
+            template_dir: None,
+            llm: None,
+            formatter: None,
+            format_enabled: false,
+            docs_enabled: true,
+            match_threshold: DEFAULT_MATCH_THRESHOLD,
+            clock: clock::system_clock(),
+            ids: clock::uuid_ids(),
         };
         
         generator.load_default_templates();
         generator
     }
 
+    /// Fall back to `backend` for descriptions no template matches (see
+    /// `generate_with_llm`)
+    pub fn with_llm_backend(mut self, backend: Arc<dyn CodeLlmBackend>) -> Self {
+        self.llm = Some(backend);
+        self
+    }
+
+    /// Stamp exported template packs with `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Draw code ids from `ids` instead of random UUIDs
+    pub fn with_id_generator(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Run generated code through `formatter`, and enable formatting
+    pub fn with_formatter(mut self, formatter: Arc<dyn CodeFormatter>) -> Self {
+        self.formatter = Some(formatter);
+        self.format_enabled = true;
+        self
+    }
+
+    /// Turn the formatter stage on or off without removing the formatter
+    pub fn with_formatting(mut self, enabled: bool) -> Self {
+        self.format_enabled = enabled;
+        self
+    }
+
+    /// Turn the documentation stage, which fills `GeneratedCode::docs`, on
+    /// or off; on by default
+    pub fn with_documentation(mut self, enabled: bool) -> Self {
+        self.docs_enabled = enabled;
+        self
+    }
+
+    /// Minimum `rank_templates` score for a template to be used; below it
+    /// generation falls back to the LLM backend or a stub
+    pub fn with_match_threshold(mut self, threshold: f64) -> Self {
+        self.match_threshold = threshold;
+        self
+    }
+
+    /// Active templates scored against `description`, best first; templates
+    /// scoring zero are left out
+    pub fn rank_templates(&self, description: &str) -> Vec<TemplateMatch> {
+        let mut matches: Vec<TemplateMatch> = self.templates.values()
+            .map(|template| TemplateMatch {
+                template_id: template.template_id.clone(),
+                score: template_match::score(template, description),
+            })
+            .filter(|m| m.score > 0.0)
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.template_id.cmp(&b.template_id)));
+        matches
+    }
+
     fn load_default_templates(&mut self) {
         // Binary search template
         self.add_template(CodeTemplate {
@@ -84,6 +834,32 @@ fn binary_search<T: Ord>(arr: &[T], target: &T) -> Option<usize> {
 }
 "#.to_string(),
             placeholders: vec![],
+            defaults: HashMap::new(),
+            description: "Find the position of a target value in a sorted slice".to_string(),
+            tags: vec!["search".to_string(), "sorted".to_string(), "array".to_string(), "index".to_string()],
+            dependencies: vec!["std".to_string()],
+            version: TemplateVersion::default(),
+            translations: vec![TemplateTranslation {
+                language: ProgrammingLanguage::Rhai,
+                template_code: r#"
+fn binary_search(arr, target) {
+    let left = 0;
+    let right = arr.len();
+    
+    while left < right {
+        let mid = left + (right - left) / 2;
+        if arr[mid] == target {
+            return mid;
+        } else if arr[mid] < target {
+            left = mid + 1;
+        } else {
+            right = mid;
+        }
+    }
+    ()
+}
+"#.to_string(),
+            }],
         });
 
         // Graph traversal template
@@ -119,6 +895,42 @@ fn bfs(graph: &HashMap<usize, Vec<usize>>, start: usize) -> Vec<usize> {
 }
 "#.to_string(),
             placeholders: vec![],
+            defaults: HashMap::new(),
+            description: "Visit every vertex reachable from a start vertex, nearest first".to_string(),
+            tags: vec!["graph".to_string(), "bfs".to_string(), "breadth-first".to_string(), "traversal".to_string()],
+            dependencies: vec!["std::collections".to_string()],
+            version: TemplateVersion::default(),
+            // Rhai maps are keyed by strings
+            translations: vec![TemplateTranslation {
+                language: ProgrammingLanguage::Rhai,
+                template_code: r#"
+fn bfs(graph, start) {
+    let visited = [];
+    let queue = [];
+    let result = [];
+    
+    queue.push(start);
+    visited.push(start);
+    
+    while queue.len() > 0 {
+        let node = queue.shift();
+        result.push(node);
+        
+        let neighbors = graph[node.to_string()];
+        if neighbors != () {
+            for neighbor in neighbors {
+                if !visited.contains(neighbor) {
+                    visited.push(neighbor);
+                    queue.push(neighbor);
+                }
+            }
+        }
+    }
+    
+    result
+}
+"#.to_string(),
+            }],
         });
 
         // Rhai script template
@@ -146,50 +958,259 @@ fn calculate(operation, a, b) {
         0
     }
 }
+
+calculate("{{operation}}", {{a}}, {{b}})
 "#.to_string(),
             placeholders: vec!["operation".to_string(), "a".to_string(), "b".to_string()],
+            defaults: [("operation", "add"), ("a", "5"), ("b", "3")].into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            description: "Apply an arithmetic operation to two numbers".to_string(),
+            tags: vec!["calculator".to_string(), "arithmetic".to_string(), "rhai".to_string()],
+            dependencies: vec![],
+            version: TemplateVersion::default(),
+            translations: vec![TemplateTranslation {
+                language: ProgrammingLanguage::Rust,
+                template_code: r#"
+fn calculate(operation: &str, a: i64, b: i64) -> i64 {
+    match operation {
+        "add" => a + b,
+        "subtract" => a - b,
+        "multiply" => a * b,
+        "divide" => {
+            if b != 0 {
+                a / b
+            } else {
+                println!("Error: Division by zero");
+                0
+            }
+        }
+        _ => {
+            println!("Unknown operation");
+            0
+        }
+    }
+}
+
+fn main() {
+    println!("{:?}", calculate("{{operation}}", {{a}}, {{b}}));
+}
+"#.to_string(),
+            }],
         });
     }
 
     /// Generate code from description
     pub fn generate(&self, description: &str) -> Result<GeneratedCode> {
-        let code_id = uuid::Uuid::new_v4().to_string();
-        
-        // Try to match with templates
-        let (code, language, dependencies) = if description.contains("binary search") {
-            (
-                self.templates.get("binary_search").unwrap().template_code.clone(),
-                ProgrammingLanguage::Rust,
-                vec!["std".to_string()],
-            )
-        } else if description.contains("graph") && description.contains("bfs") {
-            (
-                self.templates.get("graph_bfs").unwrap().template_code.clone(),
-                ProgrammingLanguage::Rust,
-                vec!["std::collections".to_string()],
-            )
-        } else if description.contains("calculator") || description.contains("rhai") {
-            (
-                self.templates.get("rhai_calculator").unwrap().template_code.clone(),
-                ProgrammingLanguage::Rhai,
-                vec![],
-            )
-        } else {
+        self.generate_with_bindings(description, &HashMap::new())
+    }
+
+    /// Generate code from description, filling the matched template's
+    /// placeholders from `bindings` (see `CodeTemplate::render`)
+    pub fn generate_with_bindings(
+        &self,
+        description: &str,
+        bindings: &HashMap<String, String>,
+    ) -> Result<GeneratedCode> {
+        let (code, language, dependencies) = self.draft(description, bindings)?;
+        Ok(self.finish(description, code, language, dependencies))
+    }
+
+    /// Code, language and dependencies from the best template, or a stub
+    fn draft(
+        &self,
+        description: &str,
+        bindings: &HashMap<String, String>,
+    ) -> Result<(String, ProgrammingLanguage, Vec<String>)> {
+        Ok(match self.match_template(description, bindings)? {
+            Some(matched) => matched,
             // Generate simple code
-            (
+            None => (
                 format!("// Generated code for: {}\nfn main() {{\n    println!(\"Implementation needed\");\n}}", description),
                 ProgrammingLanguage::Rust,
                 vec![],
-            )
+            ),
+        })
+    }
+
+    /// Generate code from description, failing with every violation listed
+    /// if the code breaks `constraints`
+    pub fn generate_with_constraints(
+        &self,
+        description: &str,
+        constraints: &GenerationConstraints,
+    ) -> Result<GeneratedCode> {
+        let code = self.generate(description)?;
+        let violations = constraints.check(&code.code, &code.language);
+        if !violations.is_empty() {
+            let listed: Vec<String> = violations.iter().map(ToString::to_string).collect();
+            return Err(Error::CodeGeneration(format!(
+                "code for '{}' violates its constraints: {}",
+                description,
+                listed.join("; "),
+            )));
+        }
+        Ok(code)
+    }
+
+    /// Generate code from description, asking the LLM backend when no
+    /// template matches
+    ///
+    /// Templates stay the fast path and never reach the backend. Without a
+    /// backend this is the same as `generate`. The description is passed to
+    /// the model as a delimited block, not as instructions.
+    pub async fn generate_with_llm(&self, description: &str) -> Result<GeneratedCode> {
+        let llm = match &self.llm {
+            Some(llm) if self.match_template(description, &HashMap::new())?.is_none() => llm,
+            _ => {
+                let (code, language, dependencies) = self.draft(description, &HashMap::new())?;
+                return Ok(self.finish_async(description, code, language, dependencies).await);
+            }
         };
+        
+        let language = ProgrammingLanguage::Rust;
+        let reply = llm.generate(&code_prompt(description, &language)).await?;
+        self.finish_llm_reply(description, &reply, &language).await
+    }
+
+    /// Generate code for `description` as `generate_with_llm` does, sending
+    /// it in pieces as it is produced
+    ///
+    /// LLM replies are streamed token by token, fences stripped; template
+    /// and stub code is sent a line at a time. The last chunk carries the
+    /// finished `GeneratedCode` or the error that ended generation.
+    pub fn generate_streaming(self: &Arc<Self>, description: &str) -> mpsc::Receiver<CodeChunk> {
+        let (tx, rx) = mpsc::channel(100);
+        let generator = Arc::clone(self);
+        let description = description.to_string();
+        tokio::spawn(async move {
+            let chunks = CodeChunkSender { tx, sent: AtomicUsize::new(0) };
+            let result = generator.stream_code(&description, &chunks).await;
+            chunks.finish(result).await;
+        });
+        rx
+    }
+
+    async fn stream_code(&self, description: &str, chunks: &CodeChunkSender) -> Result<GeneratedCode> {
+        let llm = match &self.llm {
+            Some(llm) if self.match_template(description, &HashMap::new())?.is_none() => llm,
+            _ => {
+                let (code, language, dependencies) = self.draft(description, &HashMap::new())?;
+                let generated = self.finish_async(description, code, language, dependencies).await;
+                for line in generated.code.split_inclusive('\n') {
+                    chunks.code(line).await;
+                }
+                return Ok(generated);
+            }
+        };
+        
+        let language = ProgrammingLanguage::Rust;
+        let prompt = code_prompt(description, &language);
+        let (tokens, mut received) = mpsc::channel(100);
+        let forward = async {
+            let mut fence = FenceFilter::default();
+            while let Some(token) = received.recv().await {
+                chunks.code(&fence.push(&token)).await;
+            }
+            chunks.code(&fence.finish()).await;
+        };
+        let (reply, ()) = tokio::join!(llm.generate_stream(&prompt, tokens), forward);
+        self.finish_llm_reply(description, &reply?, &language).await
+    }
+
+    async fn finish_llm_reply(&self, description: &str, reply: &str, language: &ProgrammingLanguage) -> Result<GeneratedCode> {
+        let (code, language) = extract_code_block(reply, language);
+        if code.is_empty() {
+            return Err(Error::CodeGeneration(format!("LLM backend returned no code for '{}'", description)));
+        }
+        Ok(self.finish_async(description, code, language, vec![]).await)
+    }
 
+    /// Ask the LLM backend to apply `instruction` to `existing_code`,
+    /// returning the change as edits and a unified diff
+    ///
+    /// Both the code and the instruction reach the model as delimited blocks.
+    /// The reply is diffed locally, so the patch always applies cleanly to
+    /// `existing_code`; an empty patch means the model changed nothing.
+    pub async fn generate_patch(&self, existing_code: &str, instruction: &str) -> Result<CodePatch> {
+        let llm = self.llm.as_ref()
+            .ok_or_else(|| Error::CodeGeneration("generating a patch needs an LLM backend".to_string()))?;
+        let prompt = format!(
+            "Apply the change described in the instruction block to the code block. \
+             Reply with the complete edited code in a single fenced code block.\n{}\n{}",
+            prompt_lint::delimit(existing_code, "code"),
+            prompt_lint::delimit(instruction, "instruction"),
+        );
+        let reply = llm.generate(&prompt).await?;
+        let (code, _) = extract_code_block(&reply, &ProgrammingLanguage::Rust);
+        if code.is_empty() {
+            return Err(Error::CodeGeneration(format!("LLM backend returned no code for '{}'", instruction)));
+        }
+        Ok(CodePatch::between(existing_code, &code, "generated"))
+    }
+
+    /// Code, language and dependencies from the best template for
+    /// `description`, if one scores at least the match threshold
+    ///
+    /// Templates with placeholders that neither `bindings` nor their defaults
+    /// fill are passed over.
+    fn match_template(
+        &self,
+        description: &str,
+        bindings: &HashMap<String, String>,
+    ) -> Result<Option<(String, ProgrammingLanguage, Vec<String>)>> {
+        let best = self.rank_templates(description).into_iter()
+            .take_while(|m| m.score >= self.match_threshold)
+            .filter_map(|m| self.get_template(&m.template_id))
+            .find(|template| template.is_satisfied_by(bindings));
+        let Some(template) = best else {
+            return Ok(None);
+        };
+        let code = self.render_template(&template.template_id, bindings)?;
+        Ok(Some((code, template.language.clone(), template.dependencies.clone())))
+    }
+
+    fn finish(
+        &self,
+        description: &str,
+        code: String,
+        language: ProgrammingLanguage,
+        dependencies: Vec<String>,
+    ) -> GeneratedCode {
+        let (code, formatted) = self.format_code(code, &language);
+        self.package(description, code, formatted, language, dependencies)
+    }
+
+    /// `finish`, formatting on the blocking thread pool
+    async fn finish_async(
+        &self,
+        description: &str,
+        code: String,
+        language: ProgrammingLanguage,
+        dependencies: Vec<String>,
+    ) -> GeneratedCode {
+        let (code, formatted) = self.format_code_async(code, &language).await;
+        self.package(description, code, formatted, language, dependencies)
+    }
+
+    fn package(
+        &self,
+        description: &str,
+        code: String,
+        formatted: bool,
+        language: ProgrammingLanguage,
+        dependencies: Vec<String>,
+    ) -> GeneratedCode {
+        let code_id = self.ids.next_id();
+        
         // Generate test cases
-        let test_cases = self.generate_test_cases(description, &language);
+        let test_cases = self.generate_test_cases(description, &code, &language);
         
         // Calculate safety score
-        let safety_score = self.calculate_safety_score(&code);
+        let safety_score = self.calculate_safety_score(&code, &language);
+        let docs = self.document(&code, &language, description);
 
-        Ok(GeneratedCode {
+        GeneratedCode {
             code_id,
             language,
             code,
@@ -197,10 +1218,105 @@ fn calculate(operation, a, b) {
             dependencies,
             test_cases,
             safety_score,
+            formatted,
+            docs,
+        }
+    }
+
+    /// `code_docs::document`, when the documentation stage is on
+    fn document(&self, code: &str, language: &ProgrammingLanguage, description: &str) -> CodeDocs {
+        if self.docs_enabled {
+            code_docs::document(code, language, description)
+        } else {
+            CodeDocs::default()
+        }
+    }
+
+    /// `code` through the formatter stage, and whether it was formatted
+    ///
+    /// Formatting failures are logged and leave the code as generated.
+    fn format_code(&self, code: String, language: &ProgrammingLanguage) -> (String, bool) {
+        match self.active_formatter(language) {
+            Some(formatter) => {
+                let result = formatter.format(&code, language);
+                formatted_or(code, language, result)
+            }
+            None => (code, false),
+        }
+    }
+
+    /// `format_code` for async callers, which must not block on the formatter
+    async fn format_code_async(&self, code: String, language: &ProgrammingLanguage) -> (String, bool) {
+        let Some(formatter) = self.active_formatter(language).cloned() else {
+            return (code, false);
+        };
+        let (input, target) = (code.clone(), language.clone());
+        let result = tokio::task::spawn_blocking(move || formatter.format(&input, &target))
+            .await
+            .unwrap_or_else(|e| Err(Error::CodeGeneration(format!("formatter panicked: {}", e))));
+        formatted_or(code, language, result)
+    }
+
+    fn active_formatter(&self, language: &ProgrammingLanguage) -> Option<&Arc<dyn CodeFormatter>> {
+        self.formatter.as_ref().filter(|formatter| self.format_enabled && formatter.supports(language))
+    }
+
+    /// Generate Rhai retrieval code for a reasoner's `InformationRequest`
+    ///
+    /// Renders the `graph_retrieval` template with the `entities`,
+    /// `relations`, `constraints` and `format` placeholders bound. No such
+    /// template is built in, since scripts have no standard graph module to
+    /// call yet; register one written against the executor's functions.
+    pub fn generate_for_request(&self, request: &InformationRequest) -> Result<GeneratedCode> {
+        if !self.templates.contains_key(RETRIEVAL_TEMPLATE_ID) {
+            return Err(Error::CodeGeneration(format!(
+                "no '{}' template registered for information requests",
+                RETRIEVAL_TEMPLATE_ID,
+            )));
+        }
+        let relations: Vec<String> = request.relations.iter()
+            .map(|(source, target)| rhai_array(&[source.as_str(), target.as_str()]))
+            .collect();
+        let format = match request.desired_format {
+            DesiredFormat::Facts => "facts",
+            DesiredFormat::Table => "table",
+            DesiredFormat::Json => "json",
+        };
+        let bindings: HashMap<String, String> = [
+            ("entities", rhai_array(&request.entities.iter().map(String::as_str).collect::<Vec<_>>())),
+            ("relations", format!("[{}]", relations.join(", "))),
+            ("constraints", rhai_array(&request.constraints.iter().map(String::as_str).collect::<Vec<_>>())),
+            ("format", format.to_string()),
+        ].into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        let (code, formatted) = self.format_code(
+            self.render_template(RETRIEVAL_TEMPLATE_ID, &bindings)?,
+            &ProgrammingLanguage::Rhai,
+        );
+        
+        let description = format!(
+            "Retrieve {} entities and {} relations",
+            request.entities.len(),
+            request.relations.len(),
+        );
+        
+        Ok(GeneratedCode {
+            code_id: self.ids.next_id(),
+            language: ProgrammingLanguage::Rhai,
+            safety_score: self.calculate_safety_score(&code, &ProgrammingLanguage::Rhai),
+            docs: self.document(&code, &ProgrammingLanguage::Rhai, &description),
+            code,
+            description,
+            dependencies: vec![],
+            test_cases: vec![],
+            formatted,
         })
     }
 
-    fn generate_test_cases(&self, description: &str, language: &ProgrammingLanguage) -> Vec<TestCase> {
+    /// Known-answer cases for recognized descriptions, then edge cases
+    /// synthesized from the signatures in `code`
+    fn generate_test_cases(&self, description: &str, code: &str, language: &ProgrammingLanguage) -> Vec<TestCase> {
         let mut test_cases = Vec::new();
         
         if description.contains("binary search") {
@@ -222,50 +1338,281 @@ fn calculate(operation, a, b) {
             });
         }
         
-        test_cases
+        with_synthesized(test_cases, code, language)
     }
 
-    fn calculate_safety_score(&self, code: &str) -> f64 {
-        let mut score = 1.0;
-        
-        // Check for unsafe operations
-        if code.contains("unsafe") {
-            score -= 0.3;
+    /// See `code_safety::analyze` for what is scored
+    fn calculate_safety_score(&self, code: &str, language: &ProgrammingLanguage) -> f64 {
+        code_safety::analyze(code, language).score
+    }
+
+    /// Add `template` and make it the active version of its id
+    ///
+    /// Earlier versions stay available through `get_template_version` and
+    /// `rollback_template`; adding a version again replaces it.
+    pub fn add_template(&mut self, template: CodeTemplate) {
+        self.history.entry(template.template_id.clone())
+            .or_default()
+            .insert(template.version, template.clone());
+        self.templates.insert(template.template_id.clone(), template);
+    }
+
+    /// Active version of `template_id`
+    pub fn get_template(&self, template_id: &str) -> Option<&CodeTemplate> {
+        self.templates.get(template_id)
+    }
+
+    /// A specific version of `template_id`, active or not
+    pub fn get_template_version(&self, template_id: &str, version: TemplateVersion) -> Option<&CodeTemplate> {
+        self.history.get(template_id)
+            .and_then(|versions| versions.get(&version))
+            .or_else(|| self.get_template(template_id).filter(|t| t.version == version))
+    }
+
+    /// Known versions of `template_id`, oldest first
+    pub fn template_versions(&self, template_id: &str) -> Vec<TemplateVersion> {
+        let mut versions: Vec<TemplateVersion> = self.history.get(template_id)
+            .map(|versions| versions.keys().copied().collect())
+            .unwrap_or_default();
+        if let Some(active) = self.get_template(template_id) {
+            if !versions.contains(&active.version) {
+                versions.push(active.version);
+                versions.sort();
+            }
         }
-        if code.contains("unwrap()") {
-            score -= 0.1;
+        versions
+    }
+
+    /// Make `version` the active version of `template_id`
+    ///
+    /// Works in either direction, so a rollback can itself be undone.
+    pub fn rollback_template(&mut self, template_id: &str, version: TemplateVersion) -> Result<()> {
+        let template = self.get_template_version(template_id, version)
+            .cloned()
+            .ok_or_else(|| Error::CodeGeneration(format!("template '{}' has no version {}", template_id, version)))?;
+        self.templates.insert(template_id.to_string(), template);
+        Ok(())
+    }
+
+    /// Every active template as a template pack named `name`
+    pub fn export_templates(&self, name: &str, format: PackFormat) -> Result<String> {
+        TemplatePack::new(name, self.clock.as_ref(), self.templates.values()).to_string(format)
+    }
+
+    /// Add the templates of a pack written by `export_templates`, returning
+    /// their ids
+    ///
+    /// The whole pack is checked before anything changes, so a pack with a
+    /// bad checksum or template adds nothing. Imported templates replace
+    /// loaded ones with the same id, keeping the old ones in their history.
+    pub fn import_templates(&mut self, pack: &str, format: PackFormat) -> Result<Vec<String>> {
+        let templates = TemplatePack::parse(pack, format)?.into_templates()?;
+        let ids = templates.iter().map(|t| t.template_id.clone()).collect();
+        for template in templates {
+            self.add_template(template);
         }
-        if code.contains("panic!") {
-            score -= 0.2;
+        Ok(ids)
+    }
+
+    /// Load every `.toml`, `.yaml` and `.yml` template definition in `dir`
+    ///
+    /// Each file holds one template: `id`, `language` (a fence tag such as
+    /// `rust`), `code`, and optionally `name`, `description`, `placeholders`,
+    /// `defaults`, `tags`, `dependencies` and `version`. All files are
+    /// parsed and validated before anything changes, so a bad file leaves the
+    /// current templates in place. Templates are added with `add_template`,
+    /// overriding built-in ones with the same id. Loading a directory again,
+    /// or calling `reload_templates`, replaces what the previous load added,
+    /// except templates added or rolled back since.
+    pub fn load_templates_from_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let dir = dir.as_ref();
+        let fingerprint = template_files(dir)?;
+        let mut loaded: Vec<CodeTemplate> = Vec::new();
+        let mut sources: HashMap<String, &Path> = HashMap::new();
+        for (path, _, _) in &fingerprint {
+            let template = read_template_file(path)?;
+            if let Some(other) = sources.insert(template.template_id.clone(), path) {
+                return Err(Error::CodeGeneration(format!(
+                    "template '{}' is defined in both {} and {}",
+                    template.template_id,
+                    other.display(),
+                    path.display(),
+                )));
+            }
+            loaded.push(template);
         }
         
-        // Bonus for safety features
-        if code.contains("Result<") {
-            score += 0.1;
+        // Undo the previous load before applying this one
+        if let Some(previous) = self.template_dir.take() {
+            self.unload_template_dir(previous);
         }
-        if code.contains("Option<") {
-            score += 0.05;
+        let count = loaded.len();
+        let mut shadowed = HashMap::new();
+        let mut owned = HashMap::new();
+        for template in loaded {
+            if let Some(replaced) = self.get_template(&template.template_id) {
+                shadowed.insert(template.template_id.clone(), replaced.clone());
+            }
+            owned.insert(template.template_id.clone(), template.clone());
+            self.add_template(template);
         }
-        
-        score.max(0.0).min(1.0)
+        self.template_dir = Some(TemplateDir {
+            path: dir.to_path_buf(),
+            fingerprint,
+            loaded: owned,
+            shadowed,
+        });
+        Ok(count)
     }
 
-    pub fn add_template(&mut self, template: CodeTemplate) {
-        self.templates.insert(template.template_id.clone(), template);
+    /// Withdraw the templates `dir` loaded and restore the ones they replaced
+    ///
+    /// Ids whose active template has changed since the load are left alone,
+    /// so later adds, rollbacks and imports survive.
+    fn unload_template_dir(&mut self, dir: TemplateDir) {
+        let TemplateDir { loaded, mut shadowed, .. } = dir;
+        for (template_id, template) in loaded {
+            if self.templates.get(&template_id) != Some(&template) {
+                continue;
+            }
+            self.templates.remove(&template_id);
+            if let Some(versions) = self.history.get_mut(&template_id) {
+                if versions.get(&template.version) == Some(&template) {
+                    versions.remove(&template.version);
+                }
+                if versions.is_empty() {
+                    self.history.remove(&template_id);
+                }
+            }
+            if let Some(replaced) = shadowed.remove(&template_id) {
+                self.add_template(replaced);
+            }
+        }
     }
 
-    pub fn get_template(&self, template_id: &str) -> Option<&CodeTemplate> {
-        self.templates.get(template_id)
+    /// Reload the template directory if a file was added, removed or modified
+    ///
+    /// Returns whether a reload happened. Poll this to pick up edits without
+    /// a restart; on error the previously loaded templates stay active.
+    pub fn reload_templates(&mut self) -> Result<bool> {
+        let Some(dir) = &self.template_dir else {
+            return Ok(false);
+        };
+        if template_files(&dir.path)? == dir.fingerprint {
+            return Ok(false);
+        }
+        let path = dir.path.clone();
+        self.load_templates_from_dir(path)?;
+        Ok(true)
+    }
+
+    /// Render `template_id`, expanding its includes from the loaded templates
+    ///
+    /// Bindings for an included template carry its scope as a prefix, e.g.
+    /// `calc.operation` for `{{> rhai_calculator as calc}}`, and nest for
+    /// includes of includes. Fails on include cycles.
+    pub fn render_template(&self, template_id: &str, bindings: &HashMap<String, String>) -> Result<String> {
+        self.render_included(template_id, bindings, &mut Vec::new())
+    }
+
+    /// `render_template` with `stack` holding the templates being rendered
+    fn render_included(
+        &self,
+        template_id: &str,
+        bindings: &HashMap<String, String>,
+        stack: &mut Vec<String>,
+    ) -> Result<String> {
+        let cycle = stack.iter().any(|id| id == template_id);
+        stack.push(template_id.to_string());
+        if cycle {
+            return Err(Error::CodeGeneration(format!("template include cycle: {}", stack.join(" -> "))));
+        }
+        let template = self.get_template(template_id)
+            .ok_or_else(|| Error::CodeGeneration(format!("template '{}' is not loaded", template_id)))?;
+        let rendered = template.render_with(bindings, &mut |included, scoped| {
+            self.render_included(included, scoped, stack)
+        })?;
+        stack.pop();
+        Ok(rendered)
     }
 
     /// Generate code with specific language
+    ///
+    /// Fails if the code for `description` comes out in another language;
+    /// `translate` converts it.
     pub fn generate_with_language(
         &self,
         description: &str,
         language: ProgrammingLanguage,
     ) -> Result<GeneratedCode> {
-        let mut code = self.generate(description)?;
-        code.language = language;
+        let code = self.generate(description)?;
+        if code.language != language {
+            return Err(Error::CodeGeneration(format!(
+                "'{}' generates {:?} code, not {:?}; translate it instead",
+                description, code.language, language,
+            )));
+        }
+        Ok(code)
+    }
+
+    /// `generated` rewritten in `target`
+    ///
+    /// Code rendered from any known version of a template with a translation
+    /// to `target` is translated without a model (see `code_translate`);
+    /// anything else needs the LLM backend. Hand-written test cases carry
+    /// over, since they describe behaviour rather than syntax. Edge cases are
+    /// synthesized afresh from the translated signatures and safety is scored
+    /// on the translated code. Dependencies are dropped, as they name
+    /// source-language packages.
+    pub async fn translate(&self, generated: &GeneratedCode, target: ProgrammingLanguage) -> Result<GeneratedCode> {
+        if generated.language == target {
+            return Ok(generated.clone());
+        }
+        let templates = self.history.values().flat_map(BTreeMap::values).chain(self.templates.values());
+        let code = match code_translate::translate(templates, &generated.code, &generated.language, &target) {
+            Some(code) => code,
+            None => self.translate_with_llm(generated, &target).await?,
+        };
+        
+        let (code, formatted) = self.format_code_async(code, &target).await;
+        let hand_written: Vec<TestCase> = generated.test_cases.iter()
+            .filter(|case| case.expected_output != test_synthesis::EXPECT_NO_PANIC)
+            .cloned()
+            .collect();
+        Ok(GeneratedCode {
+            code_id: self.ids.next_id(),
+            test_cases: with_synthesized(hand_written, &code, &target),
+            safety_score: self.calculate_safety_score(&code, &target),
+            docs: self.document(&code, &target, &generated.description),
+            language: target,
+            code,
+            description: generated.description.clone(),
+            dependencies: vec![],
+            formatted,
+        })
+    }
+
+    async fn translate_with_llm(&self, generated: &GeneratedCode, target: &ProgrammingLanguage) -> Result<String> {
+        let llm = self.llm.as_ref()
+            .ok_or_else(|| Error::CodeGeneration(format!(
+                "translating '{}' needs an LLM backend; only template code with a translation converts without one",
+                generated.description,
+            )))?;
+        let prompt = format!(
+            "Translate the code block from {} to {}, keeping its behaviour and function names. \
+             Reply with a single fenced code block.\n{}",
+            generated.language.fence_tag(),
+            target.fence_tag(),
+            prompt_lint::delimit(&generated.code, "code"),
+        );
+        let reply = llm.generate(&prompt).await?;
+        let (code, language) = extract_code_block(&reply, target);
+        if language != *target {
+            return Err(Error::CodeGeneration(format!("LLM backend translated to {:?}, not {:?}", language, target)));
+        }
+        if code.is_empty() {
+            return Err(Error::CodeGeneration(format!("LLM backend returned no code translating '{}'", generated.description)));
+        }
         Ok(code)
     }
 }
@@ -273,6 +1620,8 @@ fn calculate(operation, a, b) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::level4::agents::code_constraints::ErrorHandling;
+    use crate::level4::agents::code_format::CommandFormatter;
 
     #[test]
     fn test_generate_binary_search() {
@@ -281,7 +1630,8 @@ mod tests {
         
         assert_eq!(code.language, ProgrammingLanguage::Rust);
         assert!(code.code.contains("binary_search"));
-        assert!(!code.test_cases.is_empty());
+        assert_eq!(code.test_cases[0].expected_output, "Some(2)");
+        assert!(code.test_cases.iter().any(|c| c.input.starts_with("arr=[],")));
     }
 
     #[test]
@@ -290,7 +1640,575 @@ mod tests {
         let safe_code = "fn safe() -> Result<(), Error> { Ok(()) }";
         let unsafe_code = "fn unsafe_fn() { unsafe { } }";
         
-        assert!(generator.calculate_safety_score(safe_code) > 0.9);
-        assert!(generator.calculate_safety_score(unsafe_code) < 0.8);
+        let rust = ProgrammingLanguage::Rust;
+        
+        assert!(generator.calculate_safety_score(safe_code, &rust) > 0.9);
+        assert!(generator.calculate_safety_score(unsafe_code, &rust) < 0.8);
+        // Mentions in comments are not uses
+        assert_eq!(generator.calculate_safety_score("// avoid unwrap()\nfn f() {}", &rust), 1.0);
+    }
+
+    fn bindings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_template_placeholders_are_rendered() {
+        let generator = CodeGenerator::new();
+        let code = generator
+            .generate_with_bindings("rhai calculator", &bindings(&[("operation", "multiply"), ("a", "6")]))
+            .unwrap();
+        assert!(code.code.contains("calculate(\"multiply\", 6, 3)"));
+        assert!(!code.code.contains("{{"));
+        
+        let template = CodeTemplate {
+            template_id: "greet".to_string(),
+            name: "Greeting".to_string(),
+            language: ProgrammingLanguage::Python,
+            template_code: "print(\"Hello, {{name}}\")".to_string(),
+            placeholders: vec!["name".to_string()],
+            defaults: HashMap::new(),
+            description: String::new(),
+            tags: vec![],
+            dependencies: vec![],
+            version: TemplateVersion::default(),
+            translations: vec![],
+        };
+        assert_eq!(template.render(&bindings(&[("name", "graph")])).unwrap(), "print(\"Hello, graph\")");
+        assert!(template.render(&HashMap::new()).is_err());
+        assert!(template.render(&bindings(&[("name", "x"), ("age", "3")])).is_err());
+        let undeclared = CodeTemplate { placeholders: vec![], ..template.clone() };
+        assert!(undeclared.render(&HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_generate_for_information_request() {
+        let mut generator = CodeGenerator::new();
+        let request = InformationRequest {
+            entities: vec!["node_0".to_string(), "say \"hi\"".to_string()],
+            relations: vec![("node_0".to_string(), "guess_0".to_string())],
+            constraints: vec!["confidence >= 0.70".to_string()],
+            desired_format: DesiredFormat::Table,
+        };
+        // Nothing to render until the host registers a retrieval template
+        assert!(generator.generate_for_request(&request).is_err());
+        
+        generator.add_template(CodeTemplate {
+            template_id: RETRIEVAL_TEMPLATE_ID.to_string(),
+            name: "Graph Retrieval".to_string(),
+            language: ProgrammingLanguage::Rhai,
+            template_code: "let entities = {{entities}};\nlet relations = {{relations}};\nretrieve(entities, relations, {{constraints}}, \"{{format}}\")".to_string(),
+            placeholders: vec![
+                "entities".to_string(),
+                "relations".to_string(),
+                "constraints".to_string(),
+                "format".to_string(),
+            ],
+            defaults: HashMap::new(),
+            description: String::new(),
+            tags: vec![],
+            dependencies: vec![],
+            version: TemplateVersion::default(),
+            translations: vec![],
+        });
+        let code = generator.generate_for_request(&request).unwrap();
+        
+        assert_eq!(code.language, ProgrammingLanguage::Rhai);
+        assert!(code.code.contains(r#"let entities = ["node_0", "say \"hi\""];"#));
+        assert!(code.code.contains(r#"let relations = [["node_0", "guess_0"]];"#));
+        assert!(code.code.contains(r#"retrieve(entities, relations, ["confidence >= 0.70"], "table")"#));
+    }
+
+    #[test]
+    fn test_load_and_reload_templates_from_dir() {
+        let dir = std::env::temp_dir().join(format!("templates_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greet.toml"), r#"
+id = "greet"
+language = "python"
+code = 'print("Hello, {{name}}")'
+placeholders = ["name"]
+tags = ["demo"]
+"#).unwrap();
+        std::fs::write(dir.join("search.yaml"), "id: binary_search\nlanguage: rust\ncode: fn search() {}\n").unwrap();
+        
+        let mut generator = CodeGenerator::new();
+        let builtin = generator.get_template("binary_search").unwrap().template_code.clone();
+        assert_eq!(generator.load_templates_from_dir(&dir).unwrap(), 2);
+        assert_eq!(generator.get_template("greet").unwrap().tags, vec!["demo"]);
+        assert_eq!(generator.get_template("binary_search").unwrap().template_code, "fn search() {}");
+        assert!(!generator.reload_templates().unwrap());
+        
+        // A broken file is rejected and the loaded templates stay active
+        std::fs::write(dir.join("broken.toml"), "id = \"broken\"\nlanguage = \"cobol\"\ncode = \"\"\n").unwrap();
+        assert!(generator.reload_templates().is_err());
+        assert!(generator.get_template("greet").is_some());
+        
+        // Removing the override restores the built-in template
+        std::fs::remove_file(dir.join("broken.toml")).unwrap();
+        std::fs::remove_file(dir.join("search.yaml")).unwrap();
+        assert!(generator.reload_templates().unwrap());
+        assert_eq!(generator.get_template("binary_search").unwrap().template_code, builtin);
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reload_keeps_later_template_edits() {
+        let dir = std::env::temp_dir().join(format!("templates_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greet.toml"), "id = \"greet\"\nlanguage = \"python\"\ncode = 'print(\"hi\")'\n").unwrap();
+        std::fs::write(dir.join("search.yaml"), "id: binary_search\nlanguage: rust\ncode: fn search() {}\n").unwrap();
+        
+        let mut generator = CodeGenerator::new();
+        generator.load_templates_from_dir(&dir).unwrap();
+        let loaded = generator.get_template("greet").unwrap().clone();
+        
+        // Loaded templates are in the history like any other
+        let v2 = TemplateVersion::new(2, 0, 0);
+        generator.add_template(CodeTemplate { version: v2, template_code: "print(\"hello\")".to_string(), ..loaded.clone() });
+        generator.rollback_template("greet", loaded.version).unwrap();
+        generator.rollback_template("greet", v2).unwrap();
+        let mine = CodeTemplate {
+            template_code: "fn mine() {}".to_string(),
+            ..generator.get_template("binary_search").unwrap().clone()
+        };
+        generator.add_template(mine);
+        
+        // Removing the files does not undo what was done since the load
+        std::fs::remove_file(dir.join("greet.toml")).unwrap();
+        std::fs::remove_file(dir.join("search.yaml")).unwrap();
+        assert!(generator.reload_templates().unwrap());
+        assert_eq!(generator.get_template("greet").unwrap().version, v2);
+        assert_eq!(generator.get_template("binary_search").unwrap().template_code, "fn mine() {}");
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[derive(Debug, Default)]
+    struct ScriptedLlm {
+        prompts: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl CodeLlmBackend for ScriptedLlm {
+        async fn generate(&self, prompt: &str) -> Result<String> {
+            self.prompts.lock().unwrap().push(prompt.to_string());
+            Ok("Here you go:\n```rhai\nfn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }\n```\n".to_string())
+        }
+    }
+
+    /// Replies in pieces that split the fences
+    #[derive(Debug)]
+    struct TokenLlm;
+
+    #[async_trait]
+    impl CodeLlmBackend for TokenLlm {
+        async fn generate(&self, _prompt: &str) -> Result<String> {
+            unreachable!("streaming uses generate_stream")
+        }
+
+        async fn generate_stream(&self, _prompt: &str, tokens: mpsc::Sender<String>) -> Result<String> {
+            let pieces = ["Sure:\n``", "`rust\nfn one() -> i32 ", "{\n    1\n}\n`", "``\nDone."];
+            for piece in pieces {
+                tokens.send(piece.to_string()).await.unwrap();
+            }
+            Ok(pieces.concat())
+        }
+    }
+
+    async fn collect_chunks(mut rx: mpsc::Receiver<CodeChunk>) -> (String, CodeChunk) {
+        let mut streamed = String::new();
+        while let Some(chunk) = rx.recv().await {
+            if chunk.is_final {
+                return (streamed, chunk);
+            }
+            streamed.push_str(&chunk.content);
+        }
+        panic!("stream ended without a final chunk");
+    }
+
+    #[tokio::test]
+    async fn test_generate_streaming() {
+        let generator = Arc::new(CodeGenerator::new().with_llm_backend(Arc::new(TokenLlm)));
+        
+        let (streamed, last) = collect_chunks(generator.generate_streaming("return the number one")).await;
+        assert_eq!(streamed, "fn one() -> i32 {\n    1\n}\n");
+        let result = last.result.unwrap();
+        assert_eq!(result.code, streamed.trim_end());
+        assert!(last.error.is_none());
+        
+        // Templates stream line by line
+        let (streamed, last) = collect_chunks(generator.generate_streaming("implement binary search")).await;
+        assert_eq!(streamed, last.result.unwrap().code);
+        
+        let mut filter = FenceFilter::default();
+        assert_eq!(filter.push("no fence "), "");
+        assert_eq!(filter.finish(), "no fence");
+    }
+
+    #[tokio::test]
+    async fn test_llm_backend_handles_unmatched_descriptions() {
+        let llm = Arc::new(ScriptedLlm::default());
+        let generator = CodeGenerator::new().with_llm_backend(llm.clone());
+
+        // Templates are the fast path
+        let code = generator.generate_with_llm("implement binary search").await.unwrap();
+        assert!(code.code.contains("binary_search"));
+        assert!(llm.prompts.lock().unwrap().is_empty());
+
+        let code = generator.generate_with_llm("fibonacci numbers").await.unwrap();
+        assert_eq!(code.language, ProgrammingLanguage::Rhai);
+        assert!(code.code.starts_with("fn fib(n)"));
+        assert!(!code.code.contains("```"));
+        let prompts = llm.prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains(&prompt_lint::delimit("fibonacci numbers", "description")));
+    }
+
+    #[derive(Debug)]
+    struct TrimTrailing;
+
+    impl CodeFormatter for TrimTrailing {
+        fn supports(&self, language: &ProgrammingLanguage) -> bool {
+            *language == ProgrammingLanguage::Rust
+        }
+
+        fn format(&self, code: &str, _language: &ProgrammingLanguage) -> Result<String> {
+            Ok(code.lines().map(str::trim_end).collect::<Vec<_>>().join("\n"))
+        }
+    }
+
+    #[test]
+    fn test_formatter_stage() {
+        let generator = CodeGenerator::new().with_formatter(Arc::new(TrimTrailing));
+        let code = generator.generate("implement binary search").unwrap();
+        assert!(code.formatted);
+        assert!(code.code.lines().all(|line| line == line.trim_end()));
+        
+        // Unsupported languages pass through untouched
+        assert!(!generator.generate("rhai calculator").unwrap().formatted);
+        
+        let disabled = CodeGenerator::new()
+            .with_formatter(Arc::new(TrimTrailing))
+            .with_formatting(false);
+        assert!(!disabled.generate("implement binary search").unwrap().formatted);
+        
+        // A formatter that fails leaves the code as generated
+        let missing = CommandFormatter::new("no-such-formatter", &[], vec![ProgrammingLanguage::Rust]);
+        let generator = CodeGenerator::new().with_formatter(Arc::new(missing));
+        let code = generator.generate("implement binary search").unwrap();
+        assert!(!code.formatted);
+        assert!(code.code.contains("binary_search"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_formatter_timeout() {
+        let hanging = CommandFormatter::new("sleep", &["30"], vec![ProgrammingLanguage::Rust])
+            .with_timeout(std::time::Duration::from_millis(100));
+        let generator = CodeGenerator::new().with_formatter(Arc::new(hanging));
+        
+        let started = std::time::Instant::now();
+        let code = generator.generate_with_llm("implement binary search").await.unwrap();
+        assert!(!code.formatted);
+        assert!(code.code.contains("binary_search"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_template_versions_and_rollback() {
+        let mut generator = CodeGenerator::new();
+        let original = generator.get_template("binary_search").unwrap().clone();
+        let v2 = TemplateVersion::new(2, 0, 0);
+        generator.add_template(CodeTemplate {
+            template_code: "fn binary_search() {}".to_string(),
+            version: v2,
+            ..original.clone()
+        });
+        
+        assert_eq!(generator.template_versions("binary_search"), vec![TemplateVersion::default(), v2]);
+        assert_eq!(generator.get_template("binary_search").unwrap().version, v2);
+        
+        generator.rollback_template("binary_search", "1.0.0".parse().unwrap()).unwrap();
+        assert_eq!(generator.get_template("binary_search").unwrap().template_code, original.template_code);
+        assert_eq!(generator.get_template_version("binary_search", v2).unwrap().template_code, "fn binary_search() {}");
+        assert!(generator.rollback_template("binary_search", TemplateVersion::new(3, 0, 0)).is_err());
+        
+        let parsed: TemplateVersion = serde_json::from_str("\"2.0.1\"").unwrap();
+        assert_eq!(parsed.to_string(), "2.0.1");
+        assert!(serde_json::from_str::<TemplateVersion>("\"2.0\"").is_err());
+        assert!("1.2.3.4".parse::<TemplateVersion>().is_err());
+    }
+
+    #[test]
+    fn test_export_import_templates() {
+        let mut source = CodeGenerator::new();
+        let calculator = source.get_template("rhai_calculator").unwrap().clone();
+        let calculator_translations = calculator.translations.clone();
+        source.add_template(CodeTemplate {
+            template_id: "rhai_power".to_string(),
+            name: "Rhai Power".to_string(),
+            template_code: "{{base}} ** 2".to_string(),
+            placeholders: vec!["base".to_string()],
+            defaults: HashMap::new(),
+            translations: vec![],
+            ..calculator
+        });
+        let pack = source.export_templates("team", PackFormat::Toml).unwrap();
+        
+        let mut target = CodeGenerator::new();
+        let mut ids = target.import_templates(&pack, PackFormat::Toml).unwrap();
+        ids.sort();
+        assert_eq!(ids, vec!["binary_search", "graph_bfs", "rhai_calculator", "rhai_power"]);
+        assert_eq!(target.get_template("rhai_power").unwrap().template_code, "{{base}} ** 2");
+        assert_eq!(target.get_template("rhai_calculator").unwrap().translations, calculator_translations);
+        
+        // A corrupted pack leaves the templates alone
+        let corrupted = pack.replace("{{base}} ** 2", "{{base}} ** 3");
+        assert!(CodeGenerator::new().import_templates(&corrupted, PackFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn test_template_composition() {
+        let mut generator = CodeGenerator::new();
+        let bfs = generator.get_template("graph_bfs").unwrap().clone();
+        let composed = |template_id: &str, code: &str, placeholders: &[&str]| CodeTemplate {
+            template_id: template_id.to_string(),
+            name: template_id.to_string(),
+            template_code: code.to_string(),
+            placeholders: placeholders.iter().map(|p| p.to_string()).collect(),
+            defaults: HashMap::new(),
+            tags: Vec::new(),
+            translations: Vec::new(),
+            ..bfs.clone()
+        };
+        generator.add_template(composed(
+            "bfs_report",
+            "{{> graph_bfs}}\n{{> rhai_calculator as calc}}\nfn report(order: &[usize]) -> String {\n    format!(\"{{label}}: {:?}\", order)\n}",
+            &["label"],
+        ));
+        
+        let bindings: HashMap<String, String> = [("label", "visited"), ("calc.operation", "multiply")].into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let rendered = generator.render_template("bfs_report", &bindings).unwrap();
+        assert!(rendered.starts_with(&bfs.template_code));
+        assert!(rendered.contains("calculate(\"multiply\", 5, 3)"));
+        assert!(rendered.contains("format!(\"visited: {:?}\", order)"));
+        
+        // Scopes only reach the include they name
+        let stray: HashMap<String, String> = [("graph_bfs.operation".to_string(), "add".to_string())].into();
+        assert!(generator.render_template("bfs_report", &stray).is_err());
+        // Includes need a generator to resolve them
+        assert!(generator.get_template("bfs_report").unwrap().render(&HashMap::new()).is_err());
+        
+        generator.add_template(composed("a", "{{> b}}", &[]));
+        generator.add_template(composed("b", "{{> a}}", &[]));
+        let err = generator.render_template("a", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+        assert!(composed("self", "{{> self}}", &[]).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ranked_matching_falls_back_below_threshold() {
+        let llm = Arc::new(ScriptedLlm::default());
+        let generator = CodeGenerator::new().with_llm_backend(llm.clone());
+        
+        let ranked = generator.rank_templates("breadth-first traversal of a graph");
+        assert_eq!(ranked[0].template_id, "graph_bfs");
+        assert!(ranked.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        
+        // No literal "bfs", but the tags and description carry it
+        let code = generator.generate_with_llm("breadth-first traversal of a graph").await.unwrap();
+        assert!(code.code.contains("fn bfs"));
+        assert_eq!(code.dependencies, vec!["std::collections"]);
+        assert!(llm.prompts.lock().unwrap().is_empty());
+        
+        // A lone shared word is too weak a match
+        generator.generate_with_llm("graph").await.unwrap();
+        assert_eq!(llm.prompts.lock().unwrap().len(), 1);
+        
+        // Templates needing bindings nobody supplied are skipped
+        let stub = CodeGenerator::new().generate("graph retrieval facts").unwrap();
+        assert!(stub.code.contains("Implementation needed"));
+    }
+
+    #[derive(Debug)]
+    struct WideningLlm;
+
+    #[async_trait]
+    impl CodeLlmBackend for WideningLlm {
+        async fn generate(&self, prompt: &str) -> Result<String> {
+            assert!(prompt.contains("source=\"instruction\">>>\nuse i64"));
+            Ok("```rust\nfn add(a: i64, b: i64) -> i64 {\n    a + b\n}\n```".to_string())
+        }
+    }
+
+    #[derive(Debug)]
+    struct RhaiTranslator;
+
+    #[async_trait]
+    impl CodeLlmBackend for RhaiTranslator {
+        async fn generate(&self, prompt: &str) -> Result<String> {
+            assert!(prompt.contains("from rust to rhai"));
+            Ok("```rhai\nfn binary_search(arr, target) {\n    arr.index_of(target)\n}\n```".to_string())
+        }
+    }
+
+    #[test]
+    fn test_generate_with_constraints() {
+        let generator = CodeGenerator::new();
+        let short = GenerationConstraints { max_lines: Some(5), ..Default::default() };
+        let err = generator.generate_with_constraints("implement binary search", &short).unwrap_err();
+        assert!(err.to_string().contains("over the limit of 5"));
+        
+        let no_panics = GenerationConstraints {
+            error_handling: ErrorHandling::NoPanics,
+            forbidden_crates: vec!["reqwest".to_string()],
+            ..Default::default()
+        };
+        assert!(generator.generate_with_constraints("implement binary search", &no_panics).is_ok());
+    }
+
+    #[test]
+    fn test_documentation_stage() {
+        let code = CodeGenerator::new().generate("implement binary search").unwrap();
+        assert_eq!(code.docs.functions[0].function, "binary_search");
+        assert!(code.docs.functions[0].doc_block.starts_with("/// Implement binary search."));
+        assert!(code.docs.usage_example.unwrap().contains("binary_search(&[1, 2, 3], &3)"));
+        // Docs stay out of the code body
+        assert!(!code.code.contains("/// Implement binary search."));
+        
+        let undocumented = CodeGenerator::new().with_documentation(false).generate("implement binary search").unwrap();
+        assert!(undocumented.docs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_translate_rust_to_rhai() {
+        // Template code with a translation needs no LLM backend
+        let generator = CodeGenerator::new();
+        let rust = generator.generate_with_language("implement binary search", ProgrammingLanguage::Rust).unwrap();
+        assert!(generator.generate_with_language("implement binary search", ProgrammingLanguage::Rhai).is_err());
+        
+        let rhai = generator.translate(&rust, ProgrammingLanguage::Rhai).await.unwrap();
+        assert_eq!(rhai.language, ProgrammingLanguage::Rhai);
+        assert!(rhai.code.trim_start().starts_with("fn binary_search(arr, target)"));
+        assert_ne!(rhai.code_id, rust.code_id);
+        assert!(rhai.dependencies.is_empty());
+        // The behavioural cases survive; Rust-only edge cases do not
+        assert_eq!(rhai.test_cases[..2], rust.test_cases[..2]);
+        assert!(rhai.test_cases.iter().all(|c| !c.input.contains("i32::MIN")));
+        assert!(rhai.test_cases.iter().any(|c| c.input == "arr=[], target=3"));
+        
+        assert_eq!(generator.translate(&rust, ProgrammingLanguage::Rust).await.unwrap().code_id, rust.code_id);
+        
+        let calculator = generator.generate("rhai calculator").unwrap();
+        let translated = generator.translate(&calculator, ProgrammingLanguage::Rust).await.unwrap();
+        assert!(translated.code.trim_start().starts_with("fn calculate(operation: &str, a: i64, b: i64) -> i64 {"));
+        assert!(translated.code.contains("println!(\"{:?}\", calculate(\"add\", 5, 3));"));
+        let bfs = generator.generate("breadth-first traversal of a graph").unwrap();
+        assert!(generator.translate(&bfs, ProgrammingLanguage::Rhai).await.unwrap().code.contains("queue.shift()"));
+        
+        // Other code goes to the LLM backend
+        let stub = generator.generate("fibonacci numbers").unwrap();
+        assert!(generator.translate(&stub, ProgrammingLanguage::Rhai).await.is_err());
+        let with_llm = CodeGenerator::new().with_llm_backend(Arc::new(RhaiTranslator));
+        let rhai = with_llm.translate(&stub, ProgrammingLanguage::Rhai).await.unwrap();
+        assert!(rhai.code.contains("arr.index_of(target)"));
+    }
+
+    #[tokio::test]
+    async fn test_code_ids_come_from_injected_generator() {
+        let mut generator = CodeGenerator::new()
+            .with_id_generator(Arc::new(clock::SequentialIds::new("code")));
+        assert_eq!(generator.generate("implement binary search").unwrap().code_id, "code-0");
+        
+        let calculator = generator.generate("rhai calculator").unwrap();
+        assert_eq!(calculator.code_id, "code-1");
+        let rust = generator.translate(&calculator, ProgrammingLanguage::Rust).await.unwrap();
+        assert_eq!(rust.code_id, "code-2");
+        
+        let calculator = generator.get_template("rhai_calculator").unwrap().clone();
+        generator.add_template(CodeTemplate {
+            template_id: RETRIEVAL_TEMPLATE_ID.to_string(),
+            template_code: "retrieve({{entities}}, {{relations}}, {{constraints}}, \"{{format}}\")".to_string(),
+            placeholders: ["entities", "relations", "constraints", "format"].iter().map(|p| p.to_string()).collect(),
+            defaults: HashMap::new(),
+            translations: vec![],
+            ..calculator
+        });
+        let request = InformationRequest {
+            entities: vec!["node_0".to_string()],
+            relations: vec![],
+            constraints: vec![],
+            desired_format: DesiredFormat::Facts,
+        };
+        assert_eq!(generator.generate_for_request(&request).unwrap().code_id, "code-3");
+    }
+
+    #[test]
+    fn test_builtin_templates_translate_between_rust_and_rhai() {
+        let generator = CodeGenerator::new();
+        for template in generator.templates.values() {
+            template.validate().unwrap();
+            let other = match template.language {
+                ProgrammingLanguage::Rust => ProgrammingLanguage::Rhai,
+                ProgrammingLanguage::Rhai => ProgrammingLanguage::Rust,
+                _ => continue,
+            };
+            let id = &template.template_id;
+            assert!(template.code_in(&other).is_some(), "'{}' has no {:?} translation", id, other);
+            
+            let code = generator.render_template(id, &HashMap::new()).unwrap();
+            let translated = code_translate::translate([template], &code, &template.language, &other)
+                .unwrap_or_else(|| panic!("'{}' does not translate to {:?}", id, other));
+            let back = code_translate::translate([template], &translated, &other, &template.language);
+            assert_eq!(back.as_deref(), Some(code.as_str()), "'{}' does not translate back", id);
+            let rust = if other == ProgrammingLanguage::Rust { &translated } else { &code };
+            assert!(syn::parse_file(rust).is_ok(), "'{}' is not valid Rust", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_translations_follow_template_edits() {
+        let mut generator = CodeGenerator::new();
+        let before = generator.generate("rhai calculator").unwrap();
+        let calculator = generator.get_template("rhai_calculator").unwrap().clone();
+        generator.add_template(CodeTemplate {
+            template_code: "{{a}} + {{b}}".to_string(),
+            placeholders: vec!["a".to_string(), "b".to_string()],
+            defaults: [("a", "5"), ("b", "3")].into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            version: "1.1.0".parse().unwrap(),
+            translations: vec![TemplateTranslation {
+                language: ProgrammingLanguage::Rust,
+                template_code: "fn main() {\n    println!(\"{}\", {{a}} + {{b}});\n}".to_string(),
+            }],
+            ..calculator
+        });
+        
+        let after = generator.generate("rhai calculator").unwrap();
+        assert_eq!(after.code, "5 + 3");
+        let translated = generator.translate(&after, ProgrammingLanguage::Rust).await.unwrap();
+        assert_eq!(translated.code, "fn main() {\n    println!(\"{}\", 5 + 3);\n}");
+        
+        // Code from the earlier version still translates with its own translation
+        let translated = generator.translate(&before, ProgrammingLanguage::Rust).await.unwrap();
+        assert!(translated.code.contains("println!(\"{:?}\", calculate(\"add\", 5, 3));"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_patch() {
+        let existing = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        assert!(CodeGenerator::new().generate_patch(existing, "use i64").await.is_err());
+        
+        let generator = CodeGenerator::new().with_llm_backend(Arc::new(WideningLlm));
+        let patch = generator.generate_patch(existing, "use i64").await.unwrap();
+        assert_eq!(patch.edits.len(), 1);
+        assert_eq!(patch.edits[0].line, 1);
+        assert!(patch.unified_diff.contains("+fn add(a: i64, b: i64) -> i64 {"));
+        assert_eq!(patch.apply(existing).unwrap(), existing.replace("i32", "i64"));
     }
 }
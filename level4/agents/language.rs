@@ -0,0 +1,93 @@
+// -*- coding: utf-8 -*-
+//! Response Language Detection
+//! 
+//! Lightweight script and stopword heuristics for picking the language an
+//! answer should be written in when the caller does not specify one.
+
+/// Language assumed when detection finds no signal
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Stopwords for Latin-script languages, checked in order
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "is", "what", "how", "of", "and", "which", "why"]),
+    ("id", &["apa", "yang", "dan", "bagaimana", "adalah", "dengan", "untuk", "ini"]),
+    ("es", &["el", "la", "qué", "cómo", "es", "los", "por", "una"]),
+    ("fr", &["le", "la", "est", "quoi", "comment", "les", "une", "des"]),
+    ("de", &["der", "die", "das", "ist", "wie", "und", "was", "ein"]),
+];
+
+/// Detect the ISO 639-1 language code of `text`
+///
+/// Non-Latin scripts are identified by their Unicode ranges; Latin-script
+/// text is scored against small stopword lists. Falls back to
+/// `DEFAULT_LANGUAGE` when nothing matches.
+pub fn detect_language(text: &str) -> &'static str {
+    let mut script_counts: [(&'static str, usize); 6] = [
+        ("ja", 0), ("ko", 0), ("zh", 0), ("ru", 0), ("ar", 0), ("hi", 0),
+    ];
+    for c in text.chars() {
+        let slot = match c as u32 {
+            0x3040..=0x30FF => 0,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => 1,
+            0x4E00..=0x9FFF => 2,
+            0x0400..=0x04FF => 3,
+            0x0600..=0x06FF => 4,
+            0x0900..=0x097F => 5,
+            _ => continue,
+        };
+        script_counts[slot].1 += 1;
+    }
+    // Japanese text mixes kana with CJK ideographs, so any kana wins over zh
+    if script_counts[0].1 > 0 {
+        return "ja";
+    }
+    if let Some((code, _)) = script_counts.iter().filter(|(_, n)| *n > 0).max_by_key(|(_, n)| *n) {
+        return code;
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    STOPWORDS.iter()
+        .map(|(code, stopwords)| {
+            let hits = words.iter().filter(|w| stopwords.contains(&w.as_str())).count();
+            (*code, hits)
+        })
+        .filter(|(_, hits)| *hits > 0)
+        .fold(None, |best: Option<(&'static str, usize)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+        .map(|(code, _)| code)
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// Resolve the language to answer in: an explicit request wins over detection
+pub fn resolve_response_language(requested: Option<&str>, query: &str) -> String {
+    match requested.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(language) => language.to_lowercase(),
+        None => detect_language(query).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("What is the shortest path?"), "en");
+        assert_eq!(detect_language("Apa yang dimaksud dengan graf?"), "id");
+        assert_eq!(detect_language("グラフとは何ですか"), "ja");
+        assert_eq!(detect_language("Что такое граф?"), "ru");
+        assert_eq!(detect_language("12345"), DEFAULT_LANGUAGE);
+    }
+
+    #[test]
+    fn test_requested_language_overrides_detection() {
+        assert_eq!(resolve_response_language(Some("FR"), "What is a graph?"), "fr");
+        assert_eq!(resolve_response_language(Some(" "), "Apa itu graf?"), "id");
+    }
+}
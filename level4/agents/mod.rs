@@ -5,10 +5,62 @@
 
 pub mod classification;
 pub mod reasoning;
+pub mod reasoning_pipeline;
 pub mod cache_manager;
+pub mod cache_backend;
+pub mod cache_invalidation;
+pub mod cache_metrics;
+pub mod cache_bloom;
+pub mod cache_wal;
+pub mod cache_decisions;
+pub mod beam_search;
+pub mod clock;
+pub mod code_constraints;
+pub mod code_docs;
+pub mod code_format;
+pub mod code_patch;
+pub mod code_safety;
+pub mod code_translate;
+pub mod contention;
+pub mod freshness;
 pub mod generate_code;
+pub mod language;
+pub mod prompt_lint;
+pub mod template_match;
+pub mod template_pack;
+pub mod test_synthesis;
 
 pub use classification::{QueryClassifier, QueryType, ClassificationResult};
-pub use reasoning::{GLMReasoning, ReasoningStep, ReasoningChain};
-pub use cache_manager::{VertexCentricCache, CacheEntry, CacheStats};
-pub use generate_code::{CodeGenerator, GeneratedCode, CodeTemplate};
+pub use reasoning::{
+    GLMReasoning, ReasoningStep, ReasoningChain, StepType,
+    LinkPredictor, PredictedLink, LinkProvenance, InformationRequest, DesiredFormat,
+};
+pub use reasoning_pipeline::{ReasoningPipeline, PipelineStage, ReasoningStepProvider, StepContext};
+pub use beam_search::{ThoughtExpander, BeamSearchConfig, BeamSearchResult, ThoughtBranch};
+pub use cache_manager::{
+    VertexCentricCache, CacheEntry, CacheValue, CacheStats, CacheConfig,
+    EmbeddingQuantization, QuantizedEmbedding,
+    CacheSnapshot, LocalityHint, EmbeddingRecord,
+    CacheNamespace, NamespaceQuota, NamespaceStats, DEFAULT_NAMESPACE,
+    CacheListener, CacheEvent, NeighborProvider, CacheLoader, LoadSignal, PrefetchThrottle,
+    EvictionPolicy, LruPolicy, LfuPolicy, CostWeightedPolicy,
+};
+pub use cache_backend::{CacheBackend, InMemoryBackend, RedisBackend, FileBackend};
+pub use cache_invalidation::{InvalidationBus, InvalidationMessage, InvalidationTarget, RedisStreamBus};
+pub use cache_metrics::{PrefixStats, PrefetchStats, LatencyHistogram};
+pub use cache_bloom::{BloomConfig, BloomStats};
+pub use cache_wal::{WriteAheadLog, WalConfig, WalOp};
+pub use cache_decisions::{CacheDecision, DecisionKind, DecisionReason};
+pub use clock::{Clock, IdGenerator, SystemClock, ManualClock, UuidGenerator, SequentialIds};
+pub use contention::{ContentionProfile, WaitStats};
+pub use freshness::{VertexVersions, StalenessWarning};
+pub use generate_code::{CodeGenerator, GeneratedCode, CodeTemplate, CodeLlmBackend, CodeChunk, TemplateVersion, TemplateTranslation};
+pub use code_constraints::{GenerationConstraints, ConstraintViolation, ErrorHandling};
+pub use code_docs::{CodeDocs, FunctionDoc};
+pub use code_format::{CodeFormatter, CommandFormatter};
+pub use code_patch::{CodePatch, CodeEdit};
+pub use code_safety::{CodeConstruct, SafetyAnalysis};
+pub use language::{detect_language, resolve_response_language};
+pub use prompt_lint::{PromptLinter, PromptLintFinding, PromptRisk};
+pub use template_match::TemplateMatch;
+pub use template_pack::{TemplatePack, PackedTemplate, PackFormat};
@@ -0,0 +1,158 @@
+// -*- coding: utf-8 -*-
+//! Reasoning Pipelines
+//!
+//! The sequence of steps a reasoning chain runs: the built-in retrieval,
+//! hypothesis, inference, aggregation and verification steps, plus steps
+//! supplied by a `ReasoningStepProvider` such as tool calls, re-ranking or
+//! domain validation. Each step reads the previous step's output.
+
+use crate::error::Result;
+use crate::level4::agents::classification::QueryType;
+use crate::level4::agents::reasoning::ReasoningStep;
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a step provider sees of the chain it is extending
+#[derive(Debug)]
+pub struct StepContext<'a> {
+    pub query: &'a str,
+    pub query_type: &'a QueryType,
+    /// Output of the previous step, or the query for the first step
+    pub input: &'a str,
+    pub steps: &'a [ReasoningStep],
+    pub response_language: &'a str,
+}
+
+/// Source of a custom reasoning step
+#[async_trait]
+pub trait ReasoningStepProvider: Send + Sync {
+    /// Name the step is placed by in a pipeline; distinct from the built-in
+    /// step names
+    fn name(&self) -> &str;
+
+    /// The step to add, or `None` to skip it and pass the input on unchanged
+    ///
+    /// The step id is assigned by the chain.
+    async fn run(&self, context: &StepContext<'_>) -> Result<Option<ReasoningStep>>;
+}
+
+#[derive(Clone)]
+pub enum PipelineStage {
+    Retrieval,
+    /// Skipped without a link predictor, or when it predicts nothing
+    Hypothesis,
+    Inference,
+    Aggregation,
+    /// Skipped when verification is disabled
+    Verification,
+    Custom(Arc<dyn ReasoningStepProvider>),
+}
+
+impl PipelineStage {
+    pub fn name(&self) -> &str {
+        match self {
+            PipelineStage::Retrieval => "retrieval",
+            PipelineStage::Hypothesis => "hypothesis",
+            PipelineStage::Inference => "inference",
+            PipelineStage::Aggregation => "aggregation",
+            PipelineStage::Verification => "verification",
+            PipelineStage::Custom(provider) => provider.name(),
+        }
+    }
+}
+
+impl fmt::Debug for PipelineStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineStage::Custom(provider) => write!(f, "Custom({})", provider.name()),
+            stage => f.write_str(stage.name()),
+        }
+    }
+}
+
+/// Ordered stages of a reasoning chain
+#[derive(Debug, Clone)]
+pub struct ReasoningPipeline {
+    stages: Vec<PipelineStage>,
+}
+
+impl Default for ReasoningPipeline {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ReasoningPipeline {
+    pub fn new(stages: Vec<PipelineStage>) -> Self {
+        Self { stages }
+    }
+
+    /// Retrieval, hypothesis, inference, aggregation, verification
+    pub fn standard() -> Self {
+        Self::new(vec![
+            PipelineStage::Retrieval,
+            PipelineStage::Hypothesis,
+            PipelineStage::Inference,
+            PipelineStage::Aggregation,
+            PipelineStage::Verification,
+        ])
+    }
+
+    pub fn stages(&self) -> &[PipelineStage] {
+        &self.stages
+    }
+
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(PipelineStage::name).collect()
+    }
+
+    /// Add `stage` before the first stage called `name`, or at the end if
+    /// there is none
+    pub fn insert_before(mut self, name: &str, stage: PipelineStage) -> Self {
+        let at = self.position(name).unwrap_or(self.stages.len());
+        self.stages.insert(at, stage);
+        self
+    }
+
+    /// Add `stage` after the first stage called `name`, or at the end if
+    /// there is none
+    pub fn insert_after(mut self, name: &str, stage: PipelineStage) -> Self {
+        let at = self.position(name).map_or(self.stages.len(), |at| at + 1);
+        self.stages.insert(at, stage);
+        self
+    }
+
+    /// Add `stage` at the end
+    pub fn then(mut self, stage: PipelineStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Drop every stage called `name`
+    pub fn without(mut self, name: &str) -> Self {
+        self.stages.retain(|stage| stage.name() != name);
+        self
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_editing() {
+        let pipeline = ReasoningPipeline::standard()
+            .without("hypothesis")
+            .insert_before("retrieval", PipelineStage::Verification)
+            .insert_after("missing", PipelineStage::Aggregation);
+        assert_eq!(
+            pipeline.stage_names(),
+            vec!["verification", "retrieval", "inference", "aggregation", "verification", "aggregation"],
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// -*- coding: utf-8 -*-
+//! Template Matching
+//!
+//! Keyword scoring of code templates against a natural-language
+//! description, over each template's id, name, tags and description.
+
+use crate::level4::agents::generate_code::CodeTemplate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Words that say what to do rather than what to build
+const FILLER: &[&str] = &[
+    "a", "an", "the", "for", "to", "of", "in", "on", "with", "and", "or", "that", "which",
+    "me", "please", "some", "using", "implement", "write", "generate", "create", "make",
+    "code", "function", "program", "script",
+];
+
+/// Score above which a template match is taken over the fallback
+pub const DEFAULT_MATCH_THRESHOLD: f64 = 0.65;
+
+/// Score given when the template's name appears verbatim in the description
+const NAME_PHRASE_SCORE: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateMatch {
+    pub template_id: String,
+    /// Confidence in `0.0..=1.0`
+    pub score: f64,
+}
+
+/// How well `template` fits `description`
+///
+/// The share of the description's keywords the template covers, scaled by
+/// the share of the template's own keywords (id, name and tags) the
+/// description mentions, so a single shared word is a weak match.
+/// Description words count half. A description containing the template's
+/// name as a phrase scores at least `0.9`.
+pub fn score(template: &CodeTemplate, description: &str) -> f64 {
+    let query = keywords(description);
+    if query.is_empty() {
+        return 0.0;
+    }
+    let mut key_text = format!("{} {}", template.template_id, template.name);
+    for tag in &template.tags {
+        key_text.push(' ');
+        key_text.push_str(tag);
+    }
+    let keys = keywords(&key_text);
+    let described = keywords(&template.description);
+
+    let covered: f64 = query.iter()
+        .map(|word| if keys.contains(word) { 1.0 } else if described.contains(word) { 0.5 } else { 0.0 })
+        .sum();
+    let query_coverage = covered / query.len() as f64;
+    let key_coverage = if keys.is_empty() {
+        0.0
+    } else {
+        query.intersection(&keys).count() as f64 / keys.len() as f64
+    };
+    let score = query_coverage * (0.5 + 0.5 * key_coverage);
+
+    let name = template.name.to_lowercase();
+    if !name.trim().is_empty() && description.to_lowercase().contains(&name) {
+        score.max(NAME_PHRASE_SCORE)
+    } else {
+        score
+    }
+}
+
+/// Lowercased words of `text` without filler, with a plural `s` dropped
+fn keywords(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !FILLER.contains(word))
+        .map(|word| match word.strip_suffix('s') {
+            Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+            _ => word.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::generate_code::CodeGenerator;
+
+    #[test]
+    fn test_scores_rank_relevant_templates() {
+        let generator = CodeGenerator::new();
+        let search = generator.get_template("binary_search").unwrap();
+        let bfs = generator.get_template("graph_bfs").unwrap();
+
+        assert!(score(search, "implement binary search") >= NAME_PHRASE_SCORE);
+        assert!(score(search, "find an index in sorted arrays") > DEFAULT_MATCH_THRESHOLD);
+        assert!(score(bfs, "breadth-first traversal of a graph") > score(search, "breadth-first traversal of a graph"));
+        // One shared word is not enough
+        assert!(score(bfs, "graph") < DEFAULT_MATCH_THRESHOLD);
+        assert_eq!(score(search, "fibonacci numbers"), 0.0);
+        assert_eq!(score(search, "write a function"), 0.0);
+    }
+}
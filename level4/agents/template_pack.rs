@@ -0,0 +1,214 @@
+// -*- coding: utf-8 -*-
+//! Template Packs
+//!
+//! A single JSON or TOML document holding a set of code templates with
+//! pack metadata and a checksum per template, for sharing curated template
+//! libraries between deployments.
+//!
+//! Templates use the same fields as template definition files.
+
+use crate::error::{Error, Result};
+use crate::level4::agents::clock::Clock;
+use crate::level4::agents::generate_code::{CodeTemplate, TemplateDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+
+/// Pack layout written by this version; packs from newer layouts are refused
+pub const PACK_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackFormat {
+    Json,
+    Toml,
+}
+
+impl PackFormat {
+    /// Format for a file extension, e.g. `json` or `toml`
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(PackFormat::Json),
+            "toml" => Some(PackFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePack {
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Seconds since the Unix epoch
+    #[serde(default)]
+    pub exported_at: u64,
+    /// Fence tags of the languages the templates are written in, sorted
+    pub languages: Vec<String>,
+    pub templates: Vec<PackedTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedTemplate {
+    /// FNV-1a over the template's fields, see `checksum`
+    pub checksum: String,
+    #[serde(flatten)]
+    pub(crate) definition: TemplateDefinition,
+}
+
+impl TemplatePack {
+    /// Pack of `templates`, sorted by id, exported at `clock`'s time
+    pub fn new<'a>(name: &str, clock: &dyn Clock, templates: impl IntoIterator<Item = &'a CodeTemplate>) -> Self {
+        let mut templates: Vec<PackedTemplate> = templates.into_iter()
+            .map(|template| {
+                let definition = TemplateDefinition::from_template(template);
+                PackedTemplate {
+                    checksum: checksum(&definition),
+                    definition,
+                }
+            })
+            .collect();
+        templates.sort_by(|a, b| a.definition.id.cmp(&b.definition.id));
+        let languages: BTreeSet<String> = templates.iter().map(|t| t.definition.language.clone()).collect();
+        Self {
+            format_version: PACK_FORMAT_VERSION,
+            name: name.to_string(),
+            description: String::new(),
+            exported_at: clock.now_secs(),
+            languages: languages.into_iter().collect(),
+            templates,
+        }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn to_string(&self, format: PackFormat) -> Result<String> {
+        match format {
+            PackFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            PackFormat::Toml => toml::to_string(self)
+                .map_err(|e| Error::CodeGeneration(format!("failed to write template pack: {}", e))),
+        }
+    }
+
+    pub fn parse(text: &str, format: PackFormat) -> Result<Self> {
+        let pack: Self = match format {
+            PackFormat::Json => serde_json::from_str(text)
+                .map_err(|e| Error::CodeGeneration(format!("invalid template pack: {}", e)))?,
+            PackFormat::Toml => toml::from_str(text)
+                .map_err(|e| Error::CodeGeneration(format!("invalid template pack: {}", e)))?,
+        };
+        if pack.format_version > PACK_FORMAT_VERSION {
+            return Err(Error::CodeGeneration(format!(
+                "template pack '{}' uses format version {}, newer than {}",
+                pack.name, pack.format_version, PACK_FORMAT_VERSION,
+            )));
+        }
+        Ok(pack)
+    }
+
+    /// The pack's templates, after checking every checksum, language tag
+    /// and template, and that no id appears twice
+    pub fn into_templates(self) -> Result<Vec<CodeTemplate>> {
+        let pack_error = |problem: String| Error::CodeGeneration(format!("template pack '{}': {}", self.name, problem));
+        let mut seen = HashSet::new();
+        let mut templates = Vec::with_capacity(self.templates.len());
+        for packed in &self.templates {
+            let definition = &packed.definition;
+            if checksum(definition) != packed.checksum {
+                return Err(pack_error(format!("checksum mismatch for template '{}'", definition.id)));
+            }
+            if !self.languages.contains(&definition.language) {
+                return Err(pack_error(format!(
+                    "template '{}' is in '{}', which the pack does not list",
+                    definition.id, definition.language,
+                )));
+            }
+            if !seen.insert(definition.id.as_str()) {
+                return Err(pack_error(format!("template '{}' appears twice", definition.id)));
+            }
+            templates.push(definition.clone().into_template()?);
+        }
+        Ok(templates)
+    }
+}
+
+/// FNV-1a over every field of `definition`, which is stable across builds,
+/// unlike `DefaultHasher`; each field ends in a zero byte so adjacent
+/// fields cannot run together
+fn checksum(definition: &TemplateDefinition) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut feed = |text: &str| {
+        for byte in text.as_bytes().iter().chain(std::iter::once(&0u8)) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+    feed(&definition.id);
+    feed(definition.name.as_deref().unwrap_or(&definition.id));
+    feed(&definition.language);
+    feed(&definition.code);
+    feed(&definition.description);
+    feed(&definition.version.to_string());
+    for list in [&definition.placeholders, &definition.tags, &definition.dependencies] {
+        feed(&list.len().to_string());
+        list.iter().for_each(|item| feed(item));
+    }
+    feed(&definition.defaults.len().to_string());
+    for (name, value) in &definition.defaults {
+        feed(name);
+        feed(value);
+    }
+    // Left out when empty, so packs without translations keep their checksums
+    if !definition.translations.is_empty() {
+        feed(&definition.translations.len().to_string());
+        for (language, code) in &definition.translations {
+            feed(language);
+            feed(code);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::clock::ManualClock;
+    use crate::level4::agents::generate_code::CodeGenerator;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pack_roundtrip_and_tampering() {
+        let generator = CodeGenerator::new();
+        let clock = ManualClock::new(Duration::from_secs(1_700_000_000));
+        let pack = TemplatePack::new("builtins", &clock, generator.get_template("rhai_calculator"));
+        assert_eq!(pack.languages, vec!["rhai"]);
+        assert_eq!(pack.exported_at, 1_700_000_000);
+
+        for format in [PackFormat::Json, PackFormat::Toml] {
+            let text = pack.to_string(format).unwrap();
+            let templates = TemplatePack::parse(&text, format).unwrap().into_templates().unwrap();
+            assert_eq!(templates.len(), 1);
+            assert_eq!(templates[0].template_code, generator.get_template("rhai_calculator").unwrap().template_code);
+            assert_eq!(templates[0].defaults["operation"], "add");
+            assert_eq!(templates[0].translations, generator.get_template("rhai_calculator").unwrap().translations);
+        }
+
+        let mut tampered = pack.clone();
+        tampered.templates[0].definition.code.push_str("\nsystem(\"rm -rf /\")");
+        assert!(tampered.into_templates().is_err());
+        let mut tampered = pack.clone();
+        tampered.templates[0].definition.translations.insert("rust".to_string(), "fn main() {}".to_string());
+        assert!(tampered.into_templates().is_err());
+
+        let mut future = pack;
+        future.format_version = PACK_FORMAT_VERSION + 1;
+        let text = future.to_string(PackFormat::Json).unwrap();
+        assert!(TemplatePack::parse(&text, PackFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+// -*- coding: utf-8 -*-
+//! Test-Case Synthesis
+//!
+//! Edge-case inputs derived from the signatures of generated functions: for
+//! each parameter, the values its type is most likely to mishandle, with the
+//! other parameters held at ordinary values.
+//!
+//! Rust signatures are parsed with `syn`; Python, JavaScript and Rhai
+//! signatures are read from their `def`/`function`/`fn` lines, using Python
+//! annotations where present.
+
+use crate::level4::agents::generate_code::{ProgrammingLanguage, TestCase};
+
+/// `expected_output` of synthesized cases, which only check that the code
+/// handles the input without panicking or raising
+pub const EXPECT_NO_PANIC: &str = "<no panic>";
+
+/// Upper bound on synthesized cases per piece of code
+pub const MAX_SYNTHESIZED: usize = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ParamKind {
+    /// Integer type, by name, e.g. `i32`
+    Int(String),
+    Float,
+    Str,
+    Char,
+    Bool,
+    Seq(Box<ParamKind>),
+    Option(Box<ParamKind>),
+    Map(Box<ParamKind>, Box<ParamKind>),
+    /// Parameter of a dynamically typed language with no annotation
+    Dynamic,
+    Unknown,
+}
+
+impl ParamKind {
+    /// Ordinary value used while another parameter takes its edge values,
+    /// as a `language` literal
+    pub(crate) fn typical(&self, language: &ProgrammingLanguage) -> String {
+        let rust = *language == ProgrammingLanguage::Rust;
+        match self {
+            ParamKind::Int(_) | ParamKind::Dynamic => "3".to_string(),
+            ParamKind::Float => "2.5".to_string(),
+            ParamKind::Str => "\"abc\"".to_string(),
+            ParamKind::Char => "'a'".to_string(),
+            ParamKind::Bool => boolean(true, language),
+            ParamKind::Seq(item) => format!("[{}]", item.samples(language).join(", ")),
+            ParamKind::Option(inner) if rust => format!("Some({})", inner.typical(language)),
+            // Scripts pass optional values bare
+            ParamKind::Option(inner) => inner.typical(language),
+            ParamKind::Map(key, value) => {
+                let open = if *language == ProgrammingLanguage::Rhai { "#{" } else { "{" };
+                format!("{}{}: {}}}", open, key.typical(language), value.typical(language))
+            }
+            ParamKind::Unknown if rust => "Default::default()".to_string(),
+            ParamKind::Unknown => none(language),
+        }
+    }
+
+    /// Three distinct, ascending values where the type allows
+    fn samples(&self, language: &ProgrammingLanguage) -> Vec<String> {
+        match self {
+            ParamKind::Int(_) | ParamKind::Dynamic => vec!["1", "2", "3"],
+            ParamKind::Float => vec!["0.5", "1.5", "2.5"],
+            ParamKind::Str => vec!["\"a\"", "\"b\"", "\"c\""],
+            ParamKind::Char => vec!["'a'", "'b'", "'c'"],
+            ParamKind::Bool => return vec![boolean(false, language), boolean(true, language)],
+            _ => return vec![self.typical(language)],
+        }
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    /// Edge values, as `language` literals, with a short description of each
+    fn edges(&self, language: &ProgrammingLanguage) -> Vec<(String, String)> {
+        let edges: Vec<(String, &str)> = match self {
+            ParamKind::Int(name) => {
+                let mut edges = vec![("0".to_string(), "zero")];
+                let (min, max) = int_bounds(name, language);
+                if let Some(min) = min {
+                    edges.push(("-1".to_string(), "negative"));
+                    edges.push((min, "minimum"));
+                }
+                edges.push((max, "maximum"));
+                edges
+            }
+            ParamKind::Float => {
+                let mut edges = vec![("0.0".to_string(), "zero"), ("-1.5".to_string(), "negative")];
+                if let Some(nan) = nan(language) {
+                    edges.push((nan.to_string(), "NaN"));
+                }
+                edges
+            }
+            ParamKind::Str => vec![
+                ("\"\"".to_string(), "empty string"),
+                ("\"héllo wörld 🌍\"".to_string(), "unicode string"),
+            ],
+            ParamKind::Char => vec![("'é'".to_string(), "non-ASCII char")],
+            ParamKind::Bool => vec![(boolean(false, language), "false")],
+            ParamKind::Seq(item) => vec![
+                ("[]".to_string(), "empty"),
+                (format!("[{}]", item.typical(language)), "single element"),
+            ],
+            ParamKind::Option(_) => vec![(none(language), "absent")],
+            ParamKind::Map(..) if *language == ProgrammingLanguage::Rhai => vec![("#{}".to_string(), "empty map")],
+            ParamKind::Map(..) => vec![("{}".to_string(), "empty map")],
+            ParamKind::Dynamic => vec![
+                ("0".to_string(), "zero"),
+                ("-1".to_string(), "negative"),
+                ("\"\"".to_string(), "empty string"),
+                ("[]".to_string(), "empty array"),
+            ],
+            ParamKind::Unknown => vec![],
+        };
+        edges.into_iter().map(|(value, what)| (value, what.to_string())).collect()
+    }
+}
+
+fn boolean(value: bool, language: &ProgrammingLanguage) -> String {
+    match (language, value) {
+        (ProgrammingLanguage::Python, true) => "True".to_string(),
+        (ProgrammingLanguage::Python, false) => "False".to_string(),
+        (_, value) => value.to_string(),
+    }
+}
+
+fn none(language: &ProgrammingLanguage) -> String {
+    match language {
+        ProgrammingLanguage::Rust | ProgrammingLanguage::Python => "None",
+        ProgrammingLanguage::JavaScript => "null",
+        ProgrammingLanguage::Rhai => "()",
+    }
+    .to_string()
+}
+
+/// Not-a-number, where the language has a literal for it
+fn nan(language: &ProgrammingLanguage) -> Option<&'static str> {
+    match language {
+        ProgrammingLanguage::Rust => Some("f64::NAN"),
+        ProgrammingLanguage::Python => Some("float(\"nan\")"),
+        ProgrammingLanguage::JavaScript => Some("NaN"),
+        ProgrammingLanguage::Rhai => None,
+    }
+}
+
+/// Smallest and largest value of integer type `name`; no smallest for
+/// unsigned types. Script integers are bounded at 64 bits, or at the safe
+/// integer range in JavaScript.
+fn int_bounds(name: &str, language: &ProgrammingLanguage) -> (Option<String>, String) {
+    match language {
+        ProgrammingLanguage::Rust => (
+            name.starts_with('i').then(|| format!("{}::MIN", name)),
+            format!("{}::MAX", name),
+        ),
+        ProgrammingLanguage::JavaScript => (
+            Some("Number.MIN_SAFE_INTEGER".to_string()),
+            "Number.MAX_SAFE_INTEGER".to_string(),
+        ),
+        _ => (Some(i64::MIN.to_string()), i64::MAX.to_string()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Signature {
+    pub(crate) name: String,
+    pub(crate) params: Vec<(String, ParamKind)>,
+}
+
+/// Signatures of the functions declared in `code`, in order
+pub(crate) fn signatures(code: &str, language: &ProgrammingLanguage) -> Vec<Signature> {
+    match language {
+        ProgrammingLanguage::Rust => rust_signatures(code),
+        _ => script_signatures(code, language),
+    }
+}
+
+/// Edge-case test cases for the functions declared in `code`
+///
+/// `main` and functions without parameters are skipped.
+pub fn synthesize(code: &str, language: &ProgrammingLanguage) -> Vec<TestCase> {
+    let signatures = signatures(code, language);
+
+    let mut cases = Vec::new();
+    for signature in signatures.iter().filter(|s| s.name != "main" && !s.params.is_empty()) {
+        for (index, (param, kind)) in signature.params.iter().enumerate() {
+            for (value, what) in kind.edges(language) {
+                let input: Vec<String> = signature.params.iter().enumerate()
+                    .map(|(i, (name, kind))| {
+                        let value = if i == index { value.clone() } else { kind.typical(language) };
+                        format!("{}={}", name, value)
+                    })
+                    .collect();
+                cases.push(TestCase {
+                    input: input.join(", "),
+                    expected_output: EXPECT_NO_PANIC.to_string(),
+                    description: format!("{}: {} {}", signature.name, param, what),
+                });
+            }
+        }
+    }
+    cases.truncate(MAX_SYNTHESIZED);
+    cases
+}
+
+fn rust_signatures(code: &str) -> Vec<Signature> {
+    let Ok(file) = syn::parse_file(code) else {
+        return Vec::new();
+    };
+    file.items.iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(function) => Some(&function.sig),
+            _ => None,
+        })
+        .map(|sig| {
+            let generics: Vec<String> = sig.generics.type_params().map(|p| p.ident.to_string()).collect();
+            let params = sig.inputs.iter()
+                .filter_map(|input| match input {
+                    syn::FnArg::Typed(typed) => Some(typed),
+                    syn::FnArg::Receiver(_) => None,
+                })
+                .map(|typed| {
+                    let name = match &*typed.pat {
+                        syn::Pat::Ident(ident) => ident.ident.to_string(),
+                        _ => "_".to_string(),
+                    };
+                    (name, rust_kind(&typed.ty, &generics))
+                })
+                .collect();
+            Signature {
+                name: sig.ident.to_string(),
+                params,
+            }
+        })
+        .collect()
+}
+
+fn rust_kind(ty: &syn::Type, generics: &[String]) -> ParamKind {
+    match ty {
+        syn::Type::Reference(reference) => rust_kind(&reference.elem, generics),
+        syn::Type::Slice(slice) => ParamKind::Seq(Box::new(rust_kind(&slice.elem, generics))),
+        syn::Type::Array(array) => ParamKind::Seq(Box::new(rust_kind(&array.elem, generics))),
+        syn::Type::Path(path) => {
+            let Some(segment) = path.path.segments.last() else {
+                return ParamKind::Unknown;
+            };
+            let args: Vec<ParamKind> = match &segment.arguments {
+                syn::PathArguments::AngleBracketed(angle) => angle.args.iter()
+                    .filter_map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => Some(rust_kind(ty, generics)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let arg = |i: usize| Box::new(args.get(i).cloned().unwrap_or(ParamKind::Unknown));
+            let name = segment.ident.to_string();
+            match name.as_str() {
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize"
+                | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => ParamKind::Int(name),
+                "f32" | "f64" => ParamKind::Float,
+                "str" | "String" => ParamKind::Str,
+                "char" => ParamKind::Char,
+                "bool" => ParamKind::Bool,
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => ParamKind::Seq(arg(0)),
+                "Option" => ParamKind::Option(arg(0)),
+                "HashMap" | "BTreeMap" => ParamKind::Map(arg(0), arg(1)),
+                "Box" | "Rc" | "Arc" => *arg(0),
+                // Generic parameters are most often numeric, e.g. `T: Ord`
+                _ if generics.contains(&name) => ParamKind::Int("i32".to_string()),
+                _ => ParamKind::Unknown,
+            }
+        }
+        _ => ParamKind::Unknown,
+    }
+}
+
+fn script_signatures(code: &str, language: &ProgrammingLanguage) -> Vec<Signature> {
+    let keyword = match language {
+        ProgrammingLanguage::Python => "def ",
+        ProgrammingLanguage::JavaScript => "function ",
+        _ => "fn ",
+    };
+    code.lines()
+        .filter_map(|line| {
+            let declaration = line.trim_start().strip_prefix("private ").unwrap_or(line.trim_start());
+            let rest = declaration.strip_prefix(keyword)?;
+            let (name, rest) = rest.split_once('(')?;
+            let (params, _) = rest.split_once(')')?;
+            let params = params.split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty() && *p != "self")
+                .map(|param| script_param(param, language))
+                .collect();
+            Some(Signature {
+                name: name.trim().to_string(),
+                params,
+            })
+        })
+        .collect()
+}
+
+fn script_param(param: &str, language: &ProgrammingLanguage) -> (String, ParamKind) {
+    // Defaults do not change the type we test with
+    let param = param.split('=').next().unwrap_or(param).trim();
+    let Some((name, annotation)) = param.split_once(':').filter(|_| *language == ProgrammingLanguage::Python) else {
+        return (param.to_string(), ParamKind::Dynamic);
+    };
+    let annotation = annotation.trim();
+    let base = annotation.split('[').next().unwrap_or(annotation).trim();
+    let kind = match base {
+        "int" => ParamKind::Int("int".to_string()),
+        "float" => ParamKind::Float,
+        "str" => ParamKind::Str,
+        "bool" => ParamKind::Bool,
+        "list" | "List" | "Sequence" | "tuple" | "set" => ParamKind::Seq(Box::new(ParamKind::Dynamic)),
+        "dict" | "Dict" | "Mapping" => ParamKind::Map(Box::new(ParamKind::Str), Box::new(ParamKind::Dynamic)),
+        "Optional" => ParamKind::Option(Box::new(ParamKind::Dynamic)),
+        _ => ParamKind::Dynamic,
+    };
+    (name.trim().to_string(), kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_edges_follow_parameter_types() {
+        let code = "fn binary_search<T: Ord>(arr: &[T], target: &T) -> Option<usize> { None }\n\
+                    fn greet(name: &str, times: u8) {}";
+        let cases = synthesize(code, &ProgrammingLanguage::Rust);
+        let inputs: Vec<&str> = cases.iter().map(|c| c.input.as_str()).collect();
+
+        assert!(inputs.contains(&"arr=[], target=3"));
+        assert!(inputs.contains(&"arr=[3], target=3"));
+        assert!(inputs.contains(&"arr=[1, 2, 3], target=-1"));
+        assert!(inputs.contains(&"name=\"\", times=3"));
+        assert!(inputs.contains(&"name=\"héllo wörld 🌍\", times=3"));
+        assert!(inputs.contains(&"name=\"abc\", times=u8::MAX"));
+        // Unsigned types have no negative edge
+        assert!(!inputs.contains(&"name=\"abc\", times=-1"));
+        assert!(cases.iter().all(|c| c.expected_output == EXPECT_NO_PANIC));
+        assert_eq!(cases[0].description, "binary_search: arr empty");
+    }
+
+    #[test]
+    fn test_script_signatures() {
+        let python = "def mean(xs: list[float], strict=False):\n    return sum(xs) / len(xs)\n";
+        let cases = synthesize(python, &ProgrammingLanguage::Python);
+        assert_eq!(cases[0].input, "xs=[], strict=3");
+
+        let rhai = "fn calculate(operation, a, b) {\n    a + b\n}\ncalculate(\"add\", 5, 3)";
+        let cases = synthesize(rhai, &ProgrammingLanguage::Rhai);
+        assert_eq!(cases.len(), 12);
+        assert!(cases.iter().any(|c| c.input == "operation=3, a=3, b=\"\""));
+    }
+
+    #[test]
+    fn test_python_edges_are_python_literals() {
+        let python = "def scale(x: int, factor: float, strict: bool, limit: Optional[int]):\n    pass\n";
+        let cases = synthesize(python, &ProgrammingLanguage::Python);
+        let inputs: Vec<&str> = cases.iter().map(|c| c.input.as_str()).collect();
+
+        assert!(inputs.contains(&"x=0, factor=2.5, strict=True, limit=3"));
+        assert!(inputs.contains(&"x=-9223372036854775808, factor=2.5, strict=True, limit=3"));
+        assert!(inputs.contains(&"x=3, factor=float(\"nan\"), strict=True, limit=3"));
+        assert!(inputs.contains(&"x=3, factor=2.5, strict=False, limit=3"));
+        assert!(inputs.contains(&"x=3, factor=2.5, strict=True, limit=None"));
+        for rust_only in ["::", "true", "false", "Some("] {
+            assert!(inputs.iter().all(|input| !input.contains(rust_only)), "{}", rust_only);
+        }
+    }
+}
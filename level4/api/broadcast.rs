@@ -0,0 +1,163 @@
+// -*- coding: utf-8 -*-
+//! Stream Broadcast
+//!
+//! Fan-out of one in-flight stream to any number of subscribers.
+
+use crate::level4::api::stream::StreamChunk;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+
+/// Broadcast buffering settings
+#[derive(Debug, Clone, Copy)]
+pub struct BroadcastConfig {
+    /// Chunks kept for late joiners; `None` keeps the whole stream
+    pub history_limit: Option<usize>,
+    /// Per-subscriber channel capacity
+    pub subscriber_buffer: usize,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            history_limit: Some(256),
+            subscriber_buffer: 100,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BroadcastState {
+    history: VecDeque<StreamChunk>,
+    /// Stream offset of `history[0]`
+    first_offset: usize,
+    done: bool,
+}
+
+/// One source stream shared by many receivers
+///
+/// Each subscriber reads at its own offset, starting from the oldest chunk
+/// still buffered. A subscriber that falls behind the history window skips
+/// ahead to it. The source is drained to the end even with no subscribers,
+/// so observers can attach at any point.
+#[derive(Clone)]
+pub struct StreamBroadcast {
+    state: Arc<watch::Sender<BroadcastState>>,
+    config: BroadcastConfig,
+}
+
+impl StreamBroadcast {
+    pub fn new(mut source: mpsc::Receiver<StreamChunk>, config: BroadcastConfig) -> Self {
+        let state = Arc::new(watch::channel(BroadcastState::default()).0);
+        let pump = state.clone();
+
+        tokio::spawn(async move {
+            while let Some(chunk) = source.recv().await {
+                let is_final = chunk.is_final;
+                // The final chunk and `done` land together, so `is_finished`
+                // turns true exactly when subscribers can see the end
+                pump.send_modify(|state| {
+                    state.history.push_back(chunk);
+                    if let Some(limit) = config.history_limit {
+                        while state.history.len() > limit.max(1) {
+                            state.history.pop_front();
+                            state.first_offset += 1;
+                        }
+                    }
+                    state.done = is_final;
+                });
+                if is_final {
+                    return;
+                }
+            }
+            pump.send_modify(|state| state.done = true);
+        });
+
+        Self { state, config }
+    }
+
+    /// Receive the buffered history, then the live stream
+    pub fn subscribe(&self) -> mpsc::Receiver<StreamChunk> {
+        let mut state = self.state.subscribe();
+        let (tx, rx) = mpsc::channel(self.config.subscriber_buffer.max(1));
+
+        tokio::spawn(async move {
+            let mut offset = 0;
+            loop {
+                let (pending, done) = {
+                    let state = state.borrow_and_update();
+                    if offset < state.first_offset {
+                        tracing::debug!("Broadcast subscriber skipped {} chunks", state.first_offset - offset);
+                        offset = state.first_offset;
+                    }
+                    let start = offset - state.first_offset;
+                    (state.history.range(start..).cloned().collect::<Vec<_>>(), state.done)
+                };
+                offset += pending.len();
+                for chunk in pending {
+                    if tx.send(chunk).await.is_err() {
+                        return; // Receiver dropped
+                    }
+                }
+                if done || state.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Receivers currently attached, including ones not yet caught up
+    pub fn subscriber_count(&self) -> usize {
+        self.state.receiver_count()
+    }
+
+    /// Whether the source has ended; new subscribers then only replay history
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::api::stream::ChunkMetadata;
+
+    fn chunk(chunk_id: usize, is_final: bool) -> StreamChunk {
+        StreamChunk {
+            chunk_id,
+            content: chunk_id.to_string(),
+            is_final,
+            metadata: ChunkMetadata::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_joiner_gets_bounded_history() {
+        let (tx, rx) = mpsc::channel(10);
+        let broadcast = StreamBroadcast::new(rx, BroadcastConfig {
+            history_limit: Some(2),
+            ..BroadcastConfig::default()
+        });
+        let mut early = broadcast.subscribe();
+
+        for i in 0..5 {
+            tx.send(chunk(i, i == 4)).await.unwrap();
+        }
+        let mut early_ids = Vec::new();
+        while let Some(chunk) = early.recv().await {
+            early_ids.push(chunk.chunk_id);
+        }
+        assert!(broadcast.is_finished());
+
+        let mut late = broadcast.subscribe();
+        let mut late_ids = Vec::new();
+        while let Some(chunk) = late.recv().await {
+            late_ids.push(chunk.chunk_id);
+        }
+        assert_eq!(late_ids, vec![3, 4]);
+        // The early subscriber may skip chunks it fell behind on, but always sees the end
+        assert_eq!(early_ids.last(), Some(&4));
+    }
+}
@@ -0,0 +1,132 @@
+// -*- coding: utf-8 -*-
+//! Stream Compression
+//! 
+//! Per-stream compression of chunk payloads, negotiated at stream start.
+
+use crate::error::Result;
+use crate::level4::api::stream::StreamChunk;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Compression codec applied to encoded chunk payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CompressionCodec {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Codec plus level agreed for one stream
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompressionSettings {
+    pub codec: CompressionCodec,
+    /// Codec-specific level (gzip 0-9, zstd 1-22); ignored for `None`
+    pub level: i32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            codec: CompressionCodec::None,
+            level: 3,
+        }
+    }
+}
+
+/// Chunk as sent over the wire
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncodedChunk {
+    pub chunk_id: usize,
+    pub is_final: bool,
+    pub codec: CompressionCodec,
+    /// JSON-serialized `StreamChunk`, compressed with `codec`
+    pub payload: Vec<u8>,
+}
+
+/// Pick the first server-preferred codec the client accepts, falling back to `None`
+pub fn negotiate(
+    server_preference: &[CompressionCodec],
+    client_accepts: &[CompressionCodec],
+) -> CompressionCodec {
+    server_preference.iter()
+        .find(|codec| client_accepts.contains(codec))
+        .copied()
+        .unwrap_or(CompressionCodec::None)
+}
+
+pub fn compress(data: &[u8], settings: CompressionSettings) -> Result<Vec<u8>> {
+    match settings.codec {
+        CompressionCodec::None => Ok(data.to_vec()),
+        CompressionCodec::Gzip => {
+            let level = flate2::Compression::new(settings.level.clamp(0, 9) as u32);
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        CompressionCodec::Zstd => Ok(zstd::encode_all(data, settings.level)?),
+    }
+}
+
+pub fn decompress(data: &[u8], codec: CompressionCodec) -> Result<Vec<u8>> {
+    match codec {
+        CompressionCodec::None => Ok(data.to_vec()),
+        CompressionCodec::Gzip => {
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(data).read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }
+        CompressionCodec::Zstd => Ok(zstd::decode_all(data)?),
+    }
+}
+
+pub fn encode_chunk(chunk: &StreamChunk, settings: CompressionSettings) -> Result<EncodedChunk> {
+    let json = serde_json::to_vec(chunk)?;
+
+    Ok(EncodedChunk {
+        chunk_id: chunk.chunk_id,
+        is_final: chunk.is_final,
+        codec: settings.codec,
+        payload: compress(&json, settings)?,
+    })
+}
+
+pub fn decode_chunk(encoded: &EncodedChunk) -> Result<StreamChunk> {
+    let json = decompress(&encoded.payload, encoded.codec)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::api::stream::ChunkMetadata;
+
+    #[test]
+    fn test_negotiate_prefers_server_order() {
+        let server = [CompressionCodec::Zstd, CompressionCodec::Gzip];
+        
+        assert_eq!(negotiate(&server, &[CompressionCodec::Gzip, CompressionCodec::Zstd]), CompressionCodec::Zstd);
+        assert_eq!(negotiate(&server, &[CompressionCodec::Gzip]), CompressionCodec::Gzip);
+        assert_eq!(negotiate(&server, &[]), CompressionCodec::None);
+    }
+
+    #[test]
+    fn test_chunk_roundtrip() {
+        let chunk = StreamChunk {
+            chunk_id: 0,
+            content: "Verified: Aggregated result: ".repeat(20),
+            is_final: true,
+            metadata: ChunkMetadata {
+                graph_nodes_accessed: vec!["vertex_0_0".to_string()],
+                confidence: 0.85,
+                progress: 1.0,
+                ..ChunkMetadata::default()
+            },
+        };
+        
+        for codec in [CompressionCodec::None, CompressionCodec::Gzip, CompressionCodec::Zstd] {
+            let encoded = encode_chunk(&chunk, CompressionSettings { codec, level: 3 }).unwrap();
+            assert_eq!(decode_chunk(&encoded).unwrap().content, chunk.content);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// -*- coding: utf-8 -*-
+//! Streaming API
+//! 
+//! Real-time delivery of inference results to clients.
+
+pub mod stream;
+pub mod broadcast;
+pub mod compression;
+pub mod partial;
+pub mod postprocess;
+pub mod safety;
+pub mod shadow;
+
+pub use stream::{
+    StreamingInference, StreamChunk, ChunkMetadata, StreamConfig, StreamStats,
+    StreamProfile, StreamOptions,
+    ProgressModel, ProgressEstimate, StreamCheckpoint, StreamLoad,
+};
+pub use broadcast::{StreamBroadcast, BroadcastConfig};
+pub use compression::{CompressionCodec, CompressionSettings, EncodedChunk};
+pub use partial::{PartialResult, ApiError, ApiErrorKind};
+pub use postprocess::{
+    AnswerPostProcessor, PostProcessStage, PostProcessContext,
+    MarkdownNormalizer, CodeFenceTagger, CitationFootnotes,
+    Translator, ResponseLanguageEnforcer,
+};
+pub use safety::{
+    OutputSafetyFilter, SafetyClassifier, PatternMatcher, SafetyPolicy, SafetyAction,
+    SafetyFinding, SafetyDecision, AuditSink, TracingAuditSink,
+};
+pub use shadow::{ShadowRunner, ShadowSink, ShadowLog, ShadowComparison, ShadowSummary};
@@ -0,0 +1,107 @@
+// -*- coding: utf-8 -*-
+//! Partial Results
+//!
+//! Responses that carry whatever was produced alongside the errors that kept
+//! them from being complete, instead of all-or-nothing `Result`s.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// Stage of a request that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiErrorKind {
+    /// The reasoning chain could not be built, so there is no answer
+    Reasoning,
+    /// Graph or cache access failed; the answer lacks some graph metadata
+    GraphAccess,
+    /// The safety filter cut the answer short
+    PolicyViolation,
+    /// The stream closed before its final chunk
+    Interrupted,
+}
+
+/// Error reported to API clients alongside a partial result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(kind: ApiErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// A value and the errors that left it incomplete
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialResult<T> {
+    pub value: T,
+    /// Whether every stage succeeded; `errors` is empty exactly when this is set
+    pub complete: bool,
+    pub errors: Vec<ApiError>,
+}
+
+impl<T> PartialResult<T> {
+    pub fn complete(value: T) -> Self {
+        Self {
+            value,
+            complete: true,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_errors(value: T, errors: Vec<ApiError>) -> Self {
+        Self {
+            value,
+            complete: errors.is_empty(),
+            errors,
+        }
+    }
+
+    /// Whether any error is of `kind`
+    pub fn has_error(&self, kind: ApiErrorKind) -> bool {
+        self.errors.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> PartialResult<U> {
+        PartialResult {
+            value: f(self.value),
+            complete: self.complete,
+            errors: self.errors,
+        }
+    }
+
+    /// All-or-nothing view for callers that cannot use partial values
+    pub fn into_result(self) -> Result<T> {
+        match self.errors.first() {
+            None => Ok(self.value),
+            Some(e) => Err(Error::Api(format!("{:?}: {}", e.kind, e.message))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completeness_follows_errors() {
+        let full = PartialResult::complete("answer".to_string());
+        assert!(full.complete);
+        assert_eq!(full.clone().into_result().unwrap(), "answer");
+
+        let partial = PartialResult::with_errors(
+            "answer".to_string(),
+            vec![ApiError::new(ApiErrorKind::GraphAccess, "backend down")],
+        ).map(|answer| answer.len());
+        assert!(!partial.complete);
+        assert_eq!(partial.value, 6);
+        assert!(partial.has_error(ApiErrorKind::GraphAccess));
+        assert!(!partial.has_error(ApiErrorKind::Reasoning));
+        assert!(partial.into_result().is_err());
+    }
+}
@@ -0,0 +1,311 @@
+// -*- coding: utf-8 -*-
+//! Answer Post-Processing
+//! 
+//! Formatting stages applied to a chain's final answer before it is chunked.
+
+use crate::level4::agents::generate_code::ProgrammingLanguage;
+use crate::level4::agents::{detect_language, QueryType, ReasoningChain};
+use std::sync::Arc;
+
+/// What stages may consult about the answer they are formatting
+#[derive(Debug, Clone)]
+pub struct PostProcessContext {
+    pub query_type: QueryType,
+    /// Graph nodes the answer was derived from, in first-visit order
+    pub provenance: Vec<String>,
+    /// Language of generated code in the answer, when known
+    pub code_language: Option<ProgrammingLanguage>,
+    /// ISO 639-1 code the answer must be delivered in
+    pub response_language: String,
+}
+
+impl PostProcessContext {
+    pub fn from_chain(chain: &ReasoningChain) -> Self {
+        let mut provenance: Vec<String> = Vec::new();
+        for node in chain.steps.iter().flat_map(|s| &s.graph_nodes_accessed) {
+            if !provenance.contains(node) {
+                provenance.push(node.clone());
+            }
+        }
+        
+        Self {
+            query_type: chain.query_type.clone(),
+            provenance,
+            code_language: code_language(chain),
+            response_language: chain.response_language.clone(),
+        }
+    }
+}
+
+/// Language of the code in a chain's answer: the first tagged fence of the
+/// answer, else a language the query names
+fn code_language(chain: &ReasoningChain) -> Option<ProgrammingLanguage> {
+    chain.final_answer.lines()
+        .filter_map(|line| line.trim_start().strip_prefix("```"))
+        .find_map(|tag| ProgrammingLanguage::from_fence_tag(tag.trim()))
+        .or_else(|| {
+            chain.query.split(|c: char| !c.is_alphanumeric())
+                .find_map(ProgrammingLanguage::from_fence_tag)
+        })
+}
+
+/// Single formatting stage
+pub trait PostProcessStage: Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &str;
+
+    fn process(&self, answer: String, context: &PostProcessContext) -> String;
+}
+
+/// Normalize line endings, trailing whitespace, heading spacing and blank runs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownNormalizer;
+
+impl PostProcessStage for MarkdownNormalizer {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn process(&self, answer: String, _context: &PostProcessContext) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut in_fence = false;
+        
+        for line in answer.replace("\r\n", "\n").lines() {
+            let line = line.trim_end();
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                lines.push(line.to_string());
+                continue;
+            }
+            if in_fence {
+                lines.push(line.to_string());
+                continue;
+            }
+            
+            // Collapse runs of blank lines to one
+            if line.is_empty() && lines.last().map_or(true, |l| l.is_empty()) {
+                continue;
+            }
+            
+            // `#Heading` -> `# Heading`
+            let hashes = line.chars().take_while(|c| *c == '#').count();
+            if (1..=6).contains(&hashes) && line[hashes..].chars().next().is_some_and(|c| c != ' ') {
+                lines.push(format!("{} {}", &line[..hashes], &line[hashes..]));
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+        
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+}
+
+/// Add a language tag to untagged opening code fences
+#[derive(Debug, Clone, Default)]
+pub struct CodeFenceTagger {
+    /// Used when the context does not name a language
+    pub default_language: Option<ProgrammingLanguage>,
+}
+
+impl PostProcessStage for CodeFenceTagger {
+    fn name(&self) -> &str {
+        "code_fence"
+    }
+
+    fn process(&self, answer: String, context: &PostProcessContext) -> String {
+        let language = match context.code_language.as_ref().or(self.default_language.as_ref()) {
+            Some(language) => language,
+            None => return answer,
+        };
+        
+        let mut in_fence = false;
+        answer.lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if !trimmed.starts_with("```") {
+                    return line.to_string();
+                }
+                in_fence = !in_fence;
+                // Only opening fences carry an info string
+                if in_fence && trimmed.trim_end() == "```" {
+                    format!("{}{}", line.trim_end(), language.fence_tag())
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Cite the answer's provenance as footnotes: references after the answer
+/// text, definitions below it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CitationFootnotes;
+
+impl PostProcessStage for CitationFootnotes {
+    fn name(&self) -> &str {
+        "citations"
+    }
+
+    fn process(&self, answer: String, context: &PostProcessContext) -> String {
+        if context.provenance.is_empty() {
+            return answer;
+        }
+        
+        let references: Vec<String> = (1..=context.provenance.len())
+            .map(|n| format!("[^{}]", n))
+            .collect();
+        let footnotes: Vec<String> = context.provenance.iter()
+            .enumerate()
+            .map(|(i, node)| format!("[^{}]: {}", i + 1, node))
+            .collect();
+        
+        // Renderers drop footnotes nothing refers to; references cannot go
+        // on a fence line, so they get a line of their own after code
+        let last_line = answer.lines().last().unwrap_or("").trim();
+        let separator = if last_line.is_empty() || last_line.starts_with("```") { "\n\n" } else { " " };
+        format!("{}{}{}\n\n{}", answer.trim_end(), separator, references.join(" "), footnotes.join("\n"))
+    }
+}
+
+/// Machine translation used by `ResponseLanguageEnforcer`
+pub trait Translator: Send + Sync + std::fmt::Debug {
+    /// Translate `text` into `target_language`, or `None` if unsupported
+    fn translate(&self, text: &str, target_language: &str) -> Option<String>;
+}
+
+/// Translate answers whose detected language differs from the requested one
+///
+/// Not part of the default pipeline since it needs a `Translator`.
+#[derive(Debug, Clone)]
+pub struct ResponseLanguageEnforcer {
+    pub translator: Arc<dyn Translator>,
+}
+
+impl PostProcessStage for ResponseLanguageEnforcer {
+    fn name(&self) -> &str {
+        "response_language"
+    }
+
+    fn process(&self, answer: String, context: &PostProcessContext) -> String {
+        if context.response_language.is_empty() || detect_language(&answer) == context.response_language {
+            return answer;
+        }
+        match self.translator.translate(&answer, &context.response_language) {
+            Some(translated) => translated,
+            None => {
+                tracing::warn!("No translation into {} available; answer left as is", context.response_language);
+                answer
+            }
+        }
+    }
+}
+
+/// Ordered pipeline of post-processing stages
+#[derive(Debug, Clone)]
+pub struct AnswerPostProcessor {
+    stages: Vec<Arc<dyn PostProcessStage>>,
+}
+
+impl Default for AnswerPostProcessor {
+    /// Markdown normalization, fence tagging, then citations
+    fn default() -> Self {
+        Self::new()
+            .with_stage(Arc::new(MarkdownNormalizer))
+            .with_stage(Arc::new(CodeFenceTagger::default()))
+            .with_stage(Arc::new(CitationFootnotes))
+    }
+}
+
+impl AnswerPostProcessor {
+    /// Empty pipeline that passes answers through unchanged
+    pub fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    pub fn with_stage(mut self, stage: Arc<dyn PostProcessStage>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|s| s.name()).collect()
+    }
+
+    pub fn process(&self, answer: String, context: &PostProcessContext) -> String {
+        self.stages.iter()
+            .fold(answer, |answer, stage| stage.process(answer, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::GLMReasoning;
+
+    fn context(code_language: Option<ProgrammingLanguage>) -> PostProcessContext {
+        PostProcessContext {
+            query_type: QueryType::Factual,
+            provenance: vec!["node_0".to_string(), "node_1".to_string()],
+            code_language,
+            response_language: "en".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_default_pipeline() {
+        let processor = AnswerPostProcessor::default();
+        let answer = "#Result  \r\n\r\n\r\n```\nfn main() {}\n```\n".to_string();
+        
+        let processed = processor.process(answer, &context(Some(ProgrammingLanguage::Rust)));
+        assert_eq!(
+            processed,
+            "# Result\n\n```rust\nfn main() {}\n```\n\n[^1] [^2]\n\n[^1]: node_0\n[^2]: node_1",
+        );
+        
+        let cited = CitationFootnotes.process("The answer is 42.".to_string(), &context(None));
+        assert_eq!(cited, "The answer is 42. [^1] [^2]\n\n[^1]: node_0\n[^2]: node_1");
+    }
+
+    #[tokio::test]
+    async fn test_context_code_language_from_chain() {
+        let mut chain = GLMReasoning::new(10).reason("write binary search in Python", QueryType::Factual).await.unwrap();
+        assert_eq!(PostProcessContext::from_chain(&chain).code_language, Some(ProgrammingLanguage::Python));
+        
+        chain.final_answer = "```js\nconsole.log(1)\n```".to_string();
+        assert_eq!(PostProcessContext::from_chain(&chain).code_language, Some(ProgrammingLanguage::JavaScript));
+        
+        chain.query = "what is a graph".to_string();
+        chain.final_answer = "```\nuntagged\n```".to_string();
+        assert_eq!(PostProcessContext::from_chain(&chain).code_language, None);
+    }
+
+    #[derive(Debug)]
+    struct UppercaseTranslator;
+
+    impl Translator for UppercaseTranslator {
+        fn translate(&self, text: &str, _target_language: &str) -> Option<String> {
+            Some(text.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn test_response_language_enforcer() {
+        let stage = ResponseLanguageEnforcer { translator: Arc::new(UppercaseTranslator) };
+        let mut context = context(None);
+        
+        assert_eq!(stage.process("What is the answer".to_string(), &context), "What is the answer");
+        context.response_language = "id".to_string();
+        assert_eq!(stage.process("What is the answer".to_string(), &context), "WHAT IS THE ANSWER");
+    }
+
+    #[test]
+    fn test_tagged_fences_are_kept() {
+        let answer = "```python\nprint(1)\n```".to_string();
+        let processed = CodeFenceTagger::default().process(answer.clone(), &context(Some(ProgrammingLanguage::Rhai)));
+        assert_eq!(processed, answer);
+    }
+}
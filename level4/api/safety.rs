@@ -0,0 +1,414 @@
+// -*- coding: utf-8 -*-
+//! Output Safety Filter
+//! 
+//! Middleware that scans streamed chunks for disallowed content and masks
+//! matches or ends the stream with a policy-violation chunk.
+
+use crate::error::Result;
+use crate::level4::agents::clock::{self, Clock};
+use crate::level4::api::stream::StreamChunk;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Disallowed span found by a classifier, as byte offsets into the scanned text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyFinding {
+    pub category: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Detects disallowed content categories in text
+///
+/// Implementations may be simple matchers or calls out to an LLM-based classifier.
+#[async_trait]
+pub trait SafetyClassifier: Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &str;
+
+    async fn classify(&self, text: &str) -> Result<Vec<SafetyFinding>>;
+}
+
+/// Case-insensitive phrase matcher keyed by category
+#[derive(Debug, Clone, Default)]
+pub struct PatternMatcher {
+    patterns: Vec<(String, String)>,
+}
+
+impl PatternMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pattern(mut self, category: &str, pattern: &str) -> Self {
+        self.patterns.push((category.to_string(), pattern.to_ascii_lowercase()));
+        self
+    }
+}
+
+#[async_trait]
+impl SafetyClassifier for PatternMatcher {
+    fn name(&self) -> &str {
+        "pattern"
+    }
+
+    async fn classify(&self, text: &str) -> Result<Vec<SafetyFinding>> {
+        // ASCII lowercasing keeps byte offsets aligned with `text`
+        let haystack = text.to_ascii_lowercase();
+        let mut findings = Vec::new();
+        
+        for (category, pattern) in &self.patterns {
+            for (start, _) in haystack.match_indices(pattern.as_str()) {
+                findings.push(SafetyFinding {
+                    category: category.clone(),
+                    start,
+                    end: start + pattern.len(),
+                });
+            }
+        }
+        
+        Ok(findings)
+    }
+}
+
+/// What to do with content in a category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafetyAction {
+    /// Deliver unchanged but still audit
+    Allow,
+    /// Replace the matched span with the policy's mask
+    Mask,
+    /// Stop the stream with a policy-violation chunk
+    Terminate,
+}
+
+/// Per-category actions applied by `OutputSafetyFilter`
+#[derive(Debug, Clone)]
+pub struct SafetyPolicy {
+    pub actions: HashMap<String, SafetyAction>,
+    /// Action for categories without an explicit entry
+    pub default_action: SafetyAction,
+    pub mask: String,
+    /// Bytes held back from each chunk and scanned again with the next one,
+    /// so matches straddling a chunk boundary are caught before any part of
+    /// them is delivered; the final chunk flushes them
+    pub lookbehind_bytes: usize,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        Self {
+            actions: HashMap::new(),
+            default_action: SafetyAction::Terminate,
+            mask: "[redacted]".to_string(),
+            lookbehind_bytes: 64,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    pub fn action_for(&self, category: &str) -> SafetyAction {
+        self.actions.get(category).copied().unwrap_or(self.default_action)
+    }
+}
+
+/// Filter decision reported to the audit sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyDecision {
+    pub chunk_id: usize,
+    pub category: String,
+    pub action: SafetyAction,
+    pub classifier: String,
+    pub timestamp_ms: u64,
+}
+
+/// Destination for safety decisions
+pub trait AuditSink: Send + Sync + std::fmt::Debug {
+    fn record(&self, decision: &SafetyDecision);
+}
+
+/// Audit sink writing decisions to the `audit` tracing target
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, decision: &SafetyDecision) {
+        tracing::warn!(
+            target: "audit",
+            chunk_id = decision.chunk_id,
+            category = %decision.category,
+            action = ?decision.action,
+            classifier = %decision.classifier,
+            "Output safety decision"
+        );
+    }
+}
+
+/// Stream middleware enforcing a `SafetyPolicy`
+#[derive(Debug, Clone)]
+pub struct OutputSafetyFilter {
+    classifier: Arc<dyn SafetyClassifier>,
+    policy: SafetyPolicy,
+    audit: Arc<dyn AuditSink>,
+    clock: Arc<dyn Clock>,
+}
+
+impl OutputSafetyFilter {
+    pub fn new(classifier: Arc<dyn SafetyClassifier>, policy: SafetyPolicy) -> Self {
+        Self {
+            classifier,
+            policy,
+            audit: Arc::new(TracingAuditSink),
+            clock: clock::system_clock(),
+        }
+    }
+
+    pub fn with_audit(mut self, audit: Arc<dyn AuditSink>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Source of decision timestamps
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Wrap a chunk stream, returning the filtered stream
+    pub fn wrap(&self, mut rx: mpsc::Receiver<StreamChunk>) -> mpsc::Receiver<StreamChunk> {
+        let (tx, filtered) = mpsc::channel(100);
+        let filter = self.clone();
+        
+        tokio::spawn(async move {
+            let mut held = String::new();
+            let mut next_chunk_id = 0;
+            while let Some(chunk) = rx.recv().await {
+                next_chunk_id = chunk.chunk_id + 1;
+                let flush = chunk.is_final;
+                let (chunk, terminated) = filter.filter_chunk(chunk, &mut held, flush).await;
+                let is_final = chunk.is_final;
+                if tx.send(chunk).await.is_err() || terminated || is_final {
+                    return;
+                }
+            }
+            
+            // The stream closed without a final chunk; deliver what was held
+            if !held.is_empty() {
+                let closing = StreamChunk {
+                    chunk_id: next_chunk_id,
+                    content: String::new(),
+                    is_final: false,
+                    metadata: Default::default(),
+                };
+                let (chunk, _) = filter.filter_chunk(closing, &mut held, true).await;
+                let _ = tx.send(chunk).await;
+            }
+        });
+        
+        filtered
+    }
+
+    /// Apply the policy to the held text followed by one chunk
+    ///
+    /// Returns the chunk to send and whether the stream ends. The last
+    /// `lookbehind_bytes` are held back for the next chunk unless `flush`
+    /// is set; a masked span reaching into them is delivered whole instead.
+    async fn filter_chunk(&self, mut chunk: StreamChunk, held: &mut String, flush: bool) -> (StreamChunk, bool) {
+        let window = format!("{}{}", held, chunk.content);
+        
+        let mut findings = match self.classifier.classify(&window).await {
+            Ok(findings) => findings,
+            Err(e) => {
+                // Fail closed: unclassified output is never delivered
+                tracing::error!("Safety classifier {} failed: {:?}", self.classifier.name(), e);
+                return (self.violation(chunk, "classifier_error", SafetyAction::Terminate), true);
+            }
+        };
+        
+        if let Some(finding) = findings.iter().find(|f| self.policy.action_for(&f.category) == SafetyAction::Terminate) {
+            return (self.violation(chunk, &finding.category, SafetyAction::Terminate), true);
+        }
+        
+        let mut split = if flush {
+            window.len()
+        } else {
+            window.len() - Self::tail(&window, self.policy.lookbehind_bytes).len()
+        };
+        
+        // Findings starting in the held text are decided with the next chunk
+        findings.sort_by_key(|f| f.start);
+        let mut mask_spans = Vec::new();
+        for finding in findings.into_iter().filter(|f| f.end > f.start) {
+            if finding.start >= split {
+                break;
+            }
+            let action = self.policy.action_for(&finding.category);
+            self.audit_decision(chunk.chunk_id, &finding.category, action);
+            if action == SafetyAction::Mask {
+                split = split.max(finding.end.min(window.len()));
+                mask_spans.push((finding.start, finding.end));
+            }
+        }
+        while !window.is_char_boundary(split) {
+            split += 1;
+        }
+        
+        *held = window[split..].to_string();
+        chunk.content = self.mask(&window[..split], mask_spans);
+        (chunk, false)
+    }
+
+    fn violation(&self, mut chunk: StreamChunk, category: &str, action: SafetyAction) -> StreamChunk {
+        self.audit_decision(chunk.chunk_id, category, action);
+        chunk.content = format!("[response withheld: {} policy violation]", category);
+        chunk.is_final = true;
+        chunk.metadata.policy_violation = Some(category.to_string());
+        chunk
+    }
+
+    fn mask(&self, content: &str, mut spans: Vec<(usize, usize)>) -> String {
+        spans.sort();
+        let mut masked = String::with_capacity(content.len());
+        let mut cursor = 0;
+        for (start, end) in spans {
+            let (start, end) = (start.max(cursor), end.min(content.len()));
+            if start >= end || !content.is_char_boundary(start) || !content.is_char_boundary(end) {
+                continue;
+            }
+            masked.push_str(&content[cursor..start]);
+            masked.push_str(&self.policy.mask);
+            cursor = end;
+        }
+        masked.push_str(&content[cursor..]);
+        masked
+    }
+
+    /// Last `max_bytes` of `text`, widened to a char boundary
+    fn tail(text: &str, max_bytes: usize) -> &str {
+        let mut start = text.len().saturating_sub(max_bytes);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        &text[start..]
+    }
+
+    fn audit_decision(&self, chunk_id: usize, category: &str, action: SafetyAction) {
+        self.audit.record(&SafetyDecision {
+            chunk_id,
+            category: category.to_string(),
+            action,
+            classifier: self.classifier.name().to_string(),
+            timestamp_ms: self.clock.now_ms(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::api::stream::ChunkMetadata;
+
+    fn chunk(chunk_id: usize, content: &str, is_final: bool) -> StreamChunk {
+        StreamChunk {
+            chunk_id,
+            content: content.to_string(),
+            is_final,
+            metadata: ChunkMetadata::default(),
+        }
+    }
+
+    async fn run(filter: &OutputSafetyFilter, chunks: Vec<StreamChunk>) -> Vec<StreamChunk> {
+        let (tx, rx) = mpsc::channel(10);
+        for chunk in chunks {
+            tx.send(chunk).await.unwrap();
+        }
+        drop(tx);
+        
+        let mut filtered = filter.wrap(rx);
+        let mut received = Vec::new();
+        while let Some(chunk) = filtered.recv().await {
+            received.push(chunk);
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_mask_across_chunk_boundary() {
+        let matcher = PatternMatcher::new().with_pattern("secrets", "api key");
+        let mut policy = SafetyPolicy::default();
+        policy.actions.insert("secrets".to_string(), SafetyAction::Mask);
+        let filter = OutputSafetyFilter::new(Arc::new(matcher), policy);
+        
+        let received = run(&filter, vec![chunk(0, "the API", false), chunk(1, " key is x", true)]).await;
+        
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].content, "");
+        assert_eq!(received[1].content, "the [redacted] is x");
+        for fragment in ["API", "key"] {
+            assert!(received.iter().all(|c| !c.content.contains(fragment)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_held_text_is_flushed() {
+        let matcher = PatternMatcher::new().with_pattern("secrets", "api key");
+        let policy = SafetyPolicy {
+            actions: HashMap::from([("secrets".to_string(), SafetyAction::Mask)]),
+            lookbehind_bytes: 4,
+            ..SafetyPolicy::default()
+        };
+        let filter = OutputSafetyFilter::new(Arc::new(matcher), policy);
+        
+        // A match reaching into the held bytes is delivered masked as a whole
+        let received = run(&filter, vec![chunk(0, "an api key", false), chunk(1, " here", true)]).await;
+        let contents: Vec<&str> = received.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents, vec!["an [redacted]", " here"]);
+        
+        // Without a final chunk the held bytes follow in a closing chunk
+        let received = run(&filter, vec![chunk(0, "plain text", false)]).await;
+        let contents: Vec<&str> = received.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents, vec!["plain ", "text"]);
+        assert!(!received[1].is_final);
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<SafetyDecision>>);
+
+    impl AuditSink for RecordingSink {
+        fn record(&self, decision: &SafetyDecision) {
+            self.0.lock().unwrap().push(decision.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decisions_use_injected_clock() {
+        let matcher = PatternMatcher::new().with_pattern("violence", "weapon");
+        let sink = Arc::new(RecordingSink::default());
+        let clock = Arc::new(clock::ManualClock::new(std::time::Duration::from_millis(1_000)));
+        let filter = OutputSafetyFilter::new(Arc::new(matcher), SafetyPolicy::default())
+            .with_audit(sink.clone())
+            .with_clock(clock);
+        
+        run(&filter, vec![chunk(0, "a weapon", true)]).await;
+        let decisions = sink.0.lock().unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].timestamp_ms, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_with_violation_chunk() {
+        let matcher = PatternMatcher::new().with_pattern("violence", "build a weapon");
+        let filter = OutputSafetyFilter::new(Arc::new(matcher), SafetyPolicy::default());
+        
+        let received = run(&filter, vec![
+            chunk(0, "How to build a weapon", false),
+            chunk(1, "step one", true),
+        ]).await;
+        
+        assert_eq!(received.len(), 1);
+        assert!(received[0].is_final);
+        assert_eq!(received[0].metadata.policy_violation.as_deref(), Some("violence"));
+    }
+}
@@ -0,0 +1,243 @@
+// -*- coding: utf-8 -*-
+//! Shadow Traffic
+//! 
+//! Runs a candidate reasoning engine on a copy of live queries without
+//! serving its results, recording how its chains compare to the primary's.
+
+use crate::error::Result;
+use crate::level4::agents::clock::{self, Clock};
+use crate::level4::agents::{GLMReasoning, QueryType, ReasoningChain};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Primary vs. candidate outcome for one shadowed query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowComparison {
+    pub query: String,
+    pub query_type: QueryType,
+    pub primary_chain_id: String,
+    /// `None` when the candidate failed
+    pub shadow_chain_id: Option<String>,
+    pub answers_match: bool,
+    /// Candidate minus primary total confidence
+    pub confidence_delta: f64,
+    pub primary_steps: usize,
+    pub shadow_steps: usize,
+    pub primary_time_ms: u64,
+    pub shadow_time_ms: u64,
+    pub shadow_error: Option<String>,
+    pub timestamp_ms: u64,
+}
+
+impl ShadowComparison {
+    fn new(primary: &ReasoningChain, shadow: std::result::Result<&ReasoningChain, String>, timestamp_ms: u64) -> Self {
+        let mut comparison = Self {
+            query: primary.query.clone(),
+            query_type: primary.query_type.clone(),
+            primary_chain_id: primary.chain_id.clone(),
+            shadow_chain_id: None,
+            answers_match: false,
+            confidence_delta: 0.0,
+            primary_steps: primary.steps.len(),
+            shadow_steps: 0,
+            primary_time_ms: primary.execution_time_ms,
+            shadow_time_ms: 0,
+            shadow_error: None,
+            timestamp_ms,
+        };
+        
+        match shadow {
+            Ok(shadow) => {
+                comparison.shadow_chain_id = Some(shadow.chain_id.clone());
+                comparison.answers_match = shadow.final_answer.trim() == primary.final_answer.trim();
+                comparison.confidence_delta = shadow.total_confidence - primary.total_confidence;
+                comparison.shadow_steps = shadow.steps.len();
+                comparison.shadow_time_ms = shadow.execution_time_ms;
+            }
+            Err(e) => comparison.shadow_error = Some(e),
+        }
+        comparison
+    }
+}
+
+/// Aggregate view over recorded comparisons
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowSummary {
+    pub samples: usize,
+    pub errors: usize,
+    /// Share of successful candidate runs whose answer matched the primary
+    pub match_rate: f64,
+    pub avg_confidence_delta: f64,
+    pub avg_latency_delta_ms: f64,
+}
+
+/// Destination for shadow comparisons, e.g. an offline evaluation store
+#[async_trait]
+pub trait ShadowSink: Send + Sync + std::fmt::Debug {
+    async fn record(&self, comparison: ShadowComparison) -> Result<()>;
+}
+
+/// Bounded in-memory log that keeps the most recent comparisons
+#[derive(Debug)]
+pub struct ShadowLog {
+    capacity: usize,
+    comparisons: Mutex<VecDeque<ShadowComparison>>,
+}
+
+impl ShadowLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            comparisons: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub async fn comparisons(&self) -> Vec<ShadowComparison> {
+        self.comparisons.lock().await.iter().cloned().collect()
+    }
+
+    pub async fn summary(&self) -> ShadowSummary {
+        let comparisons = self.comparisons.lock().await;
+        let succeeded: Vec<&ShadowComparison> = comparisons.iter()
+            .filter(|c| c.shadow_error.is_none())
+            .collect();
+        let mean = |values: Vec<f64>| {
+            if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 }
+        };
+        
+        ShadowSummary {
+            samples: comparisons.len(),
+            errors: comparisons.len() - succeeded.len(),
+            match_rate: mean(succeeded.iter().map(|c| if c.answers_match { 1.0 } else { 0.0 }).collect()),
+            avg_confidence_delta: mean(succeeded.iter().map(|c| c.confidence_delta).collect()),
+            avg_latency_delta_ms: mean(succeeded.iter()
+                .map(|c| c.shadow_time_ms as f64 - c.primary_time_ms as f64)
+                .collect()),
+        }
+    }
+
+    /// Write recorded comparisons as JSON Lines for offline analysis
+    pub async fn export_jsonl(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let comparisons = self.comparisons().await;
+        let mut lines = Vec::new();
+        for comparison in &comparisons {
+            lines.extend(serde_json::to_vec(comparison)?);
+            lines.push(b'\n');
+        }
+        tokio::fs::write(path.as_ref(), lines).await?;
+        Ok(comparisons.len())
+    }
+}
+
+#[async_trait]
+impl ShadowSink for ShadowLog {
+    async fn record(&self, comparison: ShadowComparison) -> Result<()> {
+        let mut comparisons = self.comparisons.lock().await;
+        if comparisons.len() == self.capacity {
+            comparisons.pop_front();
+        }
+        comparisons.push_back(comparison);
+        Ok(())
+    }
+}
+
+/// Mirrors sampled queries to a candidate engine in the background
+#[derive(Clone)]
+pub struct ShadowRunner {
+    candidate: Arc<GLMReasoning>,
+    sink: Arc<dyn ShadowSink>,
+    sample_every: usize,
+    seen: Arc<AtomicUsize>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ShadowRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShadowRunner")
+            .field("sink", &self.sink)
+            .field("sample_every", &self.sample_every)
+            .finish()
+    }
+}
+
+impl ShadowRunner {
+    /// Shadow every query to `candidate`
+    pub fn new(candidate: Arc<GLMReasoning>, sink: Arc<dyn ShadowSink>) -> Self {
+        Self {
+            candidate,
+            sink,
+            sample_every: 1,
+            seen: Arc::new(AtomicUsize::new(0)),
+            clock: clock::system_clock(),
+        }
+    }
+
+    /// Shadow only one in every `n` queries
+    pub fn with_sample_every(mut self, n: usize) -> Self {
+        self.sample_every = n.max(1);
+        self
+    }
+
+    /// Source of comparison timestamps
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Replay the primary chain's query on the candidate if it is sampled
+    ///
+    /// The candidate runs on its own task; its result is only recorded, never
+    /// served, and its failures never affect the primary stream.
+    pub fn shadow(&self, primary: &ReasoningChain) -> Option<JoinHandle<()>> {
+        if self.seen.fetch_add(1, Ordering::Relaxed) % self.sample_every != 0 {
+            return None;
+        }
+        
+        let runner = self.clone();
+        let primary = primary.clone();
+        Some(tokio::spawn(async move {
+            let language = Some(primary.response_language.as_str()).filter(|l| !l.is_empty());
+            let shadow = runner.candidate
+                .reason_with_language(&primary.query, primary.query_type.clone(), language)
+                .await;
+            let timestamp_ms = runner.clock.now_ms();
+            let comparison = match &shadow {
+                Ok(chain) => ShadowComparison::new(&primary, Ok(chain), timestamp_ms),
+                Err(e) => ShadowComparison::new(&primary, Err(format!("{:?}", e)), timestamp_ms),
+            };
+            
+            if let Err(e) = runner.sink.record(comparison).await {
+                tracing::warn!("Failed to record shadow comparison for {}: {:?}", primary.chain_id, e);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shadow_records_comparison() {
+        let primary = GLMReasoning::new(10).reason("Test query", QueryType::Factual).await.unwrap();
+        let log = Arc::new(ShadowLog::new(10));
+        let clock = Arc::new(clock::ManualClock::new(std::time::Duration::from_millis(5_000)));
+        let runner = ShadowRunner::new(Arc::new(GLMReasoning::new(10)), log.clone())
+            .with_sample_every(2)
+            .with_clock(clock);
+        
+        runner.shadow(&primary).unwrap().await.unwrap();
+        assert!(runner.shadow(&primary).is_none());
+        
+        let summary = log.summary().await;
+        assert_eq!(summary.samples, 1);
+        assert_eq!(summary.match_rate, 1.0);
+        assert_eq!(log.comparisons().await[0].timestamp_ms, 5_000);
+    }
+}
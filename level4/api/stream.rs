@@ -4,11 +4,22 @@
 //! Real-time streaming of inference results with concurrent graph operations.
 
 use crate::error::Result;
-use crate::level4::agents::{GLMReasoning, VertexCentricCache, QueryType};
+use crate::level4::agents::{GLMReasoning, LoadSignal, VertexCentricCache, QueryType, ReasoningChain, ReasoningStep};
+use crate::level4::agents::clock::{self, Clock};
+use crate::level4::agents::contention::{self, ContentionProfile};
+use crate::level4::agents::freshness::StalenessWarning;
+use crate::level4::api::broadcast::{BroadcastConfig, StreamBroadcast};
+use crate::level4::api::compression::{self, CompressionCodec, CompressionSettings, EncodedChunk};
+use crate::level4::api::partial::{ApiError, ApiErrorKind, PartialResult};
+use crate::level4::api::postprocess::{AnswerPostProcessor, PostProcessContext};
+use crate::level4::api::safety::OutputSafetyFilter;
+use crate::level4::api::shadow::ShadowRunner;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, RwLock};
 use tokio::time::{Duration, interval};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// Stream chunk with partial results
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,12 +30,82 @@ pub struct StreamChunk {
     pub metadata: ChunkMetadata,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChunkMetadata {
     pub timestamp_ms: u64,
     pub graph_nodes_accessed: Vec<String>,
     pub cache_hits: usize,
     pub confidence: f64,
+    /// Fraction of the stream delivered so far, in `0.0..=1.0`
+    #[serde(default)]
+    pub progress: f32,
+    /// Estimated time until the final chunk
+    #[serde(default)]
+    pub eta_ms: u64,
+    /// Present every `checkpoint_interval` chunks and on the final chunk
+    #[serde(default)]
+    pub checkpoint: Option<StreamCheckpoint>,
+    /// Full reasoning steps, sent on the first chunk of verbose streams
+    #[serde(default)]
+    pub reasoning_steps: Vec<ReasoningStep>,
+    /// Set on the final chunk when the safety filter ended the stream
+    #[serde(default)]
+    pub policy_violation: Option<String>,
+    /// Time this request spent waiting on cache locks and chunk sends, on the
+    /// final chunk
+    #[serde(default)]
+    pub contention: Option<ContentionProfile>,
+    /// Failures that left the answer incomplete, on the final chunk
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+    /// Set on the final chunk when vertices the answer used have changed
+    /// since it was reasoned
+    #[serde(default)]
+    pub staleness: Option<StalenessWarning>,
+}
+
+/// Compact resume point for a reconnecting client
+///
+/// Identifies the chain and its state, not just the text position, so a
+/// resume request can be checked against the chain it claims to continue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamCheckpoint {
+    pub chain_id: String,
+    /// Stable hash of the chain's step outputs and answer
+    pub state_hash: String,
+    /// Id of the last reasoning step completed when the chunk was produced
+    pub last_completed_step: usize,
+    /// Byte offset into the answer just past this chunk
+    pub content_offset: usize,
+}
+
+impl StreamCheckpoint {
+    /// Hash chain state with FNV-1a, which, unlike `DefaultHasher`, is stable
+    /// across builds and therefore across server restarts
+    pub fn state_hash(chain: &ReasoningChain) -> String {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        
+        let mut hash = OFFSET_BASIS;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes.iter().chain(std::iter::once(&0u8)) {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        feed(chain.chain_id.as_bytes());
+        for step in &chain.steps {
+            feed(step.output.as_bytes());
+        }
+        feed(chain.final_answer.as_bytes());
+        
+        format!("{:016x}", hash)
+    }
+
+    /// Check that this checkpoint was taken from `chain`
+    pub fn matches(&self, chain: &ReasoningChain) -> bool {
+        self.chain_id == chain.chain_id && self.state_hash == Self::state_hash(chain)
+    }
 }
 
 /// Streaming configuration
@@ -34,6 +115,29 @@ pub struct StreamConfig {
     pub chunk_delay_ms: u64,
     pub enable_parallel_graph: bool,
     pub max_concurrent_ops: usize,
+    /// Codecs offered to clients, most preferred first
+    pub compression_preference: Vec<CompressionCodec>,
+    pub compression_level: i32,
+    /// Embed a checkpoint every this many chunks; 0 disables all but the final one
+    pub checkpoint_interval: usize,
+    /// Attach the chain's reasoning steps to the first chunk
+    pub include_step_metadata: bool,
+    /// Formatting applied to the final answer before chunking; `None` streams it raw
+    pub post_processor: Option<Arc<AnswerPostProcessor>>,
+    /// Output safety middleware applied to every stream; `None` disables it
+    pub safety_filter: Option<OutputSafetyFilter>,
+    /// Candidate engine mirroring live queries; its results are never streamed
+    pub shadow: Option<ShadowRunner>,
+    /// Serve identical concurrent requests from one reasoning run
+    pub deduplicate_queries: bool,
+    /// Source of chunk timestamps and stream timings; the safety filter and
+    /// shadow runner are run on it too
+    pub clock: Arc<dyn Clock>,
+    /// Counts running streams; share it with the cache as its `load_signal`
+    pub load: Option<Arc<StreamLoad>>,
+    /// Reason once more when the chain is already stale on completion; needs
+    /// vertex versions on the reasoning engine
+    pub rereason_stale: bool,
 }
 
 impl Default for StreamConfig {
@@ -43,8 +147,205 @@ impl Default for StreamConfig {
             chunk_delay_ms: 100,
             enable_parallel_graph: true,
             max_concurrent_ops: 4,
+            compression_preference: vec![CompressionCodec::Zstd, CompressionCodec::Gzip],
+            compression_level: 3,
+            checkpoint_interval: 5,
+            include_step_metadata: false,
+            post_processor: Some(Arc::new(AnswerPostProcessor::default())),
+            safety_filter: None,
+            shadow: None,
+            deduplicate_queries: false,
+            clock: clock::system_clock(),
+            load: None,
+            rereason_stale: false,
+        }
+    }
+}
+
+/// Named streaming profile, selectable per request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamProfile {
+    /// Small chunks with no pacing delay, for chat-style UIs
+    Interactive,
+    /// Large chunks with no pacing delay, for offline consumers
+    Batch,
+    /// Base chunking plus the full reasoning steps
+    Verbose,
+}
+
+impl StreamProfile {
+    /// Profile used when a request does not name one
+    pub fn for_query_type(query_type: &QueryType) -> Self {
+        if matches!(query_type, QueryType::Reasoning) {
+            StreamProfile::Verbose
+        } else {
+            StreamProfile::Interactive
         }
     }
+
+    /// Derive the effective config from `base`, overriding only what the profile controls
+    pub fn apply(&self, base: &StreamConfig) -> StreamConfig {
+        let mut config = base.clone();
+        match self {
+            StreamProfile::Interactive => {
+                config.chunk_size = 16;
+                config.chunk_delay_ms = 0;
+            }
+            StreamProfile::Batch => {
+                config.chunk_size = 1024;
+                config.chunk_delay_ms = 0;
+            }
+            StreamProfile::Verbose => {
+                config.include_step_metadata = true;
+            }
+        }
+        config
+    }
+}
+
+/// Per-request overrides of the engine's streaming defaults
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// Defaults to `StreamProfile::for_query_type`
+    pub profile: Option<StreamProfile>,
+    /// ISO 639-1 code to answer in; detected from the query when `None`
+    pub response_language: Option<String>,
+}
+
+/// Query plus the per-request settings a stream task needs
+struct StreamRequest {
+    query: String,
+    query_type: QueryType,
+    response_language: Option<String>,
+}
+
+/// Expected size of a stream before it starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEstimate {
+    pub expected_steps: usize,
+    pub expected_chunks: usize,
+    pub expected_duration_ms: u64,
+    /// Completed streams the estimate is based on; 0 means defaults were used
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProgressHistory {
+    samples: usize,
+    total_steps: usize,
+    total_chunks: usize,
+    total_reasoning_ms: u64,
+    total_chunk_ms: u64,
+}
+
+/// Progress model fed by completed streams, grouped by query type
+#[derive(Debug, Default)]
+pub struct ProgressModel {
+    history: RwLock<HashMap<String, ProgressHistory>>,
+}
+
+impl ProgressModel {
+    /// Steps in the default reasoning plan (retrieval, inference, aggregation, verification)
+    const DEFAULT_STEPS: usize = 4;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn history_key(query_type: &QueryType) -> String {
+        format!("{:?}", query_type)
+    }
+
+    /// Record a completed stream
+    pub async fn record(
+        &self,
+        query_type: &QueryType,
+        steps: usize,
+        chunks: usize,
+        reasoning_ms: u64,
+        chunk_ms: u64,
+    ) {
+        let mut history = self.history.write().await;
+        let entry = history.entry(Self::history_key(query_type)).or_default();
+        
+        entry.samples += 1;
+        entry.total_steps += steps;
+        entry.total_chunks += chunks;
+        entry.total_reasoning_ms += reasoning_ms;
+        entry.total_chunk_ms += chunk_ms;
+    }
+
+    /// Estimate how long a stream for `query_type` will run
+    pub async fn estimate(&self, query_type: &QueryType, config: &StreamConfig) -> ProgressEstimate {
+        let history = self.history.read().await;
+        
+        match history.get(&Self::history_key(query_type)) {
+            Some(h) if h.samples > 0 => {
+                let samples = h.samples as u64;
+                ProgressEstimate {
+                    expected_steps: h.total_steps / h.samples,
+                    expected_chunks: (h.total_chunks / h.samples).max(1),
+                    expected_duration_ms: (h.total_reasoning_ms + h.total_chunk_ms) / samples,
+                    samples: h.samples,
+                }
+            }
+            _ => ProgressEstimate {
+                expected_steps: Self::DEFAULT_STEPS,
+                expected_chunks: 1,
+                expected_duration_ms: config.chunk_delay_ms,
+                samples: 0,
+            },
+        }
+    }
+
+    /// Average observed time per chunk, if any streams have completed
+    async fn chunk_time_ms(&self, query_type: &QueryType) -> Option<u64> {
+        let history = self.history.read().await;
+        history.get(&Self::history_key(query_type))
+            .filter(|h| h.total_chunks > 0)
+            .map(|h| h.total_chunk_ms / h.total_chunks as u64)
+    }
+}
+
+/// Running streams against a capacity, readable by the cache as a `LoadSignal`
+#[derive(Debug)]
+pub struct StreamLoad {
+    active: AtomicUsize,
+    capacity: usize,
+}
+
+impl StreamLoad {
+    /// `capacity` streams count as full load
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            active: AtomicUsize::new(0),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Count a stream as running until the guard drops
+    fn enter(self: &Arc<Self>) -> StreamLoadGuard {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        StreamLoadGuard(self.clone())
+    }
+}
+
+impl LoadSignal for StreamLoad {
+    fn load(&self) -> f64 {
+        (self.active() as f64 / self.capacity as f64).min(1.0)
+    }
+}
+
+struct StreamLoadGuard(Arc<StreamLoad>);
+
+impl Drop for StreamLoadGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 /// Streaming inference engine
@@ -52,6 +353,9 @@ pub struct StreamingInference {
     config: StreamConfig,
     reasoning: Arc<GLMReasoning>,
     cache: Arc<VertexCentricCache>,
+    progress: Arc<ProgressModel>,
+    /// Deduplicated streams, keyed by `dedup_key`; finished ones are swept on the next request
+    in_flight: Arc<Mutex<HashMap<String, StreamBroadcast>>>,
 }
 
 impl StreamingInference {
@@ -64,66 +368,285 @@ impl StreamingInference {
             config,
             reasoning,
             cache,
+            progress: Arc::new(ProgressModel::new()),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Estimate the size and duration of a stream before starting it
+    pub async fn estimate_progress(&self, query_type: &QueryType) -> ProgressEstimate {
+        self.progress.estimate(query_type, &self.config).await
+    }
+
     /// Stream inference results in real-time
+    ///
+    /// Uses the default profile for `query_type`; see `stream_inference_with_options`.
     pub async fn stream_inference(
         &self,
         query: &str,
         query_type: QueryType,
     ) -> Result<mpsc::Receiver<StreamChunk>> {
+        self.stream_inference_with_options(query, query_type, StreamOptions::default()).await
+    }
+
+    /// Stream inference results using an explicitly chosen profile
+    pub async fn stream_inference_with_profile(
+        &self,
+        query: &str,
+        query_type: QueryType,
+        profile: StreamProfile,
+    ) -> Result<mpsc::Receiver<StreamChunk>> {
+        let options = StreamOptions {
+            profile: Some(profile),
+            ..StreamOptions::default()
+        };
+        self.stream_inference_with_options(query, query_type, options).await
+    }
+
+    /// Stream inference results with per-request profile and language overrides
+    ///
+    /// With `deduplicate_queries` set, a request identical to one already in
+    /// flight joins it instead of reasoning again: it receives every chunk
+    /// produced so far, then follows the live stream at its own pace. The
+    /// shared run completes even if every client disconnects.
+    pub async fn stream_inference_with_options(
+        &self,
+        query: &str,
+        query_type: QueryType,
+        options: StreamOptions,
+    ) -> Result<mpsc::Receiver<StreamChunk>> {
+        let profile = options.profile.unwrap_or_else(|| StreamProfile::for_query_type(&query_type));
+        let request = StreamRequest {
+            query: query.to_string(),
+            query_type,
+            response_language: options.response_language,
+        };
+        if !self.config.deduplicate_queries {
+            return Ok(self.start_stream(request, profile));
+        }
+        
+        let key = Self::dedup_key(&request, profile);
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.retain(|_, broadcast| !broadcast.is_finished());
+        if let Some(broadcast) = in_flight.get(&key) {
+            return Ok(broadcast.subscribe());
+        }
+        
+        // Joiners need the whole answer, not just a recent window
+        let broadcast = StreamBroadcast::new(self.start_stream(request, profile), BroadcastConfig {
+            history_limit: None,
+            ..BroadcastConfig::default()
+        });
+        let rx = broadcast.subscribe();
+        in_flight.insert(key, broadcast);
+        Ok(rx)
+    }
+
+    /// Start a stream that any number of observers can subscribe to while it runs
+    ///
+    /// Subscribers joining late receive up to `config.history_limit` buffered
+    /// chunks before the live ones.
+    pub async fn stream_inference_broadcast(
+        &self,
+        query: &str,
+        query_type: QueryType,
+        options: StreamOptions,
+        config: BroadcastConfig,
+    ) -> Result<StreamBroadcast> {
+        let rx = self.stream_inference_with_options(query, query_type, options).await?;
+        Ok(StreamBroadcast::new(rx, config))
+    }
+
+    fn dedup_key(request: &StreamRequest, profile: StreamProfile) -> String {
+        format!(
+            "{:?}|{:?}|{}|{}",
+            request.query_type,
+            profile,
+            request.response_language.as_deref().unwrap_or(""),
+            request.query
+        )
+    }
+
+    /// Spawn the reasoning and chunking task for one request
+    fn start_stream(&self, request: StreamRequest, profile: StreamProfile) -> mpsc::Receiver<StreamChunk> {
         let (tx, rx) = mpsc::channel(100);
         
-        let query = query.to_string();
         let reasoning = self.reasoning.clone();
         let cache = self.cache.clone();
-        let config = self.config.clone();
+        let config = profile.apply(&self.config);
+        let progress = self.progress.clone();
+        let safety_filter = config.safety_filter.clone().map(|filter| filter.with_clock(config.clock.clone()));
+        let load = config.load.as_ref().map(|load| load.enter());
         
         // Spawn streaming task
         tokio::spawn(async move {
-            if let Err(e) = Self::stream_task(
+            let _load = load;
+            let (result, profile) = contention::profiled(Self::stream_task(
                 tx,
-                query,
-                query_type,
+                request,
                 reasoning,
                 cache,
                 config,
-            ).await {
+                progress,
+            )).await;
+            if let Err(e) = result {
                 tracing::error!("Streaming error: {:?}", e);
             }
+            tracing::debug!(total_wait_us = profile.total_wait().as_micros() as u64, "Stream contention");
         });
         
-        Ok(rx)
+        match safety_filter {
+            Some(filter) => filter.wrap(rx),
+            None => rx,
+        }
+    }
+
+    /// Agree on chunk compression with a client from the codecs it accepts
+    pub fn negotiate_compression(&self, client_accepts: &[CompressionCodec]) -> CompressionSettings {
+        CompressionSettings {
+            codec: compression::negotiate(&self.config.compression_preference, client_accepts),
+            level: self.config.compression_level,
+        }
+    }
+
+    /// Stream inference results as wire-ready, compressed chunks
+    ///
+    /// Compression is negotiated once at stream start and returned alongside
+    /// the receiver so transports can announce it before the first chunk.
+    pub async fn stream_inference_encoded(
+        &self,
+        query: &str,
+        query_type: QueryType,
+        client_accepts: &[CompressionCodec],
+    ) -> Result<(CompressionSettings, mpsc::Receiver<EncodedChunk>)> {
+        let settings = self.negotiate_compression(client_accepts);
+        let mut chunks = self.stream_inference(query, query_type).await?;
+        let (tx, rx) = mpsc::channel(100);
+        
+        tokio::spawn(async move {
+            while let Some(chunk) = chunks.recv().await {
+                let encoded = match compression::encode_chunk(&chunk, settings) {
+                    Ok(encoded) => encoded,
+                    Err(e) => {
+                        tracing::error!("Chunk encoding error: {:?}", e);
+                        break;
+                    }
+                };
+                if tx.send(encoded).await.is_err() {
+                    break; // Receiver dropped
+                }
+            }
+        });
+        
+        Ok((settings, rx))
     }
 
     async fn stream_task(
         tx: mpsc::Sender<StreamChunk>,
-        query: String,
-        query_type: QueryType,
+        request: StreamRequest,
         reasoning: Arc<GLMReasoning>,
         cache: Arc<VertexCentricCache>,
         config: StreamConfig,
+        progress: Arc<ProgressModel>,
     ) -> Result<()> {
-        // Execute reasoning
-        let chain = reasoning.reason(&query, query_type).await?;
-        
-        // Stream results in chunks
-        let full_answer = chain.final_answer;
-        let chunks: Vec<&str> = full_answer
-            .as_bytes()
-            .chunks(config.chunk_size)
-            .map(|chunk| std::str::from_utf8(chunk).unwrap_or(""))
-            .collect();
+        let StreamRequest { query, query_type, response_language } = request;
+        
+        // Execute reasoning; on failure the client gets a final chunk
+        // carrying the error instead of a silently closed stream
+        let reasoning_start = config.clock.instant();
+        let mut result = reasoning
+            .reason_with_language(&query, query_type.clone(), response_language.as_deref())
+            .await;
+        // A single retry: changes during the second run are reported, not chased
+        if config.rereason_stale && matches!(&result, Ok(chain) if reasoning.staleness(chain).is_some()) {
+            result = reasoning
+                .reason_with_language(&query, query_type.clone(), response_language.as_deref())
+                .await;
+        }
+        let mut chain = match result {
+            Ok(chain) => chain,
+            Err(e) => {
+                let chunk = StreamChunk {
+                    chunk_id: 0,
+                    content: String::new(),
+                    is_final: true,
+                    metadata: ChunkMetadata {
+                        timestamp_ms: config.clock.now_ms(),
+                        progress: 1.0,
+                        errors: vec![ApiError::new(ApiErrorKind::Reasoning, format!("{:?}", e))],
+                        ..ChunkMetadata::default()
+                    },
+                };
+                let _ = tx.send(chunk).await;
+                return Err(e);
+            }
+        };
+        let reasoning_ms = (config.clock.instant() - reasoning_start).as_millis() as u64;
+        if let Some(shadow) = &config.shadow {
+            shadow.clone().with_clock(config.clock.clone()).shadow(&chain);
+        }
+        let steps = chain.steps.len();
+        let state_hash = StreamCheckpoint::state_hash(&chain);
+        let last_completed_step = chain.steps.last().map(|s| s.step_id).unwrap_or(0);
         
-        let mut interval = interval(Duration::from_millis(config.chunk_delay_ms));
+        let answer = std::mem::take(&mut chain.final_answer);
+        let full_answer = match &config.post_processor {
+            Some(processor) => {
+                let context = PostProcessContext::from_chain(&chain);
+                processor.process(answer, &context)
+            }
+            None => answer,
+        };
+        
+        // Stream results in chunks; an empty answer still gets a final chunk
+        // to carry the checkpoint, errors and staleness
+        let mut chunks = split_chunks(&full_answer, config.chunk_size);
+        if chunks.is_empty() {
+            chunks.push("");
+        }
+        
+        // Reasoning steps are complete before the first chunk, so they count
+        // as delivered work; only the remaining chunks contribute to the ETA
+        let total_units = steps + chunks.len();
+        let chunk_time_ms = progress.chunk_time_ms(&query_type).await
+            .unwrap_or(config.chunk_delay_ms);
+        
+        // A zero delay streams chunks back-to-back (`interval` rejects a zero period)
+        let mut ticker = (config.chunk_delay_ms > 0)
+            .then(|| interval(Duration::from_millis(config.chunk_delay_ms)));
+        let chunks_start = config.clock.instant();
+        let mut delivered = 0;
+        let mut content_offset = 0;
+        let mut errors = Vec::new();
         
         for (i, chunk_content) in chunks.iter().enumerate() {
-            interval.tick().await;
+            if let Some(ticker) = ticker.as_mut() {
+                ticker.tick().await;
+            }
             
-            // Parallel graph access
+            let is_final = i == chunks.len() - 1;
+            content_offset += chunk_content.len();
+            let checkpoint_due = config.checkpoint_interval > 0 && (i + 1) % config.checkpoint_interval == 0;
+            let checkpoint = (checkpoint_due || is_final).then(|| StreamCheckpoint {
+                chain_id: chain.chain_id.clone(),
+                state_hash: state_hash.clone(),
+                last_completed_step,
+                content_offset,
+            });
+            
+            // Parallel graph access; a failure costs this chunk its graph
+            // metadata, not the rest of the answer
             let graph_nodes = if config.enable_parallel_graph {
-                Self::parallel_graph_access(&cache, i).await?
+                match Self::parallel_graph_access(&cache, i).await {
+                    Ok(nodes) => nodes,
+                    Err(e) => {
+                        errors.push(ApiError::new(
+                            ApiErrorKind::GraphAccess,
+                            format!("chunk {}: {:?}", i, e),
+                        ));
+                        vec![]
+                    }
+                }
             } else {
                 vec![]
             };
@@ -131,18 +654,38 @@ impl StreamingInference {
             let chunk = StreamChunk {
                 chunk_id: i,
                 content: chunk_content.to_string(),
-                is_final: i == chunks.len() - 1,
+                is_final,
                 metadata: ChunkMetadata {
-                    timestamp_ms: Self::current_timestamp_ms(),
+                    timestamp_ms: config.clock.now_ms(),
                     graph_nodes_accessed: graph_nodes,
                     cache_hits: i % 3, // Simulated
                     confidence: 0.85 + (i as f64 * 0.01),
+                    progress: (steps + i + 1) as f32 / total_units as f32,
+                    eta_ms: (chunks.len() - i - 1) as u64 * chunk_time_ms,
+                    checkpoint,
+                    reasoning_steps: if i == 0 && config.include_step_metadata {
+                        chain.steps.clone()
+                    } else {
+                        Vec::new()
+                    },
+                    policy_violation: None,
+                    // Waits up to this point; the final send itself is not included
+                    contention: if is_final { contention::current() } else { None },
+                    errors: if is_final { std::mem::take(&mut errors) } else { Vec::new() },
+                    staleness: if is_final { reasoning.staleness(&chain) } else { None },
                 },
             };
             
-            if tx.send(chunk).await.is_err() {
+            if contention::timed("stream.send", tx.send(chunk)).await.is_err() {
                 break; // Receiver dropped
             }
+            delivered += 1;
+        }
+        
+        // Only complete streams are representative of future ones
+        if delivered == chunks.len() {
+            let chunk_ms = (config.clock.instant() - chunks_start).as_millis() as u64;
+            progress.record(&query_type, steps, delivered, reasoning_ms, chunk_ms).await;
         }
         
         Ok(())
@@ -157,35 +700,15 @@ impl StreamingInference {
             .map(|i| format!("vertex_{}_{}", chunk_id, i))
             .collect();
         
-        // Parallel cache lookups
-        let mut handles = vec![];
-        
-        for vertex_id in &vertex_ids {
-            let cache = cache.clone();
-            let vertex_id = vertex_id.clone();
-            
-            let handle = tokio::spawn(async move {
-                cache.get(&vertex_id, "embedding").await
-            });
-            
-            handles.push(handle);
-        }
-        
-        // Wait for all lookups
-        for handle in handles {
-            let _ = handle.await;
-        }
+        // One batched lookup instead of a task and two backend calls per vertex
+        let keys: Vec<(&str, &str)> = vertex_ids.iter()
+            .map(|vertex_id| (vertex_id.as_str(), "embedding"))
+            .collect();
+        cache.get_many(&keys).await;
         
         Ok(vertex_ids)
     }
 
-    fn current_timestamp_ms() -> u64 {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64
-    }
-
     /// Stream multiple queries in parallel
     pub async fn stream_batch(
         &self,
@@ -218,6 +741,33 @@ impl StreamingInference {
         Ok(full_content)
     }
 
+    /// Collect full stream into a single result, keeping what arrived when
+    /// the stream failed or was cut short
+    ///
+    /// Errors come from the final chunk: a safety policy violation, failed
+    /// graph access or a reasoning failure. A stream that closes without a
+    /// final chunk is reported as interrupted.
+    pub async fn collect_stream_partial(
+        mut rx: mpsc::Receiver<StreamChunk>,
+    ) -> PartialResult<String> {
+        let mut full_content = String::new();
+        let mut errors = vec![ApiError::new(ApiErrorKind::Interrupted, "stream closed before its final chunk")];
+        
+        while let Some(chunk) = rx.recv().await {
+            full_content.push_str(&chunk.content);
+            
+            if chunk.is_final {
+                errors = chunk.metadata.errors;
+                if let Some(category) = chunk.metadata.policy_violation {
+                    errors.push(ApiError::new(ApiErrorKind::PolicyViolation, category));
+                }
+                break;
+            }
+        }
+        
+        PartialResult::with_errors(full_content, errors)
+    }
+
     /// Get streaming statistics
     pub async fn get_stream_stats(
         mut rx: mpsc::Receiver<StreamChunk>,
@@ -266,9 +816,31 @@ pub struct StreamStats {
     pub avg_chunk_time_ms: u64,
 }
 
+/// `text` in pieces of at most `chunk_size` bytes, split on character
+/// boundaries; a character wider than `chunk_size` is a piece of its own
+fn split_chunks(text: &str, chunk_size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = chunk_size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::level4::agents::VertexVersions;
+    use crate::level4::api::postprocess::PostProcessStage;
 
     #[tokio::test]
     async fn test_streaming_inference() {
@@ -316,4 +888,282 @@ mod tests {
         let result = StreamingInference::collect_stream(rx).await.unwrap();
         assert!(!result.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_stream_inference_encoded() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let streaming = StreamingInference::new(
+            StreamConfig::default(),
+            reasoning,
+            cache,
+        );
+        
+        let (settings, mut rx) = streaming.stream_inference_encoded(
+            "Test query",
+            QueryType::Factual,
+            &[CompressionCodec::Gzip],
+        ).await.unwrap();
+        assert_eq!(settings.codec, CompressionCodec::Gzip);
+        
+        let encoded = rx.recv().await.unwrap();
+        let chunk = compression::decode_chunk(&encoded).unwrap();
+        assert_eq!(chunk.chunk_id, 0);
+    }
+
+    #[tokio::test]
+    async fn test_progress_reaches_completion() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let config = StreamConfig {
+            chunk_delay_ms: 1,
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        assert_eq!(streaming.estimate_progress(&QueryType::Factual).await.samples, 0);
+        
+        let mut rx = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        
+        let mut last_progress = 0.0;
+        while let Some(chunk) = rx.recv().await {
+            assert!(chunk.metadata.progress >= last_progress);
+            last_progress = chunk.metadata.progress;
+            if chunk.is_final {
+                assert_eq!(chunk.metadata.progress, 1.0);
+                assert_eq!(chunk.metadata.eta_ms, 0);
+                break;
+            }
+        }
+        
+        // History is recorded once the stream task finishes
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let estimate = streaming.estimate_progress(&QueryType::Factual).await;
+        assert_eq!(estimate.samples, 1);
+        assert_eq!(estimate.expected_steps, 4);
+    }
+
+    #[tokio::test]
+    async fn test_batch_profile_uses_large_chunks() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let streaming = StreamingInference::new(StreamConfig::default(), reasoning, cache);
+        let mut rx = streaming.stream_inference_with_profile(
+            "Test query",
+            QueryType::Factual,
+            StreamProfile::Batch,
+        ).await.unwrap();
+        
+        // The whole answer fits in one batch chunk
+        let chunk = rx.recv().await.unwrap();
+        assert!(chunk.is_final);
+        assert!(chunk.metadata.reasoning_steps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_response_language_reaches_reasoning() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let streaming = StreamingInference::new(StreamConfig::default(), reasoning, cache);
+        let options = StreamOptions {
+            profile: Some(StreamProfile::Verbose),
+            response_language: Some("id".to_string()),
+        };
+        let mut rx = streaming.stream_inference_with_options("What is a graph?", QueryType::Factual, options)
+            .await
+            .unwrap();
+        
+        let first = rx.recv().await.unwrap();
+        assert!(first.metadata.reasoning_steps[1].input.contains("[Respond in language: id]"));
+    }
+
+    #[test]
+    fn test_chunks_split_on_char_boundaries() {
+        let text = "Jawaban: grafik ß→日本語 ✓";
+        for chunk_size in 1..=8 {
+            let chunks = split_chunks(text, chunk_size);
+            assert_eq!(chunks.concat(), text);
+            // Only a character wider than the chunk size may exceed it
+            assert!(chunks.iter().all(|c| c.len() <= chunk_size || c.chars().count() == 1));
+        }
+        assert_eq!(split_chunks("日本", 4), vec!["日", "本"]);
+        assert!(split_chunks("", 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_final_chunk_carries_checkpoint() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let config = StreamConfig {
+            chunk_delay_ms: 1,
+            checkpoint_interval: 2,
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        let mut rx = streaming.stream_inference("Test query", QueryType::Reasoning).await.unwrap();
+        
+        let mut content = String::new();
+        while let Some(chunk) = rx.recv().await {
+            content.push_str(&chunk.content);
+            if chunk.chunk_id % 2 == 1 || chunk.is_final {
+                let checkpoint = chunk.metadata.checkpoint.expect("checkpoint due");
+                assert_eq!(checkpoint.content_offset, content.len());
+                assert_eq!(checkpoint.last_completed_step, 3);
+                assert_eq!(chunk.metadata.reasoning_steps.len(), if chunk.chunk_id == 0 { 4 } else { 0 });
+            } else {
+                assert!(chunk.metadata.checkpoint.is_none());
+            }
+            if chunk.is_final {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_final_chunk_carries_contention_profile() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let streaming = StreamingInference::new(StreamConfig::default(), reasoning, cache);
+        let mut rx = streaming.stream_inference("Test query", QueryType::Reasoning).await.unwrap();
+        
+        let mut chunks = 0;
+        while let Some(chunk) = rx.recv().await {
+            chunks += 1;
+            if chunk.is_final {
+                let profile = chunk.metadata.contention.expect("profile on final chunk");
+                assert_eq!(profile.sites["stream.send"].count, chunks - 1);
+                break;
+            }
+            assert!(chunk.metadata.contention.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_queries_share_one_chain() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        
+        let config = StreamConfig {
+            chunk_delay_ms: 5,
+            deduplicate_queries: true,
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        let first = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        let second = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        
+        let final_chunk = |mut rx: mpsc::Receiver<StreamChunk>| async move {
+            let mut content = String::new();
+            while let Some(chunk) = rx.recv().await {
+                content.push_str(&chunk.content);
+                if chunk.is_final {
+                    return (content, chunk.metadata.checkpoint.unwrap().chain_id);
+                }
+            }
+            panic!("stream ended without a final chunk");
+        };
+        let (a, b) = tokio::join!(final_chunk(first), final_chunk(second));
+        assert_eq!(a, b);
+        
+        // Finished streams are retired; the next request reasons afresh
+        let third = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        assert_ne!(final_chunk(third).await.1, a.1);
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_partial() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        let config = StreamConfig {
+            chunk_delay_ms: 0,
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        
+        let rx = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        let result = StreamingInference::collect_stream_partial(rx).await;
+        assert!(result.complete);
+        assert!(!result.value.is_empty());
+        
+        // A stream whose producer goes away mid-answer keeps what arrived
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(StreamChunk {
+            chunk_id: 0,
+            content: "Partial".to_string(),
+            is_final: false,
+            metadata: ChunkMetadata::default(),
+        }).await.unwrap();
+        drop(tx);
+        let result = StreamingInference::collect_stream_partial(rx).await;
+        assert_eq!(result.value, "Partial");
+        assert!(!result.complete);
+        assert!(result.has_error(ApiErrorKind::Interrupted));
+    }
+
+    #[derive(Debug)]
+    struct Blank;
+
+    impl PostProcessStage for Blank {
+        fn name(&self) -> &str {
+            "blank"
+        }
+
+        fn process(&self, _answer: String, _context: &PostProcessContext) -> String {
+            String::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_answer_still_ends_with_final_chunk() {
+        let reasoning = Arc::new(GLMReasoning::new(10));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        let config = StreamConfig {
+            chunk_delay_ms: 0,
+            post_processor: Some(Arc::new(AnswerPostProcessor::new().with_stage(Arc::new(Blank)))),
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        
+        let mut rx = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        let chunk = rx.recv().await.unwrap();
+        assert!(chunk.is_final);
+        assert!(chunk.content.is_empty());
+        assert_eq!(chunk.metadata.checkpoint.unwrap().content_offset, 0);
+        assert!(rx.recv().await.is_none());
+        
+        let rx = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        let result = StreamingInference::collect_stream_partial(rx).await;
+        assert!(result.complete);
+        assert!(result.value.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_final_chunk_warns_when_answer_goes_stale() {
+        let versions = Arc::new(VertexVersions::new());
+        let reasoning = Arc::new(GLMReasoning::new(10).with_vertex_versions(versions.clone()));
+        let cache = Arc::new(VertexCentricCache::new(1000));
+        let config = StreamConfig {
+            chunk_size: 10,
+            chunk_delay_ms: 5,
+            ..StreamConfig::default()
+        };
+        let streaming = StreamingInference::new(config, reasoning, cache);
+        
+        let mut rx = streaming.stream_inference("Test query", QueryType::Factual).await.unwrap();
+        let first = rx.recv().await.unwrap();
+        assert!(!first.is_final);
+        versions.record_change(["node_0"]);
+        
+        let mut last = first;
+        while let Some(chunk) = rx.recv().await {
+            last = chunk;
+        }
+        assert!(last.is_final);
+        assert_eq!(last.metadata.staleness.unwrap().changed_vertices, vec!["node_0"]);
+    }
 }
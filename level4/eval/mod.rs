@@ -0,0 +1,8 @@
+// -*- coding: utf-8 -*-
+//! Evaluation
+//! 
+//! Tooling for checking pipeline behavior across changes.
+
+pub mod snapshot;
+
+pub use snapshot::{SnapshotStore, SnapshotMode, SnapshotOutcome, DEFAULT_REDACTIONS};
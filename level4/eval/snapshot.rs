@@ -0,0 +1,242 @@
+// -*- coding: utf-8 -*-
+//! Snapshot Testing
+//!
+//! Serializes chains, generated code and execution outputs to reviewable
+//! snapshot files and reports drift from the approved versions.
+
+use crate::error::Result;
+use crate::level4::agents::code_patch::{line_ops, LineOp};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Fields that change on every run and are masked before comparison
+pub const DEFAULT_REDACTIONS: &[&str] = &["chain_id", "code_id", "execution_time_ms", "timestamp_ms"];
+
+const REDACTED: &str = "[redacted]";
+
+/// What to do with missing and drifted snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotMode {
+    /// Record missing snapshots; drift fails
+    New,
+    /// Missing snapshots and drift both fail, e.g. on CI
+    Strict,
+    /// Accept current values, overwriting drifted snapshots
+    Overwrite,
+}
+
+impl SnapshotMode {
+    /// Read `LEVEL4_SNAPSHOTS` (`new`, `strict` or `overwrite`); defaults to
+    /// `Strict` when `CI` is set and `New` otherwise
+    pub fn from_env() -> Self {
+        match std::env::var("LEVEL4_SNAPSHOTS").as_deref() {
+            Ok("strict") => Self::Strict,
+            Ok("overwrite") => Self::Overwrite,
+            Ok("new") => Self::New,
+            _ if std::env::var_os("CI").is_some() => Self::Strict,
+            _ => Self::New,
+        }
+    }
+}
+
+/// Result of comparing a value with its snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    Matched,
+    /// No snapshot existed and one was recorded
+    Created,
+    /// Drifted snapshot replaced in `Overwrite` mode
+    Updated,
+    /// No snapshot exists and `Strict` mode forbids recording one
+    Missing { pending: PathBuf },
+    /// Value differs from the approved snapshot
+    ///
+    /// The new value is written next to it as `<name>.snap.new` for review;
+    /// renaming it over the `.snap` file approves it.
+    Drifted { diff: String, pending: PathBuf },
+}
+
+impl SnapshotOutcome {
+    pub fn passed(&self) -> bool {
+        matches!(self, Self::Matched | Self::Created | Self::Updated)
+    }
+}
+
+/// Directory of approved snapshots, one pretty-printed JSON file per name
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+    mode: SnapshotMode,
+    redactions: Vec<String>,
+}
+
+impl SnapshotStore {
+    /// Store in `dir`, in the mode chosen by `SnapshotMode::from_env`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: SnapshotMode::from_env(),
+            redactions: DEFAULT_REDACTIONS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: SnapshotMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Also mask `field` wherever it appears, at any depth
+    pub fn redact(mut self, field: &str) -> Self {
+        self.redactions.push(field.to_string());
+        self
+    }
+
+    /// Compare `value` with the snapshot called `name`, recording or
+    /// replacing it as the mode allows
+    pub fn check<T: Serialize>(&self, name: &str, value: &T) -> Result<SnapshotOutcome> {
+        let mut value = serde_json::to_value(value)?;
+        self.mask(&mut value);
+        let rendered = format!("{}\n", serde_json::to_string_pretty(&value)?);
+
+        let path = self.path(name, "snap");
+        let pending = self.path(name, "snap.new");
+        std::fs::create_dir_all(&self.dir)?;
+
+        let approved = match std::fs::read_to_string(&path) {
+            Ok(approved) => approved,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if self.mode == SnapshotMode::Strict {
+                    std::fs::write(&pending, &rendered)?;
+                    return Ok(SnapshotOutcome::Missing { pending });
+                }
+                std::fs::write(&path, &rendered)?;
+                return Ok(SnapshotOutcome::Created);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if approved == rendered {
+            Self::discard(&pending)?;
+            return Ok(SnapshotOutcome::Matched);
+        }
+        if self.mode == SnapshotMode::Overwrite {
+            std::fs::write(&path, &rendered)?;
+            Self::discard(&pending)?;
+            return Ok(SnapshotOutcome::Updated);
+        }
+        std::fs::write(&pending, &rendered)?;
+        Ok(SnapshotOutcome::Drifted {
+            diff: line_diff(&approved, &rendered),
+            pending,
+        })
+    }
+
+    /// `check`, panicking with the diff on failure; for use in tests
+    pub fn assert_snapshot<T: Serialize>(&self, name: &str, value: &T) {
+        match self.check(name, value) {
+            Ok(SnapshotOutcome::Drifted { diff, pending }) => {
+                panic!("snapshot '{}' drifted (new value in {}):\n{}", name, pending.display(), diff)
+            }
+            Ok(SnapshotOutcome::Missing { pending }) => {
+                panic!("snapshot '{}' is missing (new value in {})", name, pending.display())
+            }
+            Ok(_) => {}
+            Err(e) => panic!("snapshot '{}' could not be checked: {:?}", name, e),
+        }
+    }
+
+    fn path(&self, name: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, extension))
+    }
+
+    fn discard(path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn mask(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (field, inner) in fields.iter_mut() {
+                    if self.redactions.iter().any(|r| r == field) {
+                        *inner = Value::String(REDACTED.to_string());
+                    } else {
+                        self.mask(inner);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.mask(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Line diff of `old` against `new`, with `-`/`+` markers on changed lines
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let mut diff = String::new();
+    for op in line_ops(&old, &new) {
+        match op {
+            LineOp::Keep(i, _) => diff.push_str(&format!("  {}\n", old[i])),
+            LineOp::Delete(i) => diff.push_str(&format!("- {}\n", old[i])),
+            LineOp::Insert(j) => diff.push_str(&format!("+ {}\n", new[j])),
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level4::agents::{CodeGenerator, GLMReasoning, QueryType};
+
+    fn temp_store(mode: SnapshotMode) -> SnapshotStore {
+        let dir = std::env::temp_dir().join(format!("snapshots_{}", uuid::Uuid::new_v4()));
+        SnapshotStore::new(dir).with_mode(mode)
+    }
+
+    #[tokio::test]
+    async fn test_chain_snapshot_is_stable_across_runs() {
+        let store = temp_store(SnapshotMode::New);
+        let reasoning = GLMReasoning::new(10);
+
+        let first = reasoning.reason("Test query", QueryType::Factual).await.unwrap();
+        assert_eq!(store.check("chain", &first).unwrap(), SnapshotOutcome::Created);
+        let second = reasoning.reason("Test query", QueryType::Factual).await.unwrap();
+        assert_eq!(store.check("chain", &second).unwrap(), SnapshotOutcome::Matched);
+
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_drift_is_reported_and_pending() {
+        let store = temp_store(SnapshotMode::New);
+        let generator = CodeGenerator::new();
+        let mut code = generator.generate("implement binary search").unwrap();
+        store.assert_snapshot("code", &code);
+
+        code.dependencies.push("rayon".to_string());
+        match store.check("code", &code).unwrap() {
+            SnapshotOutcome::Drifted { diff, pending } => {
+                assert!(diff.contains("+     \"rayon\""));
+                assert!(pending.exists());
+            }
+            other => panic!("expected drift, got {:?}", other),
+        }
+
+        let overwrite = store.clone().with_mode(SnapshotMode::Overwrite);
+        assert_eq!(overwrite.check("code", &code).unwrap(), SnapshotOutcome::Updated);
+        assert_eq!(store.check("code", &code).unwrap(), SnapshotOutcome::Matched);
+        assert!(!store.path("code", "snap.new").exists());
+
+        let strict = store.clone().with_mode(SnapshotMode::Strict);
+        assert!(!strict.check("other", &code).unwrap().passed());
+
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+}
@@ -0,0 +1,262 @@
+// -*- coding: utf-8 -*-
+//! Community Detection
+//!
+//! Label-propagation communities used to scope retrieval to the part of the
+//! graph a query's entities live in.
+
+use crate::error::Result;
+use crate::level4::agents::{LocalityHint, NeighborProvider};
+use crate::level4::graph::ml::SplitMix64;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// How far retrieval may reach beyond the entities' own communities
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RetrievalScope {
+    /// Adjacent communities added after the entities' own, most connected first
+    pub breadth: usize,
+    /// Cap on scoped vertices; the entities themselves always fit
+    pub max_vertices: Option<usize>,
+}
+
+impl Default for RetrievalScope {
+    fn default() -> Self {
+        Self {
+            breadth: 1,
+            max_vertices: Some(256),
+        }
+    }
+}
+
+/// Vertices grouped into densely connected communities
+#[derive(Debug, Clone, Default)]
+pub struct CommunityIndex {
+    membership: HashMap<String, usize>,
+    members: Vec<Vec<String>>,
+    /// Edge counts between communities, keyed by the smaller id first
+    links: HashMap<(usize, usize), usize>,
+}
+
+impl CommunityIndex {
+    /// Detect communities by label propagation over `vertices` and their neighbors
+    ///
+    /// Each round visits vertices in a seeded random order and moves each to
+    /// the label most common among its neighbors, keeping its own on ties.
+    /// Stops once a round changes nothing or after `max_rounds`. Results
+    /// depend on `seed`; weakly separated communities may come out merged.
+    pub async fn detect(
+        vertices: &[String],
+        provider: &dyn NeighborProvider,
+        max_rounds: usize,
+        seed: u64,
+    ) -> Result<Self> {
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut intern = |vertex_id: &str| -> usize {
+            *index.entry(vertex_id.to_string()).or_insert_with(|| {
+                names.push(vertex_id.to_string());
+                names.len() - 1
+            })
+        };
+
+        // Edges are treated as undirected
+        let mut adjacency: Vec<HashSet<usize>> = Vec::new();
+        for vertex_id in vertices {
+            let v = intern(vertex_id);
+            for neighbor in provider.neighbors(vertex_id).await? {
+                let n = intern(&neighbor);
+                adjacency.resize_with(adjacency.len().max(v.max(n) + 1), HashSet::new);
+                if n != v {
+                    adjacency[v].insert(n);
+                    adjacency[n].insert(v);
+                }
+            }
+        }
+        adjacency.resize_with(names.len(), HashSet::new);
+        let adjacency: Vec<Vec<usize>> = adjacency.into_iter()
+            .map(|set| {
+                let mut list: Vec<usize> = set.into_iter().collect();
+                list.sort_unstable();
+                list
+            })
+            .collect();
+
+        let mut labels: Vec<usize> = (0..names.len()).collect();
+        let mut rng = SplitMix64(seed);
+        let mut order: Vec<usize> = (0..names.len()).collect();
+        for _ in 0..max_rounds {
+            // Fisher-Yates shuffle
+            for i in (1..order.len()).rev() {
+                order.swap(i, rng.below(i + 1));
+            }
+
+            let mut changed = false;
+            for &v in &order {
+                let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+                for &n in &adjacency[v] {
+                    *counts.entry(labels[n]).or_insert(0) += 1;
+                }
+                let Some(&best) = counts.values().max() else {
+                    continue;
+                };
+                if counts.get(&labels[v]) == Some(&best) {
+                    continue;
+                }
+                let tied: Vec<usize> = counts.iter()
+                    .filter(|&(_, &count)| count == best)
+                    .map(|(&label, _)| label)
+                    .collect();
+                labels[v] = tied[rng.below(tied.len())];
+                changed = true;
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Renumber communities densely, in order of first appearance
+        let mut community_ids: HashMap<usize, usize> = HashMap::new();
+        let mut communities = Self::default();
+        for (v, name) in names.iter().enumerate() {
+            let next = community_ids.len();
+            let community = *community_ids.entry(labels[v]).or_insert(next);
+            if community == communities.members.len() {
+                communities.members.push(Vec::new());
+            }
+            communities.members[community].push(name.clone());
+            communities.membership.insert(name.clone(), community);
+        }
+        for (v, neighbors) in adjacency.iter().enumerate() {
+            for &n in neighbors.iter().filter(|&&n| n > v) {
+                let (a, b) = (community_ids[&labels[v]], community_ids[&labels[n]]);
+                if a != b {
+                    *communities.links.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                }
+            }
+        }
+
+        Ok(communities)
+    }
+
+    pub fn community_of(&self, vertex_id: &str) -> Option<usize> {
+        self.membership.get(vertex_id).copied()
+    }
+
+    pub fn members(&self, community: usize) -> &[String] {
+        self.members.get(community).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn community_count(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Vertices retrieval should consider for `entities`, most relevant first
+    ///
+    /// The entities come first, then the rest of their communities, then up to
+    /// `scope.breadth` adjacent communities. Entities outside every community
+    /// are kept; callers fall back to global retrieval when the scope runs dry.
+    pub fn scope(&self, entities: &[String], scope: &RetrievalScope) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut scoped: Vec<String> = Vec::new();
+        for entity in entities {
+            if seen.insert(entity) {
+                scoped.push(entity.clone());
+            }
+        }
+        let limit = scope.max_vertices.unwrap_or(usize::MAX).max(scoped.len());
+
+        let mut home: Vec<usize> = entities.iter().filter_map(|e| self.community_of(e)).collect();
+        let mut unique = HashSet::new();
+        home.retain(|c| unique.insert(*c));
+
+        // Adjacent communities ranked by edges into the home communities
+        let mut adjacent: HashMap<usize, usize> = HashMap::new();
+        for (&(a, b), &edges) in &self.links {
+            match (home.contains(&a), home.contains(&b)) {
+                (true, false) => *adjacent.entry(b).or_insert(0) += edges,
+                (false, true) => *adjacent.entry(a).or_insert(0) += edges,
+                _ => {}
+            }
+        }
+        let mut adjacent: Vec<(usize, usize)> = adjacent.into_iter().collect();
+        adjacent.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+
+        let communities = home.iter().copied()
+            .chain(adjacent.into_iter().take(scope.breadth).map(|(c, _)| c));
+        for community in communities {
+            for vertex_id in self.members(community) {
+                if scoped.len() >= limit {
+                    return scoped;
+                }
+                if seen.insert(vertex_id) {
+                    scoped.push(vertex_id.clone());
+                }
+            }
+        }
+        scoped
+    }
+
+    /// Cache hint pinning the entities and prefetching the rest of their scope
+    pub fn locality_hint(&self, entities: &[String], scope: &RetrievalScope) -> LocalityHint {
+        let scoped = self.scope(entities, scope);
+        let frontier: HashSet<&String> = entities.iter().collect();
+        LocalityHint {
+            frontier: entities.to_vec(),
+            neighbors: scoped.into_iter().filter(|v| !frontier.contains(v)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three 4-cliques in a chain: a - b - c
+    fn three_communities() -> HashMap<String, Vec<String>> {
+        let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+        let mut link = |a: &str, b: &str| {
+            graph.entry(a.to_string()).or_default().push(b.to_string());
+            graph.entry(b.to_string()).or_default().push(a.to_string());
+        };
+        for side in ["a", "b", "c"] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    link(&format!("{}{}", side, i), &format!("{}{}", side, j));
+                }
+            }
+        }
+        link("a3", "b0");
+        link("b3", "c0");
+        graph
+    }
+
+    #[tokio::test]
+    async fn test_scope_prefers_own_community() {
+        let graph = three_communities();
+        let mut vertices: Vec<String> = graph.keys().cloned().collect();
+        vertices.sort();
+        let index = CommunityIndex::detect(&vertices, &graph, 20, 7).await.unwrap();
+
+        assert_eq!(index.community_count(), 3);
+        assert_eq!(index.community_of("a0"), index.community_of("a3"));
+        assert_ne!(index.community_of("a0"), index.community_of("b0"));
+
+        let entities = vec!["a1".to_string()];
+        let narrow = index.scope(&entities, &RetrievalScope { breadth: 0, max_vertices: None });
+        assert_eq!(narrow[0], "a1");
+        assert_eq!(narrow.len(), 4);
+        assert!(narrow.iter().all(|v| v.starts_with('a')));
+
+        // One hop out reaches b, which links to a; c does not
+        let wide = index.scope(&entities, &RetrievalScope { breadth: 1, max_vertices: None });
+        assert_eq!(wide.len(), 8);
+        assert!(wide.iter().all(|v| !v.starts_with('c')));
+
+        let capped = index.scope(&entities, &RetrievalScope { breadth: 2, max_vertices: Some(6) });
+        assert_eq!(capped.len(), 6);
+
+        let hint = index.locality_hint(&entities, &RetrievalScope { breadth: 0, max_vertices: None });
+        assert_eq!(hint.frontier, entities);
+        assert_eq!(hint.neighbors.len(), 3);
+    }
+}
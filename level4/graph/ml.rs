@@ -0,0 +1,453 @@
+// -*- coding: utf-8 -*-
+//! Graph Embedding Training
+//!
+//! DeepWalk-style vertex embeddings: uniform random walks over the graph
+//! feed a skip-gram model trained with negative sampling.
+
+use crate::error::Result;
+use crate::level4::agents::{LinkPredictor, LinkProvenance, NeighborProvider, PredictedLink, VertexCentricCache};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Walk and skip-gram hyperparameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingTrainingConfig {
+    pub dimensions: usize,
+    /// Walks started from each root passed to `train`
+    pub walks_per_vertex: usize,
+    /// Vertices per walk, including the root
+    pub walk_length: usize,
+    /// Context vertices considered on each side of a walk position
+    pub window: usize,
+    /// Uniformly sampled vertices contrasted against each context pair
+    pub negative_samples: usize,
+    pub epochs: usize,
+    /// Initial rate, decayed linearly over each `train` call
+    pub learning_rate: f64,
+    pub seed: u64,
+    /// Key embeddings are stored under by `write_to_cache`
+    pub cache_key: String,
+    /// Random vertex pairs scored to calibrate link probabilities
+    pub calibration_samples: usize,
+}
+
+impl Default for EmbeddingTrainingConfig {
+    fn default() -> Self {
+        Self {
+            dimensions: 64,
+            walks_per_vertex: 10,
+            walk_length: 20,
+            window: 5,
+            negative_samples: 5,
+            epochs: 1,
+            learning_rate: 0.025,
+            seed: 42,
+            cache_key: "graph_embedding".to_string(),
+            calibration_samples: 1000,
+        }
+    }
+}
+
+/// Outcome of one `train` call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrainingStats {
+    /// Vertices embedded in total
+    pub vertices: usize,
+    /// Vertices first reached by this call's walks
+    pub new_vertices: usize,
+    pub walks: usize,
+    /// Positive (center, context) pairs trained on across all epochs
+    pub pairs: usize,
+    pub duration_ms: u64,
+}
+
+/// Learns vertex embeddings from graph structure
+///
+/// Vectors persist across `train` calls: earlier vertices are refined rather
+/// than reinitialized, so as the graph grows it is enough to train on walks
+/// rooted at the new vertices. Only vectors changed since the last
+/// `write_to_cache` are written back.
+pub struct EmbeddingTrainer {
+    config: EmbeddingTrainingConfig,
+    neighbors: Arc<dyn NeighborProvider>,
+    index: HashMap<String, usize>,
+    vertices: Vec<String>,
+    /// Vertex vectors, the embeddings themselves
+    input: Vec<Vec<f64>>,
+    /// Context vectors, only used during training
+    output: Vec<Vec<f64>>,
+    dirty: BTreeSet<usize>,
+    /// Training time per updated vertex in the last run, recorded as cache cost
+    cost_per_vertex: f64,
+    rng: SplitMix64,
+}
+
+impl EmbeddingTrainer {
+    pub fn new(config: EmbeddingTrainingConfig, neighbors: Arc<dyn NeighborProvider>) -> Self {
+        let rng = SplitMix64(config.seed);
+        Self {
+            config,
+            neighbors,
+            index: HashMap::new(),
+            vertices: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+            dirty: BTreeSet::new(),
+            cost_per_vertex: 0.0,
+            rng,
+        }
+    }
+
+    /// Train on random walks rooted at `roots`
+    ///
+    /// Every vertex a walk reaches is embedded, not only the roots.
+    pub async fn train(&mut self, roots: &[String]) -> Result<TrainingStats> {
+        let start = std::time::Instant::now();
+        let known = self.vertices.len();
+
+        // Adjacency is fetched once per vertex and call, so updates see graph changes
+        let mut adjacency = HashMap::new();
+        let mut walks = Vec::new();
+        for _ in 0..self.config.walks_per_vertex {
+            for root in roots {
+                walks.push(self.walk(root, &mut adjacency).await?);
+            }
+        }
+
+        let window = self.config.window;
+        let planned = walks.iter().map(|w| Self::pair_count(w.len(), window)).sum::<usize>()
+            * self.config.epochs;
+        let mut pairs = 0;
+        for _ in 0..self.config.epochs {
+            for walk in &walks {
+                for (i, &center) in walk.iter().enumerate() {
+                    let context = i.saturating_sub(window)..(i + window + 1).min(walk.len());
+                    for j in context.filter(|&j| j != i) {
+                        let progress = pairs as f64 / planned.max(1) as f64;
+                        let rate = self.config.learning_rate * (1.0 - progress).max(1e-4);
+                        self.train_pair(center, walk[j], rate);
+                        pairs += 1;
+                    }
+                }
+            }
+        }
+
+        let updated = walks.iter().flatten().copied().collect::<BTreeSet<_>>();
+        let duration = start.elapsed();
+        if !updated.is_empty() {
+            self.cost_per_vertex = duration.as_secs_f64() / updated.len() as f64;
+        }
+        self.dirty.extend(updated);
+
+        Ok(TrainingStats {
+            vertices: self.vertices.len(),
+            new_vertices: self.vertices.len() - known,
+            walks: walks.len(),
+            pairs,
+            duration_ms: duration.as_millis() as u64,
+        })
+    }
+
+    /// Learned embedding for a vertex
+    pub fn embedding(&self, vertex_id: &str) -> Option<&[f64]> {
+        self.index.get(vertex_id).map(|&i| self.input[i].as_slice())
+    }
+
+    /// All embedded vertices with their embeddings
+    pub fn embeddings(&self) -> impl Iterator<Item = (&str, &[f64])> {
+        self.vertices.iter().map(String::as_str).zip(self.input.iter().map(Vec::as_slice))
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Store embeddings changed since the last write under `config.cache_key`
+    ///
+    /// Returns the number of entries written.
+    pub async fn write_to_cache(&mut self, cache: &VertexCentricCache) -> Result<usize> {
+        let items: Vec<_> = self.dirty.iter()
+            .map(|&i| (
+                self.vertices[i].as_str(),
+                self.config.cache_key.as_str(),
+                self.input[i].clone(),
+                self.cost_per_vertex,
+            ))
+            .collect();
+        let written = items.len();
+        if written > 0 {
+            cache.put_many(items).await?;
+        }
+        self.dirty.clear();
+        Ok(written)
+    }
+
+    /// Up to `k` vertices most plausibly linked to `vertex_id` but not yet its neighbors
+    ///
+    /// Candidates are ranked by embedding dot product. Random vertex pairs are
+    /// almost all unlinked, so they serve as negative samples: a link's
+    /// `probability` is the share of sampled pairs scoring below it.
+    pub async fn predict_links(&self, vertex_id: &str, k: usize) -> Result<Vec<PredictedLink>> {
+        let Some(&source) = self.index.get(vertex_id) else {
+            return Ok(Vec::new());
+        };
+        let linked: HashSet<String> = self.neighbors.neighbors(vertex_id).await?.into_iter().collect();
+
+        let mut candidates: Vec<(usize, f64)> = (0..self.vertices.len())
+            .filter(|&target| target != source && !linked.contains(&self.vertices[target]))
+            .map(|target| (target, dot(&self.input[source], &self.input[target])))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.truncate(k);
+
+        let negatives = self.negative_scores();
+        Ok(candidates.into_iter()
+            .map(|(target, score)| PredictedLink {
+                source: vertex_id.to_string(),
+                target: self.vertices[target].clone(),
+                score,
+                probability: if negatives.is_empty() {
+                    0.0
+                } else {
+                    negatives.partition_point(|&s| s < score) as f64 / negatives.len() as f64
+                },
+                provenance: LinkProvenance::Predicted,
+            })
+            .collect())
+    }
+
+    /// Sorted scores of random vertex pairs, drawn from a fixed seed so
+    /// repeated predictions agree
+    fn negative_scores(&self) -> Vec<f64> {
+        let n = self.vertices.len();
+        if n < 2 {
+            return Vec::new();
+        }
+
+        let mut rng = SplitMix64(self.config.seed.rotate_left(32));
+        let mut scores: Vec<f64> = (0..self.config.calibration_samples)
+            .filter_map(|_| {
+                let (a, b) = (rng.below(n), rng.below(n));
+                (a != b).then(|| dot(&self.input[a], &self.input[b]))
+            })
+            .collect();
+        scores.sort_by(f64::total_cmp);
+        scores
+    }
+
+    async fn walk(&mut self, root: &str, adjacency: &mut HashMap<usize, Vec<usize>>) -> Result<Vec<usize>> {
+        let mut current = self.intern(root);
+        let mut walk = vec![current];
+
+        while walk.len() < self.config.walk_length {
+            if !adjacency.contains_key(&current) {
+                let provider = self.neighbors.clone();
+                let neighbors = provider.neighbors(&self.vertices[current]).await?;
+                let ids = neighbors.iter().map(|n| self.intern(n)).collect();
+                adjacency.insert(current, ids);
+            }
+            let next = &adjacency[&current];
+            if next.is_empty() {
+                break;
+            }
+            current = next[self.rng.below(next.len())];
+            walk.push(current);
+        }
+
+        Ok(walk)
+    }
+
+    fn intern(&mut self, vertex_id: &str) -> usize {
+        if let Some(&i) = self.index.get(vertex_id) {
+            return i;
+        }
+
+        let dimensions = self.config.dimensions.max(1);
+        let scale = 1.0 / dimensions as f64;
+        let vector = (0..dimensions).map(|_| (self.rng.unit() - 0.5) * scale).collect();
+
+        let i = self.vertices.len();
+        self.vertices.push(vertex_id.to_string());
+        self.index.insert(vertex_id.to_string(), i);
+        self.input.push(vector);
+        self.output.push(vec![0.0; dimensions]);
+        i
+    }
+
+    /// One skip-gram step: pull `context` toward `center`, push sampled negatives away
+    fn train_pair(&mut self, center: usize, context: usize, rate: f64) {
+        let mut gradient = vec![0.0; self.input[center].len()];
+        self.update_output(center, context, 1.0, rate, &mut gradient);
+        for _ in 0..self.config.negative_samples {
+            let negative = self.rng.below(self.vertices.len());
+            if negative != center && negative != context {
+                self.update_output(center, negative, 0.0, rate, &mut gradient);
+            }
+        }
+        for (weight, delta) in self.input[center].iter_mut().zip(gradient) {
+            *weight += delta;
+        }
+    }
+
+    fn update_output(&mut self, center: usize, target: usize, label: f64, rate: f64, gradient: &mut [f64]) {
+        let input = &self.input[center];
+        let output = &mut self.output[target];
+        let step = rate * (label - sigmoid(dot(input, output)));
+        for ((delta, weight), x) in gradient.iter_mut().zip(output.iter_mut()).zip(input) {
+            *delta += step * *weight;
+            *weight += step * x;
+        }
+    }
+
+    fn pair_count(len: usize, window: usize) -> usize {
+        (0..len).map(|i| i.min(window) + (len - 1 - i).min(window)).sum()
+    }
+}
+
+/// Links are proposed by a shared trainer; training takes the write lock
+#[async_trait]
+impl LinkPredictor for RwLock<EmbeddingTrainer> {
+    async fn predict_links(&self, vertex_id: &str, k: usize) -> Result<Vec<PredictedLink>> {
+        self.read().await.predict_links(vertex_id, k).await
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x.clamp(-30.0, 30.0)).exp())
+}
+
+/// Small seeded generator so training and detection are reproducible
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f64], b: &[f64]) -> f64 {
+        let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        dot / (norm(a) * norm(b))
+    }
+
+    /// Two 4-cliques joined by a single edge
+    fn two_communities() -> HashMap<String, Vec<String>> {
+        let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+        let mut link = |a: &str, b: &str| {
+            graph.entry(a.to_string()).or_default().push(b.to_string());
+            graph.entry(b.to_string()).or_default().push(a.to_string());
+        };
+        for side in ["a", "b"] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    link(&format!("{}{}", side, i), &format!("{}{}", side, j));
+                }
+            }
+        }
+        link("a3", "b0");
+        graph
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_separate_communities() {
+        let config = EmbeddingTrainingConfig {
+            dimensions: 16,
+            walks_per_vertex: 20,
+            walk_length: 10,
+            window: 2,
+            negative_samples: 3,
+            epochs: 3,
+            ..EmbeddingTrainingConfig::default()
+        };
+        let mut trainer = EmbeddingTrainer::new(config, Arc::new(two_communities()));
+
+        let stats = trainer.train(&["a0".to_string()]).await.unwrap();
+        assert_eq!(stats.vertices, 8);
+        assert_eq!(stats.new_vertices, 8);
+
+        let roots: Vec<String> = two_communities().into_keys().collect();
+        trainer.train(&roots).await.unwrap();
+        let same = cosine(trainer.embedding("a0").unwrap(), trainer.embedding("a1").unwrap());
+        let across = cosine(trainer.embedding("a0").unwrap(), trainer.embedding("b2").unwrap());
+        assert!(same > across, "same community {} vs across {}", same, across);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_training_writes_only_updated_vertices() {
+        let mut graph = two_communities();
+        let config = EmbeddingTrainingConfig {
+            dimensions: 8,
+            walks_per_vertex: 2,
+            walk_length: 4,
+            ..EmbeddingTrainingConfig::default()
+        };
+        let mut trainer = EmbeddingTrainer::new(config.clone(), Arc::new(graph.clone()));
+        let cache = VertexCentricCache::new(100);
+
+        let roots: Vec<String> = graph.keys().cloned().collect();
+        trainer.train(&roots).await.unwrap();
+        assert_eq!(trainer.write_to_cache(&cache).await.unwrap(), 8);
+        assert!(cache.get("a0", &config.cache_key).await.is_some());
+
+        // A leaf joining the graph only disturbs what its walks reach
+        graph.insert("c0".to_string(), vec![]);
+        trainer.neighbors = Arc::new(graph);
+        let stats = trainer.train(&["c0".to_string()]).await.unwrap();
+        assert_eq!(stats.new_vertices, 1);
+        assert_eq!(trainer.write_to_cache(&cache).await.unwrap(), 1);
+        assert_eq!(cache.get("c0", &config.cache_key).await.as_deref(), trainer.embedding("c0"));
+    }
+
+    #[tokio::test]
+    async fn test_predict_links_recovers_missing_edge() {
+        let mut graph = two_communities();
+        graph.get_mut("a0").unwrap().retain(|v| v != "a1");
+        graph.get_mut("a1").unwrap().retain(|v| v != "a0");
+        let config = EmbeddingTrainingConfig {
+            dimensions: 16,
+            walks_per_vertex: 20,
+            walk_length: 10,
+            window: 2,
+            negative_samples: 3,
+            epochs: 3,
+            ..EmbeddingTrainingConfig::default()
+        };
+        let roots: Vec<String> = graph.keys().cloned().collect();
+        let mut trainer = EmbeddingTrainer::new(config, Arc::new(graph));
+        trainer.train(&roots).await.unwrap();
+
+        let predictor: Arc<dyn LinkPredictor> = Arc::new(RwLock::new(trainer));
+        let links = predictor.predict_links("a0", 2).await.unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target, "a1");
+        assert_eq!(links[0].provenance, LinkProvenance::Predicted);
+        assert!(links[0].score >= links[1].score);
+        assert!(links[0].probability > 0.5);
+        // Existing neighbors are never proposed
+        assert!(links.iter().all(|l| !["a2", "a3"].contains(&l.target.as_str())));
+    }
+}
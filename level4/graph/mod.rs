@@ -0,0 +1,10 @@
+// -*- coding: utf-8 -*-
+//! Graph Learning
+//!
+//! Models learned from the knowledge graph's structure.
+
+pub mod ml;
+pub mod community;
+
+pub use ml::{EmbeddingTrainer, EmbeddingTrainingConfig, TrainingStats};
+pub use community::{CommunityIndex, RetrievalScope};